@@ -1,5 +1,5 @@
 use notify_rust::{Hint, Notification, NotificationHandle};
-use rog_aura::AuraEffect;
+use rog_aura::{AuraEffect, LedBrightness};
 use rog_dbus::{DbusProxies, Signals};
 use rog_profiles::Profile;
 use std::error::Error;
@@ -60,6 +60,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         if let Ok(data) = signals.led_mode.try_recv() {
             notify!(do_led_notif, last_notification, &data);
         }
+        if let Ok(data) = signals.led_brightness.try_recv() {
+            notify!(do_brightness_notif, last_notification, &data);
+        }
         if let Ok(data) = signals.profile.try_recv() {
             notify!(do_thermal_notif, last_notification, &data);
         }
@@ -98,6 +101,15 @@ fn do_led_notif(ledmode: &AuraEffect) -> Result<NotificationHandle, notify_rust:
     ))
 }
 
+fn do_brightness_notif(
+    brightness: &LedBrightness,
+) -> Result<NotificationHandle, notify_rust::error::Error> {
+    base_notification!(&format!(
+        "Keyboard LED brightness changed to {:?}",
+        brightness
+    ))
+}
+
 fn do_charge_notif(limit: &u8) -> Result<NotificationHandle, notify_rust::error::Error> {
     base_notification!(&format!("Battery charge limit changed to {}", limit))
 }
@@ -1,28 +1,23 @@
-use rog_aura::{GX502Layout, KeyColourArray, KeyLayout};
+use std::time::Duration;
+
+use rog_aura::{
+    effects::{Comet, EffectState},
+    layouts::KeyboardLayout,
+    KeyColourArray,
+};
 use rog_dbus::RogDbusClient;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (dbus, _) = RogDbusClient::new()?;
 
-    let layout = GX502Layout::default();
+    let layout = KeyboardLayout::default();
+    let mut comet = Comet::default();
 
     dbus.proxies().led().init_effect()?;
-    let rows = layout.get_rows();
-
-    let mut column = 0;
     loop {
+        comet.advance(Duration::from_millis(40), &layout);
         let mut key_colours = KeyColourArray::new();
-        for row in rows {
-            if let Some(c) = key_colours.key(row[column as usize]) {
-                *c.0 = 255;
-            };
-        }
-        if column == rows[0].len() - 1 {
-            column = 0
-        } else {
-            column += 1;
-        }
-
+        comet.paint(&layout, &mut key_colours);
         dbus.proxies().led().set_per_key(&key_colours)?;
     }
 }
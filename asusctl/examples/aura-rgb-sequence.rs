@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use rog_aura::{
+    layouts::KeyboardLayout, ActionData, Colour, Easing, Key, KeyColour, Keyframe, Sequences,
+};
+use rog_dbus::RogDbusClient;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (dbus, _) = RogDbusClient::new()?;
+
+    let layout = KeyboardLayout::default();
+    let mut seq = Sequences::new();
+    seq.looping = true;
+    for colour in [Colour(255, 0, 0), Colour(0, 255, 0), Colour(0, 0, 255)] {
+        seq.push(ActionData::Keyframe(Keyframe {
+            transition: Duration::from_millis(800),
+            hold: Duration::from_millis(200),
+            easing: Easing::EaseInOut,
+            base: Some(Colour(0, 0, 0)),
+            keys: [Key::W, Key::A, Key::S, Key::D]
+                .iter()
+                .map(|key| KeyColour { key: *key, colour })
+                .collect(),
+        }));
+    }
+
+    dbus.proxies().led().init_effect()?;
+    // set_per_key blocks long enough to pace the frames
+    for frame in seq.frames(&layout, 25) {
+        dbus.proxies().led().set_per_key(&frame)?;
+    }
+    Ok(())
+}
@@ -30,6 +30,8 @@ pub enum AnimeActions {
     Gif(AnimeGif),
     #[options(help = "display an animated diagonal/pixel-perfect GIF")]
     PixelGif(AnimeGifDiagonal),
+    #[options(help = "display a line of text, static or scrolling")]
+    Text(AnimeText),
 }
 
 #[derive(Options)]
@@ -99,3 +101,29 @@ pub struct AnimeGifDiagonal {
     )]
     pub loops: u32,
 }
+
+#[derive(Options)]
+pub struct AnimeText {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(free, help = "the text to display")]
+    pub text: Vec<String>,
+    #[options(no_short, help = "use the large font")]
+    pub large: bool,
+    #[options(
+        meta = "",
+        no_short,
+        help = "scroll the text across at this many columns a second"
+    )]
+    pub scroll: Option<f32>,
+    #[options(meta = "", default = "0", help = "row of the top of the text, 0-54")]
+    pub row: u32,
+    #[options(meta = "", default = "1.0", help = "brightness 0.0-1.0")]
+    pub bright: f32,
+    #[options(
+        meta = "",
+        default = "1",
+        help = "how many times to scroll past - 0 is infinite"
+    )]
+    pub loops: u32,
+}
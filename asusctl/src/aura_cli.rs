@@ -65,15 +65,24 @@ pub struct SingleSpeedDirection {
 pub struct SingleColour {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        help = "set the colour e.g, ff00ff, #f0f, rgb(255,0,255), hsv(300,100%,100%), magenta, 4000k"
+    )]
     pub colour: Colour,
+    #[options(
+        meta = "",
+        help = "set only one zone 1-4, for static on multizone keyboards"
+    )]
+    pub zone: AuraZone,
 }
 
 #[derive(Debug, Clone, Default, Options)]
 pub struct SingleColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(no_long, meta = "", help = "set the colour e.g, ff00ff, red, 4000k")]
     pub colour: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -83,25 +92,54 @@ pub struct SingleColourSpeed {
 pub struct TwoColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the first RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        help = "set the first colour e.g, ff00ff, rgb(255,0,255), magenta"
+    )]
     pub colour: Colour,
-    #[options(no_long, meta = "", help = "set the second RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        help = "set the second colour e.g, ff00ff, rgb(255,0,255), magenta"
+    )]
     pub colour2: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
+    #[options(
+        meta = "",
+        help = "set only one zone 1-4, for breathe on multizone keyboards"
+    )]
+    pub zone: AuraZone,
 }
 
 #[derive(Debug, Clone, Default, Options)]
 pub struct MultiColour {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour4: Colour,
 }
 
@@ -109,13 +147,29 @@ pub struct MultiColour {
 pub struct MultiColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        help = "set the colour e.g, ff00ff, red, 4000k"
+    )]
     pub colour4: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -168,6 +222,7 @@ impl From<&SingleColour> for AuraEffect {
     fn from(aura: &SingleColour) -> Self {
         Self {
             colour1: aura.colour,
+            zone: aura.zone,
             ..Default::default()
         }
     }
@@ -197,6 +252,7 @@ impl From<&TwoColourSpeed> for AuraEffect {
         Self {
             colour1: aura.colour,
             colour2: aura.colour2,
+            zone: aura.zone,
             ..Default::default()
         }
     }
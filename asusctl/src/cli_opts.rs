@@ -4,6 +4,7 @@ use crate::{
     profiles_cli::{FanCurveCommand, ProfileCommand},
 };
 use gumdrop::Options;
+use rog_aura::PowerZone;
 
 #[derive(Default, Options)]
 pub struct CliStart {
@@ -29,6 +30,8 @@ pub struct CliStart {
 pub enum CliCommand {
     #[options(help = "Set the keyboard lighting from built-in modes")]
     LedMode(LedModeCommand),
+    #[options(help = "Preview keyboard lighting in the terminal or as an image")]
+    LedPreview(LedPreviewCommand),
     #[options(help = "Set or select platform_profile")]
     Profile(ProfileCommand),
     #[options(help = "Set, select, or modify fan curves if supported")]
@@ -59,6 +62,57 @@ pub struct LedModeCommand {
         help = "set the keyboard LED suspend animation to enabled while the device is suspended"
     )]
     pub sleep_enable: Option<bool>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "zone to set --power-states for: keyboard, logo, lightbar, lid, rear-glow"
+    )]
+    pub power_zone: Option<PowerZone>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "comma separated states the zone is lit in: boot, awake, sleep, shutdown, or none"
+    )]
+    pub power_states: Option<String>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "ID of the LED device to control, as listed by --show-supported"
+    )]
+    pub device: Option<String>,
+    #[options(command)]
+    pub command: Option<SetAuraBuiltin>,
+}
+
+#[derive(Options)]
+pub struct LedPreviewCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "software effect to preview: ball, comet, pulser, reactive, ripple"
+    )]
+    pub effect: Option<String>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "per-key layout name, defaults to the builtin gx502"
+    )]
+    pub layout: Option<String>,
+    #[options(meta = "", help = "seconds to play for, default 4, at most 60")]
+    pub seconds: Option<f32>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "write the animation to a GIF file instead of the terminal"
+    )]
+    pub gif: Option<String>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "write the last frame to a PNG file instead of the terminal"
+    )]
+    pub png: Option<String>,
     #[options(command)]
     pub command: Option<SetAuraBuiltin>,
 }
@@ -1,14 +1,27 @@
+use std::fs::File;
+use std::io::Write;
 use std::process::Command;
+use std::sync::{atomic::AtomicBool, Arc};
 use std::thread::sleep;
+use std::time::Duration;
 use std::{env::args, path::Path};
 
 use gumdrop::{Opt, Options};
 
 use anime_cli::{AnimeActions, AnimeCommand};
 use profiles_cli::{FanCurveCommand, ProfileCommand};
-use rog_anime::{AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, Vec2};
-use rog_aura::{self, AuraEffect};
-use rog_dbus::RogDbusClient;
+use rog_anime::{
+    error::AnimeError, AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, FontSize,
+    Text, TextMode, Vec2,
+};
+use rog_aura::{
+    self,
+    effects::{Effect, EffectState},
+    layouts::KeyboardLayout,
+    preview::{approximate, write_gif, KeyboardImage},
+    AuraEffect, KeyColourArray, PowerState,
+};
+use rog_dbus::{zbus_led::LedProxy, RogDbusClient};
 use rog_profiles::error::ProfileError;
 use rog_supported::SupportedFunctions;
 use rog_supported::{
@@ -24,6 +37,11 @@ mod aura_cli;
 mod cli_opts;
 mod profiles_cli;
 
+/// 25 FPS
+const PREVIEW_FRAME_TIME: Duration = Duration::from_millis(40);
+/// Every frame of a GIF is kept in memory until it is written
+const PREVIEW_MAX_SECONDS: f32 = 60.0;
+
 const CONFIG_ADVICE: &str = "A config file need to be removed so a new one can be generated";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -47,6 +65,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Previews are drawn here, so they work without asusd
+    if let Some(CliCommand::LedPreview(cmd)) = &parsed.command {
+        if let Err(err) = handle_led_preview(cmd) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let (dbus, _) = RogDbusClient::new()
         .map_err(|e| {
             print_error_help(Box::new(e), None);
@@ -143,6 +170,8 @@ fn do_parsed(
 ) -> Result<(), Box<dyn std::error::Error>> {
     match &parsed.command {
         Some(CliCommand::LedMode(mode)) => handle_led_mode(dbus, &supported.keyboard_led, mode)?,
+        // Drawn before connecting to asusd
+        Some(CliCommand::LedPreview(_)) => {}
         Some(CliCommand::Profile(cmd)) => handle_profile(dbus, &supported.platform_profile, cmd)?,
         Some(CliCommand::FanCurve(cmd)) => {
             handle_fan_curve(dbus, &supported.platform_profile, cmd)?
@@ -329,6 +358,54 @@ fn handle_anime(
                     }
                 }
             }
+            AnimeActions::Text(text) => {
+                if text.help_requested() || text.text.is_empty() {
+                    println!("Missing arg or command\n\n{}", text.self_usage());
+                    if let Some(lst) = text.self_command_list() {
+                        println!("\n{}", lst);
+                    }
+                    std::process::exit(1);
+                }
+
+                if let Some(speed) = text.scroll {
+                    if !(speed.is_finite() && speed > 0.0) {
+                        println!("--scroll must be more than 0 columns a second");
+                        std::process::exit(1);
+                    }
+                }
+
+                let text = Text {
+                    text: text.text.join(" "),
+                    font: if text.large {
+                        FontSize::Large
+                    } else {
+                        FontSize::Small
+                    },
+                    row: text.row,
+                    mode: match text.scroll {
+                        Some(speed) => TextMode::Marquee { speed },
+                        None => TextMode::Static,
+                    },
+                    time: match text.loops {
+                        0 => AnimTime::Infinite,
+                        loops => AnimTime::Count(loops),
+                    },
+                    brightness: text.bright,
+                };
+
+                if let TextMode::Static = text.mode {
+                    dbus.proxies()
+                        .anime()
+                        .write(text.render(Duration::default()).into())?;
+                } else {
+                    rog_anime::run_text(&text, Arc::new(AtomicBool::new(false)), &|frame| {
+                        dbus.proxies()
+                            .anime()
+                            .write(frame)
+                            .map_err(|err| AnimeError::Dbus(err.to_string()))
+                    })?;
+                }
+            }
         }
     }
     Ok(())
@@ -344,6 +421,8 @@ fn handle_led_mode(
         && !mode.next_mode
         && mode.sleep_enable.is_none()
         && mode.awake_enable.is_none()
+        && mode.power_zone.is_none()
+        && mode.power_states.is_none()
     {
         if !mode.help {
             println!("Missing arg or command\n");
@@ -376,10 +455,23 @@ fn handle_led_mode(
         println!("Please specify either next or previous");
         return Ok(());
     }
+
+    let device_proxy;
+    let led = match mode.device.as_ref() {
+        Some(id) => {
+            if !supported.devices.contains(id) {
+                println!("No LED device {}, found: {:?}", id, supported.devices);
+                return Ok(());
+            }
+            device_proxy = LedProxy::new_for_device(&zbus::Connection::new_system()?, id)?;
+            &device_proxy
+        }
+        None => dbus.proxies().led(),
+    };
     if mode.next_mode {
-        dbus.proxies().led().next_led_mode()?;
+        led.next_led_mode()?;
     } else if mode.prev_mode {
-        dbus.proxies().led().prev_led_mode()?;
+        led.prev_led_mode()?;
     } else if let Some(mode) = mode.command.as_ref() {
         if mode.help_requested() {
             println!("{}", mode.self_usage());
@@ -389,24 +481,122 @@ fn handle_led_mode(
             SetAuraBuiltin::MultiStatic(_) | SetAuraBuiltin::MultiBreathe(_) => {
                 let zones = <Vec<AuraEffect>>::from(mode);
                 for eff in zones {
-                    dbus.proxies().led().set_led_mode(&eff)?
+                    led.set_led_mode(&eff)?
                 }
             }
-            _ => dbus
-                .proxies()
-                .led()
-                .set_led_mode(&<AuraEffect>::from(mode))?,
+            _ => led.set_led_mode(&<AuraEffect>::from(mode))?,
         }
     }
 
     if let Some(enable) = mode.awake_enable {
-        dbus.proxies().led().set_awake_enabled(enable)?;
+        led.set_awake_enabled(enable)?;
     }
 
     if let Some(enable) = mode.sleep_enable {
-        dbus.proxies().led().set_sleep_enabled(enable)?;
+        led.set_sleep_enabled(enable)?;
+    }
+
+    match (mode.power_zone, mode.power_states.as_ref()) {
+        (Some(zone), Some(states)) => {
+            if !supported.power_zones.contains(&zone) {
+                println!("{:?} is not supported on this laptop", zone);
+                return Ok(());
+            }
+            let states: Vec<PowerState> = if states.to_lowercase() == "none" {
+                vec![]
+            } else {
+                states
+                    .split(',')
+                    .map(|s| s.trim().parse())
+                    .collect::<Result<_, _>>()?
+            };
+            for state in PowerState::ALL.iter() {
+                led.set_power_state(zone, *state, states.contains(state))?;
+            }
+        }
+        (Some(zone), None) => {
+            let current = led.power_states()?;
+            let lit: Vec<&PowerState> = PowerState::ALL
+                .iter()
+                .filter(|state| current.get(zone, **state))
+                .collect();
+            println!("{:?} is lit in: {:?}", zone, lit);
+        }
+        (None, Some(_)) => println!("--power-states requires a --power-zone"),
+        (None, None) => {}
+    }
+
+    Ok(())
+}
+
+fn handle_led_preview(cmd: &LedPreviewCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(mode) = cmd.command.as_ref() {
+        if mode.help_requested() {
+            println!("{}", mode.self_usage());
+            return Ok(());
+        }
+    }
+    if cmd.help || (cmd.command.is_none() && cmd.effect.is_none()) {
+        if !cmd.help {
+            println!("Missing arg or command\n");
+        }
+        println!("{}\n", cmd.self_usage());
+        if let Some(cmdlist) = LedPreviewCommand::command_list() {
+            println!("Builtin modes to preview\n{}", cmdlist);
+        }
+        return Ok(());
+    }
+
+    let layout = match cmd.layout.as_ref() {
+        Some(name) => KeyboardLayout::load(name)?,
+        None => KeyboardLayout::default(),
+    };
+    let builtin: Vec<AuraEffect> = match cmd.command.as_ref() {
+        Some(mode @ SetAuraBuiltin::MultiStatic(_))
+        | Some(mode @ SetAuraBuiltin::MultiBreathe(_)) => mode.into(),
+        Some(mode) => vec![mode.into()],
+        None => vec![],
+    };
+    let mut effect = cmd.effect.as_deref().map(Effect::from_name).transpose()?;
+
+    let to_file = cmd.gif.is_some() || cmd.png.is_some();
+    let scale = if to_file { 16 } else { 4 };
+    let seconds = cmd.seconds.unwrap_or(4.0).clamp(0.0, PREVIEW_MAX_SECONDS);
+    let frames = ((seconds / PREVIEW_FRAME_TIME.as_secs_f32()).ceil() as u32).max(1);
+    let mut render = |frame: u32| {
+        let mut colours = KeyColourArray::new();
+        for zone in builtin.iter() {
+            approximate(zone, &layout, PREVIEW_FRAME_TIME * frame, &mut colours);
+        }
+        if let Some(effect) = effect.as_mut() {
+            effect.advance(PREVIEW_FRAME_TIME, &layout);
+            effect.paint(&layout, &mut colours);
+        }
+        KeyboardImage::render(&layout, &colours, scale)
+    };
+
+    if to_file {
+        let images: Vec<KeyboardImage> = (0..frames).map(&mut render).collect();
+        if let Some(path) = cmd.gif.as_ref() {
+            write_gif(&images, PREVIEW_FRAME_TIME, File::create(path)?)?;
+        }
+        if let (Some(path), Some(last)) = (cmd.png.as_ref(), images.last()) {
+            last.write_png(File::create(path)?)?;
+        }
+        return Ok(());
     }
 
+    let mut stdout = std::io::stdout();
+    for frame in 0..frames {
+        let image = render(frame);
+        if frame > 0 {
+            // Draw over the last frame
+            write!(stdout, "\x1b[{}A", image.ansi_lines())?;
+        }
+        write!(stdout, "{}", image.to_ansi())?;
+        stdout.flush()?;
+        sleep(PREVIEW_FRAME_TIME);
+    }
     Ok(())
 }
 
@@ -0,0 +1,68 @@
+use std::{
+    fs::File,
+    io::{stdin, BufReader},
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+use rog_anime::{error::AnimeError, AudioSource, PcmSource, SampleSource};
+
+/// Sample rate asked of the sound server
+const MONITOR_RATE: u32 = 44100;
+
+/// Records the monitor of the default output with `parec`, which both
+/// PulseAudio and pipewire-pulse provide
+pub struct MonitorSource {
+    child: Child,
+    pcm: PcmSource<ChildStdout>,
+}
+
+impl MonitorSource {
+    pub fn new() -> Result<Self, AnimeError> {
+        let mut child = Command::new("parec")
+            .args([
+                "--device=@DEFAULT_MONITOR@",
+                "--raw",
+                "--format=s16le",
+                "--channels=1",
+                "--latency-msec=20",
+            ])
+            .arg(format!("--rate={}", MONITOR_RATE))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| AnimeError::Audio(format!("could not run parec: {}", err)))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| AnimeError::Audio("parec has no output".into()))?;
+        Ok(Self {
+            child,
+            pcm: PcmSource::new(stdout, MONITOR_RATE, 1),
+        })
+    }
+}
+
+impl SampleSource for MonitorSource {
+    fn sample_rate(&self) -> u32 {
+        self.pcm.sample_rate()
+    }
+
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, AnimeError> {
+        self.pcm.read(buf)
+    }
+}
+
+impl Drop for MonitorSource {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+pub fn open_source(source: &AudioSource) -> Result<Box<dyn SampleSource>, AnimeError> {
+    Ok(match source {
+        AudioSource::Monitor => Box::new(MonitorSource::new()?),
+        AudioSource::Wav(path) => Box::new(PcmSource::from_wav(BufReader::new(File::open(path)?))?),
+        AudioSource::Stdin => Box::new(PcmSource::from_wav(stdin())?),
+    })
+}
@@ -1,5 +1,5 @@
 use rog_anime::error::AnimeError;
-use rog_anime::{ActionData, ActionLoader, AnimTime, Fade, Sequences, Vec2};
+use rog_anime::{ActionData, ActionLoader, AnimTime, Fade, Sequences, SysfsSensors, Vec2};
 use rog_dbus::RogDbusClient;
 use serde_derive::{Deserialize, Serialize};
 use std::time::Duration;
@@ -15,7 +15,7 @@ use zbus::dbus_interface;
 use zvariant::ObjectPath;
 use zvariant_derive::Type;
 
-use crate::{error::Error, user_config::UserAnimeConfig};
+use crate::{audio::open_source, error::Error, user_config::UserAnimeConfig};
 
 #[derive(Debug, Clone, Deserialize, Serialize, Type)]
 pub struct Timer {
@@ -118,10 +118,72 @@ impl<'a> CtrlAnimeInner<'static> {
                         sleep(Duration::from_millis(1));
                     }
                 }
-                ActionData::AudioEq => {}
-                ActionData::SystemInfo => {}
-                ActionData::TimeDate => {}
-                ActionData::Matrix => {}
+                ActionData::AudioEq(eq) => {
+                    // A source that can't be opened skips the action rather
+                    // than stopping the whole sequence
+                    match open_source(&eq.source) {
+                        Ok(mut source) => {
+                            rog_anime::run_audio_eq(
+                                eq,
+                                source.as_mut(),
+                                self.do_early_return.clone(),
+                                &|output| {
+                                    self.client
+                                        .proxies()
+                                        .anime()
+                                        .write(output)
+                                        .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                                },
+                            )?;
+                        }
+                        Err(err) => println!("AudioEq: {}", err),
+                    }
+                }
+                ActionData::SystemInfo(info) => {
+                    rog_anime::run_system_info(
+                        info,
+                        &mut SysfsSensors::new(),
+                        self.do_early_return.clone(),
+                        &|output| {
+                            self.client
+                                .proxies()
+                                .anime()
+                                .write(output)
+                                .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                        },
+                    )?;
+                }
+                ActionData::Text(text) => {
+                    rog_anime::run_text(text, self.do_early_return.clone(), &|output| {
+                        self.client
+                            .proxies()
+                            .anime()
+                            .write(output)
+                            .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                    })?;
+                }
+                ActionData::TimeDate(clock) => {
+                    rog_anime::run_clock(clock, self.do_early_return.clone(), &|output| {
+                        self.client
+                            .proxies()
+                            .anime()
+                            .write(output)
+                            .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                    })?;
+                }
+                ActionData::Matrix(procedural) => {
+                    rog_anime::run_procedural(
+                        procedural,
+                        self.do_early_return.clone(),
+                        &|output| {
+                            self.client
+                                .proxies()
+                                .anime()
+                                .write(output)
+                                .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                        },
+                    )?;
+                }
             }
         }
 
@@ -302,6 +364,49 @@ impl CtrlAnime<'static> {
         Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_time_date(
+        &mut self,
+        index: u32,
+        hour_12: bool,
+        seconds: bool,
+        date: bool,
+        xy: (f32, f32),
+        time: Timer,
+        brightness: f32,
+    ) -> zbus::fdo::Result<String> {
+        if let Ok(mut config) = self.config.try_lock() {
+            let action = ActionLoader::TimeDate {
+                hour_12,
+                seconds,
+                date,
+                translation: Vec2::new(xy.0, xy.1),
+                time: time.into(),
+                brightness,
+            };
+
+            // Must make the inner run loop return early
+            self.inner_early_return.store(true, Ordering::SeqCst);
+
+            if let Ok(mut controller) = self.inner.lock() {
+                controller
+                    .sequences
+                    .insert(index as usize, &action)
+                    .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?;
+            }
+            config.anime.push(action);
+            config.write()?;
+
+            let json =
+                serde_json::to_string_pretty(&*config.anime).expect("Parse config to JSON failed");
+
+            // Release the inner run loop again
+            self.inner_early_return.store(false, Ordering::SeqCst);
+            return Ok(json);
+        }
+        Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
+    }
+
     pub fn insert_pause(&mut self, index: u32, millis: u64) -> zbus::fdo::Result<String> {
         if let Ok(mut config) = self.config.try_lock() {
             let action = ActionLoader::Pause(Duration::from_millis(millis));
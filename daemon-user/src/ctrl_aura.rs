@@ -0,0 +1,241 @@
+use rog_aura::{
+    effects::{Effect, EffectState},
+    layouts::KeyboardLayout,
+    AuraEffect, Key, KeyColourArray,
+};
+use rog_dbus::RogDbusClient;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Receiver,
+    Arc, Mutex,
+};
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use zbus::dbus_interface;
+use zvariant::ObjectPath;
+
+use crate::{error::Error, indicators::Indicators, user_config::UserAuraConfig};
+
+/// Time to wait between checks while the runner is stopped
+const IDLE_TIME: Duration = Duration::from_millis(100);
+
+/// The inner object exists to allow the zbus proxy to share it with a runner thread
+/// and a zbus server behind `Arc<Mutex<T>>`
+pub struct CtrlAuraInner<'a> {
+    effects: Vec<Effect>,
+    layout: KeyboardLayout,
+    client: RogDbusClient<'a>,
+    do_early_return: Arc<AtomicBool>,
+    last_frame: Option<Instant>,
+    /// Key presses for reactive effects
+    keys: Receiver<Key>,
+    indicators: Indicators,
+}
+
+impl CtrlAuraInner<'static> {
+    pub fn new(
+        effects: Vec<Effect>,
+        layout: KeyboardLayout,
+        client: RogDbusClient<'static>,
+        do_early_return: Arc<AtomicBool>,
+        keys: Receiver<Key>,
+        indicators: Indicators,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            effects,
+            layout,
+            client,
+            do_early_return,
+            last_frame: None,
+            keys,
+            indicators,
+        })
+    }
+
+    /// To be called on each main loop iteration to pump out a frame to the keyboard
+    pub fn run(&mut self) -> Result<(), Error> {
+        // Indicators are only painted over effects, so that without effects
+        // the keyboard is left in its builtin mode
+        if self.do_early_return.load(Ordering::SeqCst) || self.effects.is_empty() {
+            // Don't let presses queue up while stopped
+            while self.keys.try_recv().is_ok() {}
+            self.last_frame = None;
+            sleep(IDLE_TIME);
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        if self.last_frame.is_none() {
+            self.client.proxies().led().init_effect()?;
+        }
+        let dt = self
+            .last_frame
+            .map_or(Duration::from_secs(0), |last| now.duration_since(last));
+        self.last_frame = Some(now);
+
+        while let Ok(key) = self.keys.try_recv() {
+            for effect in self.effects.iter_mut() {
+                effect.key_pressed(key);
+            }
+        }
+
+        let mut colours = KeyColourArray::new();
+        for effect in self.effects.iter_mut() {
+            effect.advance(dt, &self.layout);
+            effect.paint(&self.layout, &mut colours);
+        }
+        self.indicators.update(now);
+        self.indicators.paint(&self.layout, &mut colours);
+        // This blocks long enough to pace the frames
+        if let Err(err) = self.client.proxies().led().set_per_key(&colours) {
+            // asusd may have restarted, so initialise again on the next frame
+            self.last_frame = None;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+}
+
+pub struct CtrlAura<'a> {
+    config: Arc<Mutex<UserAuraConfig>>,
+    client: RogDbusClient<'a>,
+    inner: Arc<Mutex<CtrlAuraInner<'a>>>,
+    /// Must be the same Atomic as in CtrlAuraInner
+    inner_early_return: Arc<AtomicBool>,
+}
+
+impl CtrlAura<'static> {
+    pub fn new(
+        config: Arc<Mutex<UserAuraConfig>>,
+        inner: Arc<Mutex<CtrlAuraInner<'static>>>,
+        client: RogDbusClient<'static>,
+        inner_early_return: Arc<AtomicBool>,
+    ) -> Result<Self, Error> {
+        Ok(CtrlAura {
+            config,
+            client,
+            inner,
+            inner_early_return,
+        })
+    }
+
+    pub fn add_to_server(self, server: &mut zbus::ObjectServer) {
+        server
+            .at(&ObjectPath::from_str_unchecked("/org/asuslinux/Aura"), self)
+            .map_err(|err| {
+                println!("CtrlAura: add_to_server {}", err);
+                err
+            })
+            .ok();
+    }
+}
+
+// The pattern for a zbus method is:
+// - Get config lock if required
+// - Set inner_early_return to stop the inner run loop temporarily
+// - Do actions
+// - Write config if required
+// - Unset inner_early_return
+#[dbus_interface(name = "org.asuslinux.Daemon")]
+impl CtrlAura<'static> {
+    /// Insert an effect at `index`. `effect` may be the name of an effect to
+    /// use its defaults, or a full JSON description such as `{"Pulser":{...}}`
+    pub fn insert_effect(&mut self, index: u32, effect: String) -> zbus::fdo::Result<String> {
+        let effect = match serde_json::from_str::<Effect>(&effect) {
+            Ok(effect) => effect,
+            Err(_) => Effect::from_name(&effect)
+                .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?,
+        };
+
+        if let Ok(mut config) = self.config.try_lock() {
+            let index = (index as usize).min(config.effects.len());
+            // Must make the inner run loop return early
+            let was_stopped = self.inner_early_return.swap(true, Ordering::SeqCst);
+
+            if let Ok(mut controller) = self.inner.lock() {
+                controller.effects.insert(index, effect.clone());
+            }
+            config.effects.insert(index, effect);
+            let written = config.write();
+            // Release the inner run loop again, even if the write failed
+            self.inner_early_return.store(was_stopped, Ordering::SeqCst);
+            written?;
+
+            let json =
+                serde_json::to_string_pretty(&config.effects).expect("Parse config to JSON failed");
+            return Ok(json);
+        }
+        Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
+    }
+
+    pub fn remove_item(&mut self, index: u32) -> zbus::fdo::Result<String> {
+        if let Ok(mut config) = self.config.try_lock() {
+            // Must make the inner run loop return early
+            let was_stopped = self.inner_early_return.swap(true, Ordering::SeqCst);
+
+            if let Ok(mut controller) = self.inner.lock() {
+                if (index as usize) < controller.effects.len() {
+                    controller.effects.remove(index as usize);
+                }
+            }
+            if (index as usize) < config.effects.len() {
+                config.effects.remove(index as usize);
+            }
+            let written = config.write();
+            // Release the inner run loop again, even if the write failed
+            self.inner_early_return.store(was_stopped, Ordering::SeqCst);
+            written?;
+
+            let json =
+                serde_json::to_string_pretty(&config.effects).expect("Parse config to JSON failed");
+            return Ok(json);
+        }
+        Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
+    }
+
+    /// List the active effects as JSON
+    pub fn list(&self) -> zbus::fdo::Result<String> {
+        if let Ok(config) = self.config.try_lock() {
+            return Ok(
+                serde_json::to_string_pretty(&config.effects).expect("Parse config to JSON failed")
+            );
+        }
+        Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
+    }
+
+    /// Start playing the effects on the keyboard
+    pub fn start(&mut self) -> zbus::fdo::Result<()> {
+        if let Ok(mut config) = self.config.try_lock() {
+            config.enabled = true;
+            config.write()?;
+        }
+        self.inner_early_return.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stop playing effects and return the keyboard to its builtin mode
+    pub fn stop(&mut self) -> zbus::fdo::Result<()> {
+        if let Ok(mut config) = self.config.try_lock() {
+            config.enabled = false;
+            config.write()?;
+        }
+        self.inner_early_return.store(true, Ordering::SeqCst);
+        // Wait for the runner to finish its current frame
+        if let Ok(mut controller) = self.inner.lock() {
+            controller.last_frame = None;
+        }
+        let mode = self.client.proxies().led().proxy().led_mode()?;
+        let mode: AuraEffect =
+            serde_json::from_str(&mode).map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?;
+        self.client.proxies().led().set_led_mode(&mode)?;
+        Ok(())
+    }
+
+    /// Are effects currently being played
+    #[dbus_interface(property)]
+    pub fn running(&self) -> bool {
+        !self.inner_early_return.load(Ordering::SeqCst)
+    }
+}
@@ -1,19 +1,31 @@
+use rog_aura::layouts::KeyboardLayout;
 use rog_dbus::RogDbusClient;
 use rog_user::{
     ctrl_anime::{CtrlAnime, CtrlAnimeInner},
+    ctrl_aura::{CtrlAura, CtrlAuraInner},
+    indicators::{spawn_profile_listener, Indicators},
+    input::{find_keyboard, spawn_key_listener},
     user_config::*,
     DBUS_NAME,
 };
+use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::thread;
+use std::thread::{self, sleep};
+use std::time::Duration;
 use zbus::{fdo, Connection};
 
 use std::sync::atomic::AtomicBool;
 
+/// The first wait before running the aura effects again after an error
+const RETRY_MIN: Duration = Duration::from_millis(500);
+/// The longest wait between retries
+const RETRY_MAX: Duration = Duration::from_secs(30);
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  user daemon v{}", rog_user::VERSION);
     println!("    rog-anime v{}", rog_anime::VERSION);
+    println!("     rog-aura v{}", rog_aura::VERSION);
     println!("     rog-dbus v{}", rog_dbus::VERSION);
     println!("rog-supported v{}", rog_supported::VERSION);
 
@@ -57,7 +69,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             })?;
     }
 
-    if supported.keyboard_led.per_key_led_mode {}
+    if supported.keyboard_led.per_key_led_mode {
+        let aura_config = UserAuraConfig::load_config(config.active_aura)?;
+        let layout =
+            KeyboardLayout::load(&supported.keyboard_led.per_key_layout).unwrap_or_else(|err| {
+                println!("Using default keyboard layout: {}", err);
+                KeyboardLayout::default()
+            });
+
+        let (key_send, key_recv) = channel();
+        let (led_send, led_recv) = channel();
+        if let Some(path) = aura_config.input_device.clone().or_else(find_keyboard) {
+            // Reading input requires the user to be in the `input` group
+            spawn_key_listener(path.clone(), key_send, led_send)
+                .map_err(|err| println!("Reactive effects disabled: {}: {}", path.display(), err))
+                .ok();
+        }
+
+        let (profile_send, profile_recv) = channel();
+        if !aura_config.indicators.is_empty() {
+            spawn_profile_listener(profile_send)
+                .map_err(|err| println!("Profile indicators disabled: {}", err))
+                .ok();
+        }
+        let indicators = Indicators::new(aura_config.indicators.clone(), led_recv, profile_recv);
+
+        let early_return = Arc::new(AtomicBool::new(!aura_config.enabled));
+        let (client, _) = RogDbusClient::new()?;
+        // Inner behind mutex required for thread safety
+        let inner = Arc::new(Mutex::new(CtrlAuraInner::new(
+            aura_config.effects.clone(),
+            layout,
+            client,
+            early_return.clone(),
+            key_recv,
+            indicators,
+        )?));
+        // Need new client object for dbus control part
+        let (client, _) = RogDbusClient::new()?;
+        let aura_control = CtrlAura::new(
+            Arc::new(Mutex::new(aura_config)),
+            inner.clone(),
+            client,
+            early_return,
+        )?;
+        aura_control.add_to_server(&mut server);
+        // Thread using inner
+        let _aura_thread = thread::Builder::new()
+            .name("Aura User".into())
+            .spawn(move || {
+                let mut retry = RETRY_MIN;
+                loop {
+                    let result = match inner.try_lock() {
+                        Ok(mut inner) => inner.run(),
+                        Err(_) => Ok(()),
+                    };
+                    // Back off while asusd is unavailable, the lock is
+                    // released so that dbus calls aren't held up
+                    match result {
+                        Ok(_) => retry = RETRY_MIN,
+                        Err(err) => {
+                            println!("Aura User: {}", err);
+                            sleep(retry);
+                            retry = (retry * 2).min(RETRY_MAX);
+                        }
+                    }
+                }
+            })?;
+    }
 
     loop {
         if let Err(err) = server.try_handle_next() {
@@ -1,6 +1,7 @@
 use std::fmt;
 
 use rog_anime::error::AnimeError;
+use rog_aura::error::Error as AuraError;
 
 #[derive(Debug)]
 pub enum Error {
@@ -9,6 +10,8 @@ pub enum Error {
     ConfigLockFail,
     XdgVars,
     Anime(AnimeError),
+    Aura(AuraError),
+    Zbus(zbus::Error),
 }
 
 impl fmt::Display for Error {
@@ -20,6 +23,8 @@ impl fmt::Display for Error {
             Error::ConfigLockFail => write!(f, "Failed to lock user config"),
             Error::XdgVars => write!(f, "XDG environment vars appear unset"),
             Error::Anime(err) => write!(f, "Anime error: {}", err),
+            Error::Aura(err) => write!(f, "Aura error: {}", err),
+            Error::Zbus(err) => write!(f, "Zbus error: {}", err),
         }
     }
 }
@@ -38,8 +43,20 @@ impl From<AnimeError> for Error {
     }
 }
 
+impl From<AuraError> for Error {
+    fn from(err: AuraError) -> Self {
+        Error::Aura(err)
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(err: zbus::Error) -> Self {
+        Error::Zbus(err)
+    }
+}
+
 impl From<Error> for zbus::fdo::Error {
     fn from(err: Error) -> Self {
-        zbus::fdo::Error::Failed(format!("Anime zbus error: {}", err))
+        zbus::fdo::Error::Failed(format!("User daemon error: {}", err))
     }
 }
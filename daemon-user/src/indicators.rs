@@ -0,0 +1,331 @@
+//! Keys which show the state of the system, such as Caps Lock or the active
+//! platform profile, painted over the per-key effects.
+
+use std::{
+    fs::{read_dir, read_to_string},
+    path::Path,
+    sync::mpsc::{Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rog_aura::{layouts::KeyboardLayout, Colour, ColourSpace, Key, KeyColourArray};
+use rog_dbus::RogDbusClient;
+use rog_profiles::Profile;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::Error, input::LockLed};
+
+/// How often the battery and mic mute state are read
+const POLL_TIME: Duration = Duration::from_secs(2);
+const MIC_MUTE_PATH: &str = "/sys/class/leds/platform::micmute/brightness";
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// A system state which can be shown on a key
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum IndicatorSource {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    MicMute,
+    /// Lit while this platform profile is active
+    Profile(Profile),
+    /// Lit while the battery is charging
+    Charging,
+    /// Always lit, blended from `low_colour` when empty to `colour` when full
+    Battery,
+}
+
+/// Show `source` on `key`. Where several indicators are lit on the same key
+/// the last one is shown.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Indicator {
+    pub key: Key,
+    pub source: IndicatorSource,
+    pub colour: Colour,
+    /// Only used by `Battery`
+    #[serde(default = "default_low_colour")]
+    pub low_colour: Colour,
+}
+
+fn default_low_colour() -> Colour {
+    Colour(255, 0, 0)
+}
+
+/// Everything indicators can show
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndicatorState {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+    pub mic_mute: bool,
+    pub charging: bool,
+    pub profile: Option<Profile>,
+    /// Battery charge in percent, `None` if there is no battery
+    pub battery: Option<u8>,
+}
+
+impl Indicator {
+    /// The colour of the key in this state, `None` leaves the effects showing
+    pub fn colour_for(&self, state: &IndicatorState) -> Option<Colour> {
+        let lit = match &self.source {
+            IndicatorSource::CapsLock => state.caps_lock,
+            IndicatorSource::NumLock => state.num_lock,
+            IndicatorSource::ScrollLock => state.scroll_lock,
+            IndicatorSource::MicMute => state.mic_mute,
+            IndicatorSource::Profile(profile) => state.profile == Some(*profile),
+            IndicatorSource::Charging => state.charging,
+            IndicatorSource::Battery => {
+                return state.battery.map(|level| {
+                    Colour::gradient_at(
+                        &[self.low_colour, self.colour],
+                        level as f32 / 100.0,
+                        ColourSpace::Hsv,
+                    )
+                });
+            }
+        };
+        if lit {
+            Some(self.colour)
+        } else {
+            None
+        }
+    }
+}
+
+/// Paint the lit indicators over `colours`, replacing the effect colours
+pub fn paint_indicators(
+    indicators: &[Indicator],
+    state: &IndicatorState,
+    layout: &KeyboardLayout,
+    colours: &mut KeyColourArray,
+) {
+    for indicator in indicators {
+        if let Some(colour) = indicator.colour_for(state) {
+            if let Some((r, g, b)) = colours.key_in(layout, indicator.key) {
+                *r = colour.0;
+                *g = colour.1;
+                *b = colour.2;
+            }
+        }
+    }
+}
+
+#[inline]
+fn read_trimmed(path: &Path) -> Option<String> {
+    read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Is an LED class `brightness` non-zero
+#[inline]
+fn is_lit(path: &Path) -> bool {
+    matches!(read_trimmed(path).as_deref(), Some(b) if b != "0")
+}
+
+/// Keeps the `IndicatorState` up to date from input events, dbus signals,
+/// and sysfs
+pub struct Indicators {
+    pub rules: Vec<Indicator>,
+    pub state: IndicatorState,
+    leds: Receiver<(LockLed, bool)>,
+    profiles: Receiver<Profile>,
+    last_poll: Option<Instant>,
+}
+
+impl Indicators {
+    pub fn new(
+        rules: Vec<Indicator>,
+        leds: Receiver<(LockLed, bool)>,
+        profiles: Receiver<Profile>,
+    ) -> Self {
+        let mut state = IndicatorState::default();
+        // Events only arrive on changes, so start from the LED class state
+        if let Ok(entries) = read_dir("/sys/class/leds") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let lit = is_lit(&entry.path().join("brightness"));
+                if name.ends_with("::capslock") {
+                    state.caps_lock |= lit;
+                } else if name.ends_with("::numlock") {
+                    state.num_lock |= lit;
+                } else if name.ends_with("::scrolllock") {
+                    state.scroll_lock |= lit;
+                }
+            }
+        }
+        Self {
+            rules,
+            state,
+            leds,
+            profiles,
+            last_poll: None,
+        }
+    }
+
+    /// Apply any new events, and read sysfs if it is due
+    pub fn update(&mut self, now: Instant) {
+        while let Ok((led, lit)) = self.leds.try_recv() {
+            match led {
+                LockLed::CapsLock => self.state.caps_lock = lit,
+                LockLed::NumLock => self.state.num_lock = lit,
+                LockLed::ScrollLock => self.state.scroll_lock = lit,
+            }
+        }
+        while let Ok(profile) = self.profiles.try_recv() {
+            self.state.profile = Some(profile);
+        }
+
+        let due = match self.last_poll {
+            Some(last) => now.duration_since(last) >= POLL_TIME,
+            None => true,
+        };
+        if due {
+            self.last_poll = Some(now);
+            self.poll_sysfs();
+        }
+    }
+
+    fn poll_sysfs(&mut self) {
+        self.state.mic_mute = is_lit(Path::new(MIC_MUTE_PATH));
+
+        self.state.battery = None;
+        self.state.charging = false;
+        if let Ok(entries) = read_dir(POWER_SUPPLY_PATH) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if read_trimmed(&path.join("type")).as_deref() != Some("Battery") {
+                    continue;
+                }
+                self.state.battery = read_trimmed(&path.join("capacity"))
+                    .and_then(|c| c.parse::<u8>().ok())
+                    .map(|c| c.min(100));
+                self.state.charging =
+                    read_trimmed(&path.join("status")).as_deref() == Some("Charging");
+                break;
+            }
+        }
+    }
+
+    pub fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        paint_indicators(&self.rules, &self.state, layout, colours);
+    }
+}
+
+/// Spawn a thread which sends the active platform profile, then each change
+/// of it signalled by asusd
+pub fn spawn_profile_listener(send: Sender<Profile>) -> Result<(), Error> {
+    thread::Builder::new()
+        .name("Aura Indicators".into())
+        .spawn(move || {
+            let (client, conn) = match RogDbusClient::new() {
+                Ok(client) => client,
+                Err(err) => {
+                    println!("Aura Indicators: {}", err);
+                    return;
+                }
+            };
+            if let Ok(profile) = client.proxies().profile().active_profile() {
+                send.send(profile).ok();
+            }
+            let recv = client.setup_recv(conn);
+            loop {
+                if let Err(err) = recv.next_signal() {
+                    println!("Aura Indicators: {}", err);
+                    thread::sleep(POLL_TIME);
+                    continue;
+                }
+                while let Ok(profile) = client.signals().profile.try_recv() {
+                    if send.send(profile).is_err() {
+                        return;
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{paint_indicators, Indicator, IndicatorSource, IndicatorState};
+    use rog_aura::{layouts::KeyboardLayout, Colour, Key, KeyColourArray};
+    use rog_profiles::Profile;
+
+    fn indicator(key: Key, source: IndicatorSource, colour: Colour) -> Indicator {
+        Indicator {
+            key,
+            source,
+            colour,
+            low_colour: Colour(255, 0, 0),
+        }
+    }
+
+    #[test]
+    fn painted_over_effects() {
+        let layout = KeyboardLayout::default();
+        let rules = vec![
+            indicator(Key::Caps, IndicatorSource::CapsLock, Colour(255, 0, 0)),
+            indicator(
+                Key::F5,
+                IndicatorSource::Profile(Profile::Performance),
+                Colour(255, 128, 0),
+            ),
+            indicator(Key::Rog, IndicatorSource::Battery, Colour(0, 255, 0)),
+            indicator(Key::MicMute, IndicatorSource::MicMute, Colour(255, 0, 0)),
+        ];
+        let state = IndicatorState {
+            caps_lock: true,
+            profile: Some(Profile::Quiet),
+            battery: Some(50),
+            ..Default::default()
+        };
+
+        let mut colours = KeyColourArray::new();
+        colours.set_in(&layout, Key::MicMute, 1, 2, 3);
+        colours.set_in(&layout, Key::Caps, 1, 2, 3);
+        paint_indicators(&rules, &state, &layout, &mut colours);
+
+        let key = |colours: &mut KeyColourArray, key| {
+            let (r, g, b) = colours.key_in(&layout, key).unwrap();
+            Colour(*r, *g, *b)
+        };
+        assert_eq!(key(&mut colours, Key::Caps), Colour(255, 0, 0));
+        // Unlit indicators leave the effect colour
+        assert_eq!(key(&mut colours, Key::MicMute), Colour(1, 2, 3));
+        assert_eq!(key(&mut colours, Key::F5), Colour(0, 0, 0));
+        // Half way around the hue circle from red to green
+        assert_eq!(key(&mut colours, Key::Rog), Colour(255, 255, 0));
+    }
+
+    #[test]
+    fn last_lit_rule_wins() {
+        let rules = vec![
+            indicator(Key::Rog, IndicatorSource::Battery, Colour(0, 255, 0)),
+            indicator(Key::Rog, IndicatorSource::Charging, Colour(0, 0, 255)),
+        ];
+        let mut state = IndicatorState {
+            battery: Some(100),
+            ..Default::default()
+        };
+        let layout = KeyboardLayout::default();
+        let mut colours = KeyColourArray::new();
+        paint_indicators(&rules, &state, &layout, &mut colours);
+        assert_eq!(
+            colours
+                .key_in(&layout, Key::Rog)
+                .map(|(r, g, b)| (*r, *g, *b)),
+            Some((0, 255, 0))
+        );
+
+        state.charging = true;
+        paint_indicators(&rules, &state, &layout, &mut colours);
+        assert_eq!(
+            colours
+                .key_in(&layout, Key::Rog)
+                .map(|(r, g, b)| (*r, *g, *b)),
+            Some((0, 0, 255))
+        );
+
+        // No battery, no colour
+        assert_eq!(rules[0].colour_for(&IndicatorState::default()), None);
+    }
+}
@@ -0,0 +1,189 @@
+//! Reads key presses and lock LED changes from an evdev device node, or from
+//! a file of recorded `struct input_event`, for use by reactive per-key
+//! effects and indicators.
+
+use std::{
+    fs::{read_to_string, OpenOptions},
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    thread,
+};
+
+use rog_aura::Key;
+
+/// `EV_KEY` from `linux/input-event-codes.h`
+const EV_KEY: u16 = 0x01;
+/// `EV_LED` from `linux/input-event-codes.h`
+const EV_LED: u16 = 0x11;
+/// Size of `struct input_event`, a `struct timeval` followed by type, code, value
+#[cfg(target_pointer_width = "64")]
+pub const INPUT_EVENT_LEN: usize = 24;
+#[cfg(not(target_pointer_width = "64"))]
+pub const INPUT_EVENT_LEN: usize = 16;
+
+/// The part of an `input_event` that is needed for effects
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InputEvent {
+    pub type_of: u16,
+    pub code: u16,
+    /// For key events: 0 = release, 1 = press, 2 = autorepeat
+    pub value: i32,
+}
+
+impl InputEvent {
+    #[inline]
+    pub fn from_bytes(buf: &[u8; INPUT_EVENT_LEN]) -> Self {
+        let off = INPUT_EVENT_LEN - 8;
+        Self {
+            type_of: u16::from_ne_bytes([buf[off], buf[off + 1]]),
+            code: u16::from_ne_bytes([buf[off + 2], buf[off + 3]]),
+            value: i32::from_ne_bytes([buf[off + 4], buf[off + 5], buf[off + 6], buf[off + 7]]),
+        }
+    }
+
+    #[inline]
+    pub fn is_key_press(&self) -> bool {
+        self.type_of == EV_KEY && self.value == 1
+    }
+
+    /// The lock LED and its new state if this is an LED event
+    #[inline]
+    pub fn lock_led(&self) -> Option<(LockLed, bool)> {
+        if self.type_of != EV_LED {
+            return None;
+        }
+        let led = match self.code {
+            0 => LockLed::NumLock,
+            1 => LockLed::CapsLock,
+            2 => LockLed::ScrollLock,
+            _ => return None,
+        };
+        Some((led, self.value != 0))
+    }
+}
+
+/// The keyboard lock LEDs, as reported by `EV_LED` events
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LockLed {
+    NumLock,
+    CapsLock,
+    ScrollLock,
+}
+
+/// Read the next event from a device or recorded stream. Returns `None` at
+/// the end of a recorded stream.
+pub fn read_event<R: Read>(reader: &mut R) -> std::io::Result<Option<InputEvent>> {
+    let mut buf = [0u8; INPUT_EVENT_LEN];
+    match reader.read_exact(&mut buf) {
+        Ok(_) => Ok(Some(InputEvent::from_bytes(&buf))),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Forward every key press in the stream to `keys`, and lock LED changes to
+/// `leds`, until the stream ends or the key receiver is dropped
+pub fn forward_input<R: Read>(
+    reader: &mut R,
+    keys: &Sender<Key>,
+    leds: &Sender<(LockLed, bool)>,
+) -> std::io::Result<()> {
+    while let Some(event) = read_event(reader)? {
+        if event.is_key_press() {
+            for key in Key::from_evdev(event.code) {
+                if keys.send(*key).is_err() {
+                    return Ok(());
+                }
+            }
+        } else if let Some(led) = event.lock_led() {
+            // Indicators are optional, so a dropped receiver is fine
+            leds.send(led).ok();
+        }
+    }
+    Ok(())
+}
+
+/// Find the internal keyboard event node via `/proc/bus/input/devices`
+pub fn find_keyboard() -> Option<PathBuf> {
+    let devices = read_to_string("/proc/bus/input/devices").ok()?;
+    for block in devices.split("\n\n") {
+        if !block.contains("N: Name=\"AT Translated Set 2 keyboard\"")
+            && !block.contains("N: Name=\"Asus Keyboard\"")
+        {
+            continue;
+        }
+        let handlers = block.lines().find(|l| l.starts_with("H: Handlers="))?;
+        if let Some(event) = handlers.split_whitespace().find(|h| h.starts_with("event")) {
+            return Some(Path::new("/dev/input").join(event));
+        }
+    }
+    None
+}
+
+/// Spawn a thread which sends key presses and lock LED changes from `path`
+/// until it ends
+pub fn spawn_key_listener(
+    path: PathBuf,
+    keys: Sender<Key>,
+    leds: Sender<(LockLed, bool)>,
+) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().read(true).open(&path)?;
+    thread::Builder::new()
+        .name("Aura Input".into())
+        .spawn(move || {
+            forward_input(&mut file, &keys, &leds)
+                .map_err(|err| println!("Aura Input: {}: {}", path.display(), err))
+                .ok();
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, sync::mpsc::channel};
+
+    use rog_aura::Key;
+
+    use super::{forward_input, LockLed, INPUT_EVENT_LEN};
+
+    fn event(type_of: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut buf = vec![0u8; INPUT_EVENT_LEN - 8];
+        buf.extend_from_slice(&type_of.to_ne_bytes());
+        buf.extend_from_slice(&code.to_ne_bytes());
+        buf.extend_from_slice(&value.to_ne_bytes());
+        buf
+    }
+
+    #[test]
+    fn recorded_stream() {
+        let mut stream = Vec::new();
+        // press, autorepeat, release of A
+        stream.extend(event(1, 30, 1));
+        stream.extend(event(0, 0, 0)); // EV_SYN
+        stream.extend(event(1, 30, 2));
+        stream.extend(event(1, 30, 0));
+        // MSC_SCAN then press of space
+        stream.extend(event(4, 4, 57));
+        stream.extend(event(1, 57, 1));
+        // Caps lock LED on then off
+        stream.extend(event(0x11, 1, 1));
+        stream.extend(event(0x11, 1, 0));
+
+        let (send, recv) = channel();
+        let (led_send, led_recv) = channel();
+        forward_input(&mut Cursor::new(stream), &send, &led_send).unwrap();
+        drop(send);
+        drop(led_send);
+        let keys: Vec<Key> = recv.iter().collect();
+        assert_eq!(
+            keys,
+            vec![Key::A, Key::Space1, Key::Space2, Key::Space3, Key::Space4]
+        );
+        let leds: Vec<(LockLed, bool)> = led_recv.iter().collect();
+        assert_eq!(
+            leds,
+            vec![(LockLed::CapsLock, true), (LockLed::CapsLock, false)]
+        );
+    }
+}
@@ -6,6 +6,16 @@ pub mod ctrl_anime;
 
 pub mod zbus_anime;
 
+pub mod ctrl_aura;
+
+pub mod zbus_aura;
+
+pub mod input;
+
+pub mod indicators;
+
+pub mod audio;
+
 pub static DBUS_NAME: &str = "org.asuslinux.Daemon";
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
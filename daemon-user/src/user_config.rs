@@ -1,13 +1,15 @@
 use std::{
     fs::{create_dir, OpenOptions},
     io::{Read, Write},
+    path::PathBuf,
     time::Duration,
 };
 
 use rog_anime::{ActionLoader, AnimTime, Fade, Sequences, Vec2};
+use rog_aura::effects::{Comet, Effect, Ripple};
 use serde_derive::{Deserialize, Serialize};
 
-use crate::error::Error;
+use crate::{error::Error, indicators::Indicator};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UserAnimeConfig {
@@ -151,16 +153,121 @@ impl Default for UserAnimeConfig {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserAuraConfig {
+    pub name: String,
+    /// Start playing the effects when the user daemon starts
+    #[serde(default)]
+    pub enabled: bool,
+    /// Keyboard event node for reactive effects, found automatically if not set
+    #[serde(default)]
+    pub input_device: Option<PathBuf>,
+    /// Effects are layered in order on each frame
+    pub effects: Vec<Effect>,
+    /// Keys which show system state, painted over the effects
+    #[serde(default)]
+    pub indicators: Vec<Indicator>,
+}
+
+impl UserAuraConfig {
+    pub fn write(&self) -> Result<(), Error> {
+        let mut path = if let Some(dir) = dirs::config_dir() {
+            dir
+        } else {
+            return Err(Error::XdgVars);
+        };
+
+        path.push("rog");
+        if !path.exists() {
+            create_dir(path.clone())?;
+        }
+        let name = self.name.clone();
+        path.push(name + ".cfg");
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        let json = serde_json::to_string_pretty(&self).unwrap();
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load_config(name: String) -> Result<UserAuraConfig, Error> {
+        let mut path = if let Some(dir) = dirs::config_dir() {
+            dir
+        } else {
+            return Err(Error::XdgVars);
+        };
+
+        path.push("rog");
+        if !path.exists() {
+            create_dir(path.clone())?;
+        }
+
+        path.push(name.clone() + ".cfg");
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let mut buf = String::new();
+
+        if let Ok(read_len) = file.read_to_string(&mut buf) {
+            if read_len == 0 {
+                let default = UserAuraConfig {
+                    name,
+                    ..Default::default()
+                };
+                let json = serde_json::to_string_pretty(&default).unwrap();
+                file.write_all(json.as_bytes())?;
+                return Ok(default);
+            } else if let Ok(data) = serde_json::from_str::<UserAuraConfig>(&buf) {
+                return Ok(data);
+            }
+        }
+        Err(Error::ConfigLoadFail)
+    }
+}
+
+impl Default for UserAuraConfig {
+    fn default() -> Self {
+        Self {
+            name: "aura-default".to_string(),
+            enabled: false,
+            input_device: None,
+            indicators: Vec::new(),
+            effects: vec![
+                Effect::Comet(Comet::default()),
+                Effect::Ripple(Ripple::default()),
+            ],
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct UserConfig {
     /// Name of active anime config file in the user config directory
     pub active_anime: String,
+    /// Name of active per-key effect config file in the user config directory
+    #[serde(default = "default_active_aura")]
+    pub active_aura: String,
+}
+
+fn default_active_aura() -> String {
+    "aura-default".to_string()
 }
 
 impl UserConfig {
     pub fn new() -> Self {
         Self {
             active_anime: "anime-default".to_string(),
+            active_aura: default_active_aura(),
         }
     }
 
@@ -192,6 +299,7 @@ impl UserConfig {
                 file.write_all(json.as_bytes())?;
             } else if let Ok(data) = serde_json::from_str::<UserConfig>(&buf) {
                 self.active_anime = data.active_anime;
+                self.active_aura = data.active_aura;
                 return Ok(());
             }
         }
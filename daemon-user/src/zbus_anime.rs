@@ -22,6 +22,8 @@
 
 use zbus::dbus_proxy;
 
+use crate::ctrl_anime::Timer;
+
 #[dbus_proxy(interface = "org.asuslinux.Daemon")]
 trait Daemon {
     /// InsertAsusGif method
@@ -58,6 +60,18 @@ trait Daemon {
         brightness: f64,
     ) -> zbus::Result<String>;
 
+    /// InsertTimeDate method
+    fn insert_time_date(
+        &self,
+        index: u32,
+        hour_12: bool,
+        seconds: bool,
+        date: bool,
+        xy: &(f64, f64),
+        time: &Timer,
+        brightness: f64,
+    ) -> zbus::Result<String>;
+
     /// InsertPause method
     fn insert_pause(&self, index: u32, millis: u64) -> zbus::Result<String>;
 
@@ -9,12 +9,17 @@ pub static CONFIG_PATH: &str = "/etc/asusd/asusd.conf";
 pub struct Config {
     /// Save charge limit for restoring on boot
     pub bat_charge_limit: u8,
+    /// Serve the keyboard to OpenRGB SDK clients on this localhost port.
+    /// Disabled if not set, OpenRGB uses 6742 by default.
+    #[serde(default)]
+    pub openrgb_port: Option<u16>,
 }
 
 impl Config {
     fn new() -> Self {
         Config {
             bat_charge_limit: 100,
+            openrgb_port: None,
         }
     }
 
@@ -10,7 +10,7 @@ use rog_anime::{
         pkt_for_apply, pkt_for_flush, pkt_for_set_boot, pkt_for_set_on, pkts_for_init, PROD_ID,
         VENDOR_ID,
     },
-    ActionData, AnimeDataBuffer, AnimePacketType, ANIME_DATA_LEN,
+    ActionData, AnimeDataBuffer, AnimePacketType, SysfsSensors, ANIME_DATA_LEN,
 };
 use rog_supported::AnimeSupportedFunctions;
 use rusb::{Device, DeviceHandle};
@@ -210,10 +210,94 @@ impl CtrlAnime {
                                 }
                             }
                             ActionData::Pause(duration) => sleep(*duration),
-                            ActionData::AudioEq => {}
-                            ActionData::SystemInfo => {}
-                            ActionData::TimeDate => {}
-                            ActionData::Matrix => {}
+                            // Needs the user's audio session, so only asusd-user
+                            // runs it
+                            ActionData::AudioEq(_) => {}
+                            ActionData::SystemInfo(info) => {
+                                if let Err(err) = rog_anime::run_system_info(
+                                    info,
+                                    &mut SysfsSensors::new(),
+                                    thread_exit.clone(),
+                                    &|frame| {
+                                        inner
+                                            .try_lock()
+                                            .map(|lock| lock.write_data_buffer(frame))
+                                            .map_err(|err| {
+                                                warn!("rog_anime::run_system_info: {}", err);
+                                                AnimeError::NoFrames
+                                            })
+                                    },
+                                ) {
+                                    warn!("rog_anime::run_system_info: {}", err);
+                                    break 'main;
+                                };
+
+                                if thread_exit.load(Ordering::SeqCst) {
+                                    break 'main;
+                                }
+                            }
+                            ActionData::Text(text) => {
+                                if let Err(err) =
+                                    rog_anime::run_text(text, thread_exit.clone(), &|frame| {
+                                        inner
+                                            .try_lock()
+                                            .map(|lock| lock.write_data_buffer(frame))
+                                            .map_err(|err| {
+                                                warn!("rog_anime::run_text: {}", err);
+                                                AnimeError::NoFrames
+                                            })
+                                    })
+                                {
+                                    warn!("rog_anime::run_text: {}", err);
+                                    break 'main;
+                                };
+
+                                if thread_exit.load(Ordering::SeqCst) {
+                                    break 'main;
+                                }
+                            }
+                            ActionData::TimeDate(clock) => {
+                                if let Err(err) =
+                                    rog_anime::run_clock(clock, thread_exit.clone(), &|frame| {
+                                        inner
+                                            .try_lock()
+                                            .map(|lock| lock.write_data_buffer(frame))
+                                            .map_err(|err| {
+                                                warn!("rog_anime::run_clock: {}", err);
+                                                AnimeError::NoFrames
+                                            })
+                                    })
+                                {
+                                    warn!("rog_anime::run_clock: {}", err);
+                                    break 'main;
+                                };
+
+                                if thread_exit.load(Ordering::SeqCst) {
+                                    break 'main;
+                                }
+                            }
+                            ActionData::Matrix(procedural) => {
+                                if let Err(err) = rog_anime::run_procedural(
+                                    procedural,
+                                    thread_exit.clone(),
+                                    &|frame| {
+                                        inner
+                                            .try_lock()
+                                            .map(|lock| lock.write_data_buffer(frame))
+                                            .map_err(|err| {
+                                                warn!("rog_anime::run_procedural: {}", err);
+                                                AnimeError::NoFrames
+                                            })
+                                    },
+                                ) {
+                                    warn!("rog_anime::run_procedural: {}", err);
+                                    break 'main;
+                                };
+
+                                if thread_exit.load(Ordering::SeqCst) {
+                                    break 'main;
+                                }
+                            }
                         }
                     }
                     if once || actions.is_empty() {
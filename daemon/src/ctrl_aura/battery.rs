@@ -0,0 +1,237 @@
+use crate::{ctrl_charge::CtrlCharge, error::RogError, CtrlTask};
+use log::warn;
+use rog_aura::AuraEffect;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use super::controller::CtrlKbdLed;
+use super::hotplug::spawn_udev_listener;
+use super::idle::on_ac_power;
+
+/// How often the battery is read when there are no power_supply events, the
+/// capacity doesn't always send one as it drops
+const BATTERY_CHECK_TIME: Duration = Duration::from_secs(30);
+
+/// A battery state which temporarily replaces the LED mode
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum BatteryCondition {
+    /// Running from the battery
+    Unplugged,
+    /// Running from the battery with less than this percent charge
+    Below(u8),
+    /// Plugged in and charging
+    Charging,
+    /// Plugged in and charged, either full or stopped at the charge limit
+    Full,
+}
+
+/// Show `effect` while `condition` holds
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BatteryRule {
+    pub condition: BatteryCondition,
+    pub effect: AuraEffect,
+}
+
+/// The charger and battery as read from sysfs
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BatteryStatus {
+    pub on_ac: bool,
+    pub charging: bool,
+    /// Full, or not charging while on AC which is how the charge limit shows
+    pub full: bool,
+    /// Charge in percent
+    pub capacity: u8,
+}
+
+impl BatteryStatus {
+    /// Read the status of the battery in the `battery` power_supply directory
+    pub fn read(battery: &Path) -> Result<Self, RogError> {
+        let read = |name: &str| {
+            let path = battery.join(name);
+            read_to_string(&path)
+                .map(|s| s.trim().to_string())
+                .map_err(|err| RogError::Read(path.to_string_lossy().into(), err))
+        };
+        let status = read("status")?;
+        let capacity = read("capacity")?
+            .parse::<u8>()
+            .map_err(|err| RogError::NotFound(format!("battery capacity: {}", err)))?;
+        let on_ac = on_ac_power();
+        Ok(Self {
+            on_ac,
+            charging: status == "Charging",
+            full: status == "Full" || (on_ac && status == "Not charging"),
+            capacity: capacity.min(100),
+        })
+    }
+
+    pub fn matches(&self, condition: BatteryCondition) -> bool {
+        match condition {
+            BatteryCondition::Unplugged => !self.on_ac,
+            BatteryCondition::Below(percent) => !self.on_ac && self.capacity < percent,
+            BatteryCondition::Charging => self.on_ac && self.charging,
+            BatteryCondition::Full => self.on_ac && self.full,
+        }
+    }
+}
+
+/// The first rule which matches `status`, earlier rules take priority
+pub fn active_rule(rules: &[BatteryRule], status: &BatteryStatus) -> Option<usize> {
+    rules.iter().position(|rule| status.matches(rule.condition))
+}
+
+/// Tracks which battery rule is showing in place of the saved mode
+#[derive(Debug, Default)]
+pub struct BatteryOverride {
+    active: Option<usize>,
+    /// Write the active rule on the next check even if unchanged
+    stale: bool,
+}
+
+impl BatteryOverride {
+    /// Decide what to write when the active rule changes. Returns
+    /// `Some(Some(effect))` to show a rule, or `Some(None)` to restore the
+    /// mode underneath when no rule matches any more.
+    pub fn due(
+        &mut self,
+        rules: &[BatteryRule],
+        status: &BatteryStatus,
+    ) -> Option<Option<AuraEffect>> {
+        let active = active_rule(rules, status);
+        let previous = std::mem::replace(&mut self.active, active);
+        let stale = std::mem::take(&mut self.stale);
+        match active {
+            Some(idx) if stale || previous != active => Some(Some(rules[idx].effect.clone())),
+            None if previous.is_some() => Some(None),
+            _ => None,
+        }
+    }
+
+    /// Is a rule's effect showing in place of the saved mode
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Write the active rule again on the next check, such as after the
+    /// saved mode was written over it or the rules were edited
+    pub fn reset(&mut self) {
+        self.stale = true;
+    }
+}
+
+/// Switches the LED mode while battery rules match, and back after
+pub struct CtrlKbdBatteryTask {
+    inner: Arc<Mutex<CtrlKbdLed>>,
+    battery: PathBuf,
+    events: Receiver<()>,
+}
+
+impl CtrlKbdBatteryTask {
+    /// Errors if there is no battery
+    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>) -> Result<Self, RogError> {
+        let battery = CtrlCharge::get_battery_dir()?;
+        let (send, events) = std::sync::mpsc::channel();
+        spawn_udev_listener("power_supply", send, |_| Some(()))?;
+        Ok(Self {
+            inner,
+            battery,
+            events,
+        })
+    }
+}
+
+impl CtrlTask for CtrlKbdBatteryTask {
+    fn do_task(&self) -> Result<(), RogError> {
+        match self.events.recv_timeout(BATTERY_CHECK_TIME) {
+            Ok(_) => while self.events.try_recv().is_ok() {},
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => sleep(BATTERY_CHECK_TIME),
+        }
+
+        let status = match BatteryStatus::read(&self.battery) {
+            Ok(status) => status,
+            Err(err) => {
+                warn!("LED battery effects: {}", err);
+                return Ok(());
+            }
+        };
+        if let Ok(mut lock) = self.inner.lock() {
+            lock.apply_battery(&status)
+                .unwrap_or_else(|err| warn!("LED battery effects: {}", err));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatteryCondition, BatteryOverride, BatteryRule, BatteryStatus};
+    use rog_aura::{AuraEffect, AuraModeNum};
+
+    fn rule(condition: BatteryCondition, mode: AuraModeNum) -> BatteryRule {
+        BatteryRule {
+            condition,
+            effect: AuraEffect::default_with_mode(mode),
+        }
+    }
+
+    fn status(on_ac: bool, charging: bool, full: bool, capacity: u8) -> BatteryStatus {
+        BatteryStatus {
+            on_ac,
+            charging,
+            full,
+            capacity,
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let low = status(false, false, false, 15);
+        assert!(low.matches(BatteryCondition::Unplugged));
+        assert!(low.matches(BatteryCondition::Below(20)));
+        assert!(!low.matches(BatteryCondition::Below(15)));
+        assert!(!low.matches(BatteryCondition::Charging));
+
+        let at_limit = status(true, false, true, 80);
+        assert!(at_limit.matches(BatteryCondition::Full));
+        assert!(!at_limit.matches(BatteryCondition::Below(90)));
+        assert!(!at_limit.matches(BatteryCondition::Unplugged));
+    }
+
+    #[test]
+    fn override_and_restore() {
+        let rules = vec![
+            rule(BatteryCondition::Below(20), AuraModeNum::Breathe),
+            rule(BatteryCondition::Full, AuraModeNum::Pulse),
+        ];
+        let breathe = Some(Some(rules[0].effect.clone()));
+        let mut state = BatteryOverride::default();
+
+        // Nothing to restore at start
+        assert_eq!(state.due(&rules, &status(false, false, false, 50)), None);
+        assert_eq!(state.due(&rules, &status(false, false, false, 19)), breathe);
+        assert!(state.is_active());
+        assert_eq!(state.due(&rules, &status(false, false, false, 18)), None);
+        // Plugged in
+        assert_eq!(
+            state.due(&rules, &status(true, true, false, 19)),
+            Some(None)
+        );
+        assert!(!state.is_active());
+        assert_eq!(
+            state.due(&rules, &status(true, false, true, 80)),
+            Some(Some(rules[1].effect.clone()))
+        );
+
+        state.reset();
+        assert_eq!(
+            state.due(&rules, &status(true, false, true, 80)),
+            Some(Some(rules[1].effect.clone()))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{error::RogError, CtrlTask};
+use log::{info, warn};
+use nix::poll::{poll, PollFd, PollFlags};
+use rog_aura::LedBrightness;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+use zbus::Connection;
+
+use super::controller::CtrlKbdLed;
+
+/// Changed by the kernel when the brightness is set by the firmware, e.g. a hotkey
+static KBD_BRIGHT_HW_CHANGED_PATH: &str =
+    "/sys/class/leds/asus::kbd_backlight/brightness_hw_changed";
+/// Check the brightness at least this often. Writes by other programs do not
+/// notify `brightness_hw_changed`.
+const RECHECK_TIME: Duration = Duration::from_millis(1000);
+/// How often to check the brightness if `brightness_hw_changed` is missing
+const POLL_TIME: Duration = Duration::from_millis(250);
+
+/// The next brightness level up, wrapping from `High` to `Off`
+pub fn step_up(brightness: LedBrightness) -> LedBrightness {
+    match brightness {
+        LedBrightness::High => LedBrightness::Off,
+        _ => LedBrightness::from(brightness as u32 + 1),
+    }
+}
+
+/// The next brightness level down, wrapping from `Off` to `High`
+pub fn step_down(brightness: LedBrightness) -> LedBrightness {
+    match brightness {
+        LedBrightness::Off => LedBrightness::High,
+        _ => LedBrightness::from(brightness as u32 - 1),
+    }
+}
+
+/// Tracks changes to the keyboard brightness made outside of the daemon,
+/// stores them in the config, and signals `NotifyBrightness`
+pub struct CtrlKbdBrightnessTask {
+    inner: Arc<Mutex<CtrlKbdLed>>,
+    connection: Connection,
+    hw_changed: Option<File>,
+}
+
+impl CtrlKbdBrightnessTask {
+    /// `connection` should be the connection of the object server so that
+    /// signals come from the daemon's bus name
+    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>, connection: Connection) -> Self {
+        let hw_changed = match OpenOptions::new()
+            .read(true)
+            .open(KBD_BRIGHT_HW_CHANGED_PATH)
+        {
+            Ok(file) => Some(file),
+            Err(err) => {
+                info!(
+                    "{} not available ({}), polling keyboard brightness",
+                    KBD_BRIGHT_HW_CHANGED_PATH, err
+                );
+                None
+            }
+        };
+        Self {
+            inner,
+            connection,
+            hw_changed,
+        }
+    }
+
+    /// Block until `brightness_hw_changed` is notified, or `RECHECK_TIME` passes
+    fn wait_for_change(mut file: &File) -> Result<(), RogError> {
+        // sysfs only notifies pollers once the attribute has been read. The
+        // read fails with ENODATA until the first hardware change.
+        let mut buf = Vec::new();
+        file.seek(SeekFrom::Start(0))
+            .map_err(|err| RogError::Read(KBD_BRIGHT_HW_CHANGED_PATH.into(), err))?;
+        file.read_to_end(&mut buf).ok();
+
+        let mut fds = [PollFd::new(
+            file.as_raw_fd(),
+            PollFlags::POLLPRI | PollFlags::POLLERR,
+        )];
+        if let Err(err) = poll(&mut fds, RECHECK_TIME.as_millis() as i32) {
+            warn!("{}: {}", KBD_BRIGHT_HW_CHANGED_PATH, err);
+            sleep(POLL_TIME);
+        }
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlKbdBrightnessTask {
+    fn do_task(&self) -> Result<(), RogError> {
+        match &self.hw_changed {
+            Some(file) => Self::wait_for_change(file)?,
+            None => sleep(POLL_TIME),
+        }
+
+        // A change missed here is caught on the next pass as the config still differs
+        if let Ok(mut lock) = self.inner.try_lock() {
+            let changed = lock
+                .sync_brightness()
+                .map_err(|err| warn!("{}", err))
+                .unwrap_or(None);
+            if let Some(brightness) = changed {
+                self.connection
+                    .emit_signal(
+                        None,
+                        "/org/asuslinux/Led",
+                        "org.asuslinux.Daemon",
+                        "NotifyBrightness",
+                        &brightness,
+                    )
+                    .map_err(|err| warn!("NotifyBrightness: {}", err))
+                    .ok();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{step_down, step_up};
+    use rog_aura::LedBrightness;
+
+    #[test]
+    fn steps() {
+        assert_eq!(step_up(LedBrightness::Off), LedBrightness::Low);
+        assert_eq!(step_up(LedBrightness::Low), LedBrightness::Med);
+        assert_eq!(step_up(LedBrightness::Med), LedBrightness::High);
+        assert_eq!(step_down(LedBrightness::High), LedBrightness::Med);
+        assert_eq!(step_down(LedBrightness::Med), LedBrightness::Low);
+        assert_eq!(step_down(LedBrightness::Low), LedBrightness::Off);
+    }
+
+    #[test]
+    fn steps_wrap_at_limits() {
+        assert_eq!(step_up(LedBrightness::High), LedBrightness::Off);
+        assert_eq!(step_down(LedBrightness::Off), LedBrightness::High);
+    }
+}
@@ -1,13 +1,25 @@
 use crate::laptops::LaptopLedData;
 use log::{error, info, warn};
-use rog_aura::{AuraEffect, AuraModeNum, AuraZone, LedBrightness};
+use rog_aura::{
+    AuraEffect, AuraModeNum, AuraPowerStates, AuraZone, LedBrightness, PowerState, PowerZone,
+};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 
+use super::battery::BatteryRule;
+use super::idle::IdleConfig;
+use super::profile_modes::ProfileModes;
+use super::schedule::ScheduleEntry;
+
 pub static AURA_CONFIG_PATH: &str = "/etc/asusd/aura.conf";
 
+/// The config of an LED device other than the laptop keyboard
+pub fn device_config_path(id: &str) -> String {
+    format!("/etc/asusd/aura-{}.conf", id)
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct AuraConfigV320 {
     pub brightness: u32,
@@ -23,8 +35,13 @@ impl AuraConfigV320 {
             current_mode: self.current_mode,
             builtins: self.builtins,
             multizone: self.multizone,
-            awake_enabled: true,
-            sleep_anim_enabled: true,
+            multizone_on: false,
+            power_states: AuraPowerStates::default(),
+            schedule: Vec::new(),
+            idle: IdleConfig::default(),
+            battery: Vec::new(),
+            profile_modes: ProfileModes::default(),
+            path: AURA_CONFIG_PATH.to_string(),
         }
     }
 }
@@ -44,22 +61,82 @@ impl AuraConfigV352 {
             current_mode: self.current_mode,
             builtins: self.builtins,
             multizone: self.multizone,
-            awake_enabled: true,
-            sleep_anim_enabled: true,
+            multizone_on: false,
+            power_states: AuraPowerStates::default(),
+            schedule: Vec::new(),
+            idle: IdleConfig::default(),
+            battery: Vec::new(),
+            profile_modes: ProfileModes::default(),
+            path: AURA_CONFIG_PATH.to_string(),
         }
     }
 }
 
 #[derive(Deserialize, Serialize)]
-pub struct AuraConfig {
+pub struct AuraConfigV407 {
     pub brightness: LedBrightness,
     pub current_mode: AuraModeNum,
     pub builtins: BTreeMap<AuraModeNum, AuraEffect>,
     pub multizone: Option<AuraMultiZone>,
+    #[serde(default)]
+    pub multizone_on: bool,
     pub awake_enabled: bool,
     pub sleep_anim_enabled: bool,
 }
 
+impl AuraConfigV407 {
+    pub(crate) fn into_current(self) -> AuraConfig {
+        let mut power_states = AuraPowerStates::default();
+        power_states.set(PowerZone::Keyboard, PowerState::Awake, self.awake_enabled);
+        power_states.set(
+            PowerZone::Keyboard,
+            PowerState::Sleep,
+            self.sleep_anim_enabled,
+        );
+        AuraConfig {
+            brightness: self.brightness,
+            current_mode: self.current_mode,
+            builtins: self.builtins,
+            multizone: self.multizone,
+            multizone_on: self.multizone_on,
+            power_states,
+            schedule: Vec::new(),
+            idle: IdleConfig::default(),
+            battery: Vec::new(),
+            profile_modes: ProfileModes::default(),
+            path: AURA_CONFIG_PATH.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AuraConfig {
+    pub brightness: LedBrightness,
+    pub current_mode: AuraModeNum,
+    pub builtins: BTreeMap<AuraModeNum, AuraEffect>,
+    pub multizone: Option<AuraMultiZone>,
+    /// The zones in `multizone` are in use rather than the builtin for `current_mode`
+    #[serde(default)]
+    pub multizone_on: bool,
+    /// Which LED zones are lit in each power state
+    pub power_states: AuraPowerStates,
+    /// Modes and brightness to switch to at times of day
+    #[serde(default)]
+    pub schedule: Vec<ScheduleEntry>,
+    /// Turn the keyboard off when there is no input
+    #[serde(default)]
+    pub idle: IdleConfig,
+    /// Modes shown in place of `current_mode` while the battery is in a state
+    #[serde(default)]
+    pub battery: Vec<BatteryRule>,
+    /// Modes switched to when the platform profile changes
+    #[serde(default)]
+    pub profile_modes: ProfileModes,
+    /// Where the config is saved, each LED device has its own file
+    #[serde(skip)]
+    pub path: String,
+}
+
 impl Default for AuraConfig {
     fn default() -> Self {
         AuraConfig {
@@ -67,8 +144,13 @@ impl Default for AuraConfig {
             current_mode: AuraModeNum::Static,
             builtins: BTreeMap::new(),
             multizone: None,
-            awake_enabled: true,
-            sleep_anim_enabled: true,
+            multizone_on: false,
+            power_states: AuraPowerStates::default(),
+            schedule: Vec::new(),
+            idle: IdleConfig::default(),
+            battery: Vec::new(),
+            profile_modes: ProfileModes::default(),
+            path: AURA_CONFIG_PATH.to_string(),
         }
     }
 }
@@ -76,100 +158,126 @@ impl Default for AuraConfig {
 impl AuraConfig {
     /// `load` will attempt to read the config, and panic if the dir is missing
     pub fn load(supported_led_modes: &LaptopLedData) -> Self {
+        Self::load_from(AURA_CONFIG_PATH, supported_led_modes)
+    }
+
+    /// Load the config at `path`, as `load` does for the laptop keyboard
+    pub fn load_from(path: &str, supported_led_modes: &LaptopLedData) -> Self {
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(&AURA_CONFIG_PATH)
-            .unwrap_or_else(|_| {
-                panic!(
-                    "The file {} or directory /etc/asusd/ is missing",
-                    AURA_CONFIG_PATH
-                )
-            }); // okay to cause panic here
+            .open(path)
+            .unwrap_or_else(|_| panic!("The file {} or directory /etc/asusd/ is missing", path)); // okay to cause panic here
         let mut buf = String::new();
         if let Ok(read_len) = file.read_to_string(&mut buf) {
             if read_len == 0 {
-                return AuraConfig::create_default(&mut file, supported_led_modes);
+                return AuraConfig::create_default(&mut file, path, supported_led_modes);
             } else {
-                if let Ok(data) = serde_json::from_str(&buf) {
-                    return data;
+                let config = if let Ok(data) = serde_json::from_str::<AuraConfig>(&buf) {
+                    Some((data, false))
+                } else if let Ok(data) = serde_json::from_str::<AuraConfigV407>(&buf) {
+                    Some((data.into_current(), true))
                 } else if let Ok(data) = serde_json::from_str::<AuraConfigV320>(&buf) {
-                    let config = data.into_current();
-                    config.write();
-                    info!("Updated AuraConfig version");
-                    return config;
+                    Some((data.into_current(), true))
                 } else if let Ok(data) = serde_json::from_str::<AuraConfigV352>(&buf) {
-                    let config = data.into_current();
-                    config.write();
-                    info!("Updated AuraConfig version");
+                    Some((data.into_current(), true))
+                } else {
+                    None
+                };
+                if let Some((mut config, updated)) = config {
+                    config.path = path.to_string();
+                    if config.init_multizone(supported_led_modes) || updated {
+                        config.write();
+                    }
+                    if updated {
+                        info!("Updated AuraConfig version");
+                    }
                     return config;
                 }
                 warn!(
                     "Could not deserialise {}.\nWill rename to {}-old and recreate config",
-                    AURA_CONFIG_PATH, AURA_CONFIG_PATH
+                    path, path
                 );
-                let cfg_old = AURA_CONFIG_PATH.to_string() + "-old";
-                std::fs::rename(AURA_CONFIG_PATH, cfg_old).unwrap_or_else(|err| {
+                let cfg_old = path.to_string() + "-old";
+                std::fs::rename(path, cfg_old).unwrap_or_else(|err| {
                     panic!(
                         "Could not rename. Please remove {} then restart service: Error {}",
-                        AURA_CONFIG_PATH, err
+                        path, err
                     )
                 });
             }
         }
-        AuraConfig::create_default(&mut file, supported_led_modes)
+        AuraConfig::create_default(&mut file, path, supported_led_modes)
     }
 
-    fn create_default(file: &mut File, support_data: &LaptopLedData) -> Self {
+    fn create_default(file: &mut File, path: &str, support_data: &LaptopLedData) -> Self {
         // create a default config here
-        let mut config = AuraConfig::default();
+        let mut config = AuraConfig {
+            path: path.to_string(),
+            ..Default::default()
+        };
 
         for n in &support_data.standard {
             config
                 .builtins
                 .insert(*n, AuraEffect::default_with_mode(*n));
         }
+        config.init_multizone(support_data);
 
         // Should be okay to unwrap this as is since it is a Default
         let json = serde_json::to_string(&config).unwrap();
         file.write_all(json.as_bytes())
-            .unwrap_or_else(|_| panic!("Could not write {}", AURA_CONFIG_PATH));
+            .unwrap_or_else(|_| panic!("Could not write {}", path));
         config
     }
 
+    /// Create the multizone store if the laptop supports zones and the config
+    /// doesn't have one yet. Returns true if the config was changed.
+    fn init_multizone(&mut self, support_data: &LaptopLedData) -> bool {
+        if support_data.multizone && self.multizone.is_none() {
+            self.multizone = Some(AuraMultiZone::default());
+            return true;
+        }
+        false
+    }
+
     pub fn read(&mut self) {
         let mut file = OpenOptions::new()
             .read(true)
-            .open(&AURA_CONFIG_PATH)
-            .unwrap_or_else(|err| panic!("Error reading {}: {}", AURA_CONFIG_PATH, err));
+            .open(&self.path)
+            .unwrap_or_else(|err| panic!("Error reading {}: {}", self.path, err));
         let mut buf = String::new();
         if let Ok(l) = file.read_to_string(&mut buf) {
             if l == 0 {
-                warn!("File is empty {}", AURA_CONFIG_PATH);
+                warn!("File is empty {}", self.path);
             } else {
-                let x: AuraConfig = serde_json::from_str(&buf)
-                    .unwrap_or_else(|_| panic!("Could not deserialise {}", AURA_CONFIG_PATH));
+                let mut x: AuraConfig = serde_json::from_str(&buf)
+                    .unwrap_or_else(|_| panic!("Could not deserialise {}", self.path));
+                x.path = std::mem::take(&mut self.path);
                 *self = x;
             }
         }
     }
 
     pub fn write(&self) {
-        let mut file = File::create(AURA_CONFIG_PATH).expect("Couldn't overwrite config");
+        let mut file = File::create(&self.path).expect("Couldn't overwrite config");
         let json = serde_json::to_string_pretty(self).expect("Parse config to JSON failed");
         file.write_all(json.as_bytes())
             .unwrap_or_else(|err| error!("Could not write config: {}", err));
     }
 
-    /// Multipurpose, will accept AuraEffect with zones and put in the correct store
+    /// Multipurpose, will accept AuraEffect with zones and put in the correct store.
+    /// Also records if zones or the zoneless builtin are the active setting.
     pub fn set_builtin(&mut self, effect: AuraEffect) {
         match effect.zone() {
             AuraZone::None => {
+                self.multizone_on = false;
                 self.builtins.insert(*effect.mode(), effect);
             }
             _ => {
                 if let Some(multi) = self.multizone.as_mut() {
+                    self.multizone_on = true;
                     multi.set(effect)
                 }
             }
@@ -6,35 +6,47 @@ use crate::{
     laptops::{LaptopLedData, ASUS_KEYBOARD_DEVICES},
     CtrlTask,
 };
-use log::{info, warn};
+use log::warn;
 use logind_zbus::ManagerProxy;
+use nix::poll::{poll, PollFd, PollFlags};
 use rog_aura::{
-    usb::{
-        LED_APPLY, LED_AWAKE_OFF_SLEEP_OFF, LED_AWAKE_OFF_SLEEP_ON, LED_AWAKE_ON_SLEEP_OFF,
-        LED_AWAKE_ON_SLEEP_ON, LED_SET,
-    },
-    AuraEffect, LedBrightness, LED_MSG_LEN,
+    layouts::DEFAULT_LAYOUT,
+    usb::{aura_brightness_bytes, LED_APPLY, LED_INIT, LED_SET},
+    AuraEffect, AuraModeNum, AuraPowerStates, AuraZone, KeyColourArray, LedBrightness, LED_MSG_LEN,
 };
+use rog_dbus::zbus_led::{led_device_path, LED_PATH};
+use rog_profiles::Profile;
 use rog_supported::LedSupportedFunctions;
 use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{fs::OpenOptions, thread::spawn};
 use zbus::Connection;
 
 use crate::GetSupported;
 
+use super::battery::{BatteryOverride, BatteryStatus};
+use super::brightness::{step_down, step_up};
 use super::config::AuraConfig;
+use super::idle::IdleDimmer;
+use super::schedule::{Clock, LocalClock, ScheduleChange, ScheduleState, TimeOfDay};
+use super::transport::{take_laptop_keyboard, HidRaw, LedDevice, LedTransport};
+
+/// How often the schedule is checked while waiting for sleep signals
+const SCHEDULE_CHECK_TIME: Duration = Duration::from_millis(1000);
 
 impl GetSupported for CtrlKbdLed {
     type A = LedSupportedFunctions;
 
     fn get_supported() -> Self::A {
         // let mode = <&str>::from(&<AuraModes>::from(*mode));
-        let multizone_led_mode = false;
-        let per_key_led_mode = false;
         let laptop = LaptopLedData::get_data();
+        let multizone_led_mode = laptop.multizone;
+        let per_key_led_mode = laptop.per_key;
+        let power_zones = laptop.supported_power_zones();
         let stock_led_modes = laptop.standard;
 
         LedSupportedFunctions {
@@ -42,26 +54,65 @@ impl GetSupported for CtrlKbdLed {
             stock_led_modes,
             multizone_led_mode,
             per_key_led_mode,
+            per_key_layout: laptop
+                .layout_name
+                .unwrap_or_else(|| DEFAULT_LAYOUT.to_string()),
+            power_zones,
+            devices: HidRaw::find_all(&ASUS_KEYBOARD_DEVICES)
+                .map(|devices| devices.into_iter().map(|device| device.id).collect())
+                .unwrap_or_default(),
         }
     }
 }
 
 pub struct CtrlKbdLed {
-    pub led_node: Option<String>,
+    /// Carries LED packets to the keyboard, `None` if only brightness is available
+    pub transport: Option<Box<dyn LedTransport>>,
+    /// The ID of the LED device in use, `None` if there is none
+    pub device_id: Option<String>,
+    /// The LED class brightness node. Empty for devices other than the
+    /// laptop keyboard, their brightness is sent to the device instead.
     pub bright_node: String,
     pub supported_modes: LaptopLedData,
     pub flip_effect_write: bool,
+    /// The LED device takes power states for each zone rather than only
+    /// for the keyboard (0x19b6)
+    pub zoned_power: bool,
+    /// Set when the keyboard has been switched to custom per-key mode, a
+    /// builtin mode write will switch it back
+    pub per_key_mode_active: bool,
+    /// The mode bound to the profile while it is shown in place of the
+    /// saved mode
+    pub profile_mode: Option<AuraEffect>,
+    pub schedule: ScheduleState,
+    pub idle: IdleDimmer,
+    pub battery: BatteryOverride,
     pub config: AuraConfig,
 }
 
 pub struct CtrlKbdLedTask<'a> {
     inner: Arc<Mutex<CtrlKbdLed>>,
-    _c: Connection,
+    /// The object server connection, used for signals
+    server: Connection,
+    /// The connection the sleep signals arrive on
+    connection: Connection,
     manager: ManagerProxy<'a>,
+    clock: Box<dyn Clock>,
 }
 
 impl<'a> CtrlKbdLedTask<'a> {
-    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>) -> Self {
+    /// `server` should be the connection of the object server so that
+    /// signals come from the daemon's bus name
+    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>, server: Connection) -> Self {
+        Self::with_clock(inner, server, Box::new(LocalClock))
+    }
+
+    /// Create the task with the schedule following any clock
+    pub fn with_clock(
+        inner: Arc<Mutex<CtrlKbdLed>>,
+        server: Connection,
+        clock: Box<dyn Clock>,
+    ) -> Self {
         let connection =
             Connection::new_system().expect("CtrlKbdLedTask could not create dbus connection");
 
@@ -79,7 +130,10 @@ impl<'a> CtrlKbdLedTask<'a> {
                         //std::thread::sleep(Duration::from_millis(100));
                         loop {
                             if let Ok(ref mut lock) = c1.try_lock() {
-                                lock.set_brightness(lock.config.brightness).ok();
+                                lock.restore_state()
+                                    .unwrap_or_else(|err| warn!("LED resume: {}", err));
+                                // The entry may have changed while asleep
+                                lock.schedule.reset();
                                 break;
                             }
                         }
@@ -95,41 +149,59 @@ impl<'a> CtrlKbdLedTask<'a> {
 
         Self {
             inner,
-            _c: connection,
+            server,
+            connection,
             manager,
+            clock,
         }
     }
 
-    fn update_config(lock: &mut CtrlKbdLed) -> Result<(), RogError> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .open(&lock.bright_node)
-            .map_err(|err| match err.kind() {
-                std::io::ErrorKind::NotFound => {
-                    RogError::MissingLedBrightNode((&lock.bright_node).into(), err)
-                }
-                _ => RogError::Path((&lock.bright_node).into(), err),
-            })?;
-        let mut buf = [0u8; 1];
-        file.read_exact(&mut buf)
-            .map_err(|err| RogError::Read("buffer".into(), err))?;
-        if let Some(num) = char::from(buf[0]).to_digit(10) {
-            if lock.config.brightness != num.into() {
-                lock.config.read();
-                lock.config.brightness = num.into();
-                lock.config.write();
-            }
-            return Ok(());
+    /// Signal the mode and brightness that the schedule switched to
+    fn notify_schedule(&self, effect: Option<&AuraEffect>, brightness: LedBrightness) {
+        if let Some(effect) = effect {
+            self.server
+                .emit_signal(
+                    None,
+                    "/org/asuslinux/Led",
+                    "org.asuslinux.Daemon",
+                    "NotifyLed",
+                    effect,
+                )
+                .map_err(|err| warn!("NotifyLed: {}", err))
+                .ok();
         }
-        Err(RogError::ParseLed)
+        self.server
+            .emit_signal(
+                None,
+                "/org/asuslinux/Led",
+                "org.asuslinux.Daemon",
+                "NotifyBrightness",
+                &brightness,
+            )
+            .map_err(|err| warn!("NotifyBrightness: {}", err))
+            .ok();
     }
 }
 
 impl<'a> CtrlTask for CtrlKbdLedTask<'a> {
     fn do_task(&self) -> Result<(), RogError> {
-        self.manager.next_signal()?;
+        // Wait for a sleep signal, but not so long that the schedule is late
+        let mut fds = [PollFd::new(self.connection.as_raw_fd(), PollFlags::POLLIN)];
+        let ready = poll(&mut fds, SCHEDULE_CHECK_TIME.as_millis() as i32)
+            .map_err(|err| RogError::DoTask(format!("CtrlKbdLedTask: {}", err)))?;
+        if ready > 0 {
+            self.manager.next_signal()?;
+            if let Ok(ref mut lock) = self.inner.try_lock() {
+                lock.sync_brightness()?;
+            }
+        }
+
         if let Ok(ref mut lock) = self.inner.try_lock() {
-            return Self::update_config(lock);
+            match lock.apply_schedule(self.clock.now()) {
+                Ok(Some((effect, brightness))) => self.notify_schedule(effect.as_ref(), brightness),
+                Ok(None) => {}
+                Err(err) => warn!("LED schedule: {}", err),
+            }
         }
         Ok(())
     }
@@ -140,45 +212,44 @@ pub struct CtrlKbdLedReloader(pub Arc<Mutex<CtrlKbdLed>>);
 impl crate::Reloadable for CtrlKbdLedReloader {
     fn reload(&mut self) -> Result<(), RogError> {
         if let Ok(mut ctrl) = self.0.try_lock() {
-            let current = ctrl.config.current_mode;
-            if let Some(mode) = ctrl.config.builtins.get(&current).cloned() {
-                ctrl.do_command(mode).ok();
-            }
-
-            ctrl.set_states_enabled(ctrl.config.awake_enabled, ctrl.config.sleep_anim_enabled)
-                .map_err(|err| warn!("{}", err))
-                .ok();
+            ctrl.restore_state().map_err(|err| warn!("{}", err)).ok();
         }
         Ok(())
     }
 }
 
-pub struct CtrlKbdLedZbus(pub Arc<Mutex<CtrlKbdLed>>);
+/// The dbus interface of an LED device, and the object path it is served at
+pub struct CtrlKbdLedZbus(pub Arc<Mutex<CtrlKbdLed>>, String);
 
 impl CtrlKbdLedZbus {
+    /// Served at `/org/asuslinux/Led`, for the laptop keyboard
     pub fn new(inner: Arc<Mutex<CtrlKbdLed>>) -> Self {
-        Self(inner)
+        Self(inner, LED_PATH.to_string())
+    }
+
+    /// Served at the path of the device ID
+    pub fn for_device(inner: Arc<Mutex<CtrlKbdLed>>, id: &str) -> Self {
+        Self(inner, led_device_path(id))
+    }
+
+    pub fn path(&self) -> &str {
+        &self.1
     }
 }
 
 impl CtrlKbdLed {
+    /// The laptop keyboard, taking its LED device from `devices` for LED
+    /// packets if there is one. On error `devices` is left as it was, so
+    /// each can still be set up with `for_device`.
     #[inline]
-    pub fn new(supported_modes: LaptopLedData, config: AuraConfig) -> Result<Self, RogError> {
-        // TODO: return error if *all* nodes are None
-        let mut led_node = None;
-        for prod in ASUS_KEYBOARD_DEVICES.iter() {
-            match Self::find_led_node(prod) {
-                Ok(node) => {
-                    led_node = Some(node);
-                    break;
-                }
-                Err(err) => warn!("led_node: {}", err),
-            }
-        }
-
+    pub fn new(
+        supported_modes: LaptopLedData,
+        config: AuraConfig,
+        devices: &mut Vec<LedDevice>,
+    ) -> Result<Self, RogError> {
         let bright_node = Self::get_kbd_bright_path();
 
-        if led_node.is_none() && bright_node.is_none() {
+        if devices.is_empty() && bright_node.is_none() {
             return Err(RogError::MissingFunction(
                 "All keyboard features missing, you may require a v5.11 series kernel or newer"
                     .into(),
@@ -191,14 +262,91 @@ impl CtrlKbdLed {
             ));
         }
 
-        let ctrl = CtrlKbdLed {
-            led_node,
-            bright_node: bright_node.unwrap(), // If was none then we already returned above
+        // If was none then we already returned above
+        let mut ctrl = Self::with_transport(supported_modes, config, None, bright_node.unwrap());
+        if let Some(device) = take_laptop_keyboard(devices) {
+            ctrl.set_device(device);
+        }
+        Ok(ctrl)
+    }
+
+    /// An LED device other than the laptop keyboard, such as a lightbar
+    /// controller or an external keyboard
+    pub fn for_device(
+        supported_modes: LaptopLedData,
+        config: AuraConfig,
+        device: LedDevice,
+    ) -> Self {
+        let mut ctrl = Self::with_transport(supported_modes, config, None, String::new());
+        ctrl.set_device(device);
+        ctrl
+    }
+
+    fn set_device(&mut self, device: LedDevice) {
+        self.zoned_power = device.product == "19b6";
+        self.transport = Some(Box::new(device.node));
+        self.device_id = Some(device.id);
+    }
+
+    /// Create the controller with any LED transport, such as a `Recorder` for testing
+    pub fn with_transport(
+        supported_modes: LaptopLedData,
+        config: AuraConfig,
+        transport: Option<Box<dyn LedTransport>>,
+        bright_node: String,
+    ) -> Self {
+        CtrlKbdLed {
+            transport,
+            device_id: None,
+            bright_node,
             supported_modes,
             flip_effect_write: false,
+            zoned_power: false,
+            per_key_mode_active: false,
+            profile_mode: None,
+            schedule: ScheduleState::default(),
+            idle: IdleDimmer::new(Instant::now()),
+            battery: BatteryOverride::default(),
             config,
-        };
-        Ok(ctrl)
+        }
+    }
+
+    /// Find the LED device again after it was re-enumerated, as the old
+    /// node may be gone, then restore the LED state to it. A controller
+    /// without a device takes the laptop keyboard if it isn't in `taken`, the
+    /// IDs of the devices of other controllers.
+    pub fn reconnect(&mut self, taken: &[String]) -> Result<(), RogError> {
+        let mut devices = HidRaw::find_all(&ASUS_KEYBOARD_DEVICES)?;
+        let device = match &self.device_id {
+            Some(id) => devices.into_iter().find(|device| device.id == *id),
+            None => {
+                devices.retain(|device| !taken.contains(&device.id));
+                take_laptop_keyboard(&mut devices)
+            }
+        }
+        .ok_or_else(|| RogError::MissingFunction("ASUS LED device node not found".into()))?;
+        self.set_device(device);
+        self.restore_state()
+    }
+
+    /// Write all of the saved LED state, as the keyboard loses it when reset
+    /// or re-enumerated: the init packets, power states, mode, and brightness
+    pub fn restore_state(&mut self) -> Result<(), RogError> {
+        if self.transport.is_some() {
+            for packet in LED_INIT.iter() {
+                self.write_bytes(packet)?;
+            }
+            self.set_power_states(&self.config.power_states)
+                .map_err(|err| warn!("LED power states: {}", err))
+                .ok();
+            self.write_base_mode()
+                .map_err(|err| warn!("LED mode: {}", err))
+                .ok();
+            // The base mode was written over any battery effect
+            self.battery.reset();
+        }
+        // Stay dimmed if idle, the saved level is restored on the next input
+        self.write_brightness(self.shown_brightness())
     }
 
     fn get_kbd_bright_path() -> Option<String> {
@@ -209,6 +357,9 @@ impl CtrlKbdLed {
     }
 
     pub(super) fn get_brightness(&self) -> Result<u8, RogError> {
+        if self.bright_node.is_empty() {
+            return Ok(self.shown_brightness().as_char_code());
+        }
         let mut file = OpenOptions::new()
             .read(true)
             .open(&self.bright_node)
@@ -224,7 +375,31 @@ impl CtrlKbdLed {
         Ok(buf[0])
     }
 
-    pub(super) fn set_brightness(&self, brightness: LedBrightness) -> Result<(), RogError> {
+    /// Set the brightness and save it in the config, devices without a
+    /// brightness node only have the config to read it back from
+    pub(super) fn set_brightness(&mut self, brightness: LedBrightness) -> Result<(), RogError> {
+        self.write_brightness(brightness)?;
+        self.idle.cancel(Instant::now());
+        self.schedule.brightness = None;
+        self.config.read();
+        self.config.brightness = brightness;
+        self.config.write();
+        Ok(())
+    }
+
+    /// The brightness that should be on the keyboard: the idle level while
+    /// dimmed, the level of a schedule entry, or else the saved level
+    pub fn shown_brightness(&self) -> LedBrightness {
+        self.idle
+            .level()
+            .or(self.schedule.brightness)
+            .unwrap_or(self.config.brightness)
+    }
+
+    fn write_brightness(&self, brightness: LedBrightness) -> Result<(), RogError> {
+        if self.bright_node.is_empty() {
+            return self.write_bytes(&aura_brightness_bytes(brightness as u8));
+        }
         let path = Path::new(&self.bright_node);
         let mut file =
             OpenOptions::new()
@@ -241,40 +416,78 @@ impl CtrlKbdLed {
         Ok(())
     }
 
-    pub fn next_brightness(&mut self) -> Result<(), RogError> {
-        let mut bright = (self.config.brightness as u32) + 1;
-        if bright > 3 {
-            bright = 0;
+    /// Read the brightness from the LED class node and store it in the config
+    /// if it was changed elsewhere, such as by a hotkey. Returns the new
+    /// brightness if it changed.
+    pub fn sync_brightness(&mut self) -> Result<Option<LedBrightness>, RogError> {
+        // The node holds the idle level while dimmed, which isn't saved
+        if self.idle.is_dimmed() {
+            return Ok(None);
         }
-        self.config.brightness = <LedBrightness>::from(bright);
+        let num = char::from(self.get_brightness()?)
+            .to_digit(10)
+            .ok_or(RogError::ParseLed)?;
+        let brightness = <LedBrightness>::from(num);
+        // The schedule's level isn't saved either, but a change from it is
+        if self.schedule.brightness.unwrap_or(self.config.brightness) != brightness {
+            self.idle.cancel(Instant::now());
+            self.schedule.brightness = None;
+            self.config.read();
+            self.config.brightness = brightness;
+            self.config.write();
+            return Ok(Some(brightness));
+        }
+        Ok(None)
+    }
+
+    pub fn next_brightness(&mut self) -> Result<(), RogError> {
+        self.idle.cancel(Instant::now());
+        let current = self.schedule.brightness.take();
+        self.config.brightness = step_up(current.unwrap_or(self.config.brightness));
         self.config.write();
-        self.set_brightness(self.config.brightness)
+        self.write_brightness(self.config.brightness)
     }
 
     pub fn prev_brightness(&mut self) -> Result<(), RogError> {
-        let mut bright = self.config.brightness as u32;
-        if bright == 0 {
-            bright = 3;
-        } else {
-            bright -= 1;
-        }
-        self.config.brightness = <LedBrightness>::from(bright);
+        self.idle.cancel(Instant::now());
+        let current = self.schedule.brightness.take();
+        self.config.brightness = step_down(current.unwrap_or(self.config.brightness));
         self.config.write();
-        self.set_brightness(self.config.brightness)
-    }
-
-    /// Set if awake/on LED active, and/or sleep animation active
-    pub(super) fn set_states_enabled(&self, awake: bool, sleep: bool) -> Result<(), RogError> {
-        let bytes = if awake && sleep {
-            LED_AWAKE_ON_SLEEP_ON
-        } else if awake && !sleep {
-            LED_AWAKE_ON_SLEEP_OFF
-        } else if !awake && sleep {
-            LED_AWAKE_OFF_SLEEP_ON
-        } else if !awake && !sleep {
-            LED_AWAKE_OFF_SLEEP_OFF
+        self.write_brightness(self.config.brightness)
+    }
+
+    /// Note input activity at `now`, restoring the brightness if it was
+    /// dimmed for being idle
+    pub fn idle_activity(&mut self, now: Instant) -> Result<(), RogError> {
+        if let Some(brightness) = self.idle.activity(now) {
+            self.set_idle_brightness(brightness)?;
+        }
+        Ok(())
+    }
+
+    /// Dim the keyboard if there has been no input for the configured timeout
+    pub fn idle_check(&mut self, now: Instant, on_ac: bool) -> Result<(), RogError> {
+        let timeout = self.config.idle.timeout(on_ac);
+        let fade = self.config.idle.fade;
+        if let Some(brightness) = self.idle.check(now, timeout, fade, self.shown_brightness()) {
+            self.set_idle_brightness(brightness)?;
+        }
+        Ok(())
+    }
+
+    /// Idle brightness changes are only kept by the dimmer, so that the saved
+    /// level is used after a restart and the change isn't signalled
+    fn set_idle_brightness(&mut self, brightness: LedBrightness) -> Result<(), RogError> {
+        self.write_brightness(brightness)
+    }
+
+    /// Set which zones are lit in each power state. Older keyboards only
+    /// support the keyboard awake and sleep animation states.
+    pub(super) fn set_power_states(&self, states: &AuraPowerStates) -> Result<(), RogError> {
+        let bytes = if self.zoned_power {
+            states.to_zone_packet()
         } else {
-            LED_AWAKE_ON_SLEEP_ON
+            states.to_keyboard_packet()
         };
         self.write_bytes(&bytes)?;
         self.write_bytes(&LED_SET)?;
@@ -283,65 +496,125 @@ impl CtrlKbdLed {
         Ok(())
     }
 
-    fn find_led_node(id_product: &str) -> Result<String, RogError> {
-        let mut enumerator = udev::Enumerator::new().map_err(|err| {
-            warn!("{}", err);
-            RogError::Udev("enumerator failed".into(), err)
-        })?;
-        enumerator.match_subsystem("hidraw").map_err(|err| {
-            warn!("{}", err);
-            RogError::Udev("match_subsystem failed".into(), err)
-        })?;
-
-        for device in enumerator.scan_devices().map_err(|err| {
-            warn!("{}", err);
-            RogError::Udev("scan_devices failed".into(), err)
-        })? {
-            if let Some(parent) = device
-                .parent_with_subsystem_devtype("usb", "usb_device")
-                .map_err(|err| {
-                    warn!("{}", err);
-                    RogError::Udev("parent_with_subsystem_devtype failed".into(), err)
-                })?
-            {
-                if parent
-                    .attribute_value("idProduct")
-                    .ok_or_else(|| RogError::NotFound("LED idProduct".into()))?
-                    == id_product
-                {
-                    if let Some(dev_node) = device.devnode() {
-                        info!("Using device at: {:?} for LED control", dev_node);
-                        return Ok(dev_node.to_string_lossy().to_string());
-                    }
-                }
+    pub(crate) fn do_command(&mut self, mode: AuraEffect) -> Result<(), RogError> {
+        self.set_and_save(mode)
+    }
+
+    /// Switch to the mode and brightness of the schedule entry for `now` if
+    /// a boundary was crossed, or back to the base mode and saved brightness
+    /// once an entry ends. Neither is saved, so the user's own settings are kept.
+    /// Returns the effect and brightness shown if they changed.
+    pub fn apply_schedule(
+        &mut self,
+        now: TimeOfDay,
+    ) -> Result<Option<(Option<AuraEffect>, LedBrightness)>, RogError> {
+        let entry = match self.schedule.due(&self.config.schedule, now) {
+            Some(ScheduleChange::Start(entry)) => Some(entry.clone()),
+            Some(ScheduleChange::End) => None,
+            None => return Ok(None),
+        };
+        let shown = match entry {
+            Some(entry) => {
+                self.write_mode(&entry.effect)?;
+                (Some(entry.effect), entry.brightness)
             }
+            None => (self.write_base_mode()?, self.config.brightness),
+        };
+        self.idle.cancel(Instant::now());
+        self.write_brightness(shown.1)?;
+        // The schedule wrote over any battery effect
+        self.battery.reset();
+        Ok(Some(shown))
+    }
+
+    /// Show the mode bound to `profile` if the binding is enabled, or the
+    /// saved mode if a bound mode was shown. An active schedule entry stays
+    /// shown over either. The bound mode is not saved. Returns the effect
+    /// written, if any.
+    pub fn apply_profile_mode(&mut self, profile: Profile) -> Result<Option<AuraEffect>, RogError> {
+        let bound = self.config.profile_modes.mode_for(profile).cloned();
+        if bound.is_none() && self.profile_mode.is_none() {
+            return Ok(None);
         }
-        Err(RogError::MissingFunction(
-            "ASUS LED device node not found".into(),
-        ))
+        self.profile_mode = bound;
+        let effect = self.write_base_mode()?;
+        // The profile mode wrote over any battery effect
+        self.battery.reset();
+        Ok(effect)
     }
 
-    pub(crate) fn do_command(&mut self, mode: AuraEffect) -> Result<(), RogError> {
-        self.set_and_save(mode)
+    /// Show the effect of the battery rule matching `status`, or the base
+    /// mode once no rule matches. The config is not changed.
+    pub fn apply_battery(&mut self, status: &BatteryStatus) -> Result<(), RogError> {
+        match self.battery.due(&self.config.battery, status) {
+            Some(Some(effect)) => self.write_mode(&effect),
+            Some(None) => self.write_base_mode().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the mode shown when nothing is temporarily over it: the active
+    /// schedule entry, else the mode bound to the profile, else the saved
+    /// mode. Returns the effect written, the saved builtin for the saved
+    /// mode.
+    pub(super) fn write_base_mode(&mut self) -> Result<Option<AuraEffect>, RogError> {
+        let temporary = self.schedule.shown.as_ref().or(self.profile_mode.as_ref());
+        if let Some(effect) = temporary.cloned() {
+            self.write_mode(&effect)?;
+            return Ok(Some(effect));
+        }
+        self.write_current_mode()?;
+        Ok(self.config.builtins.get(&self.config.current_mode).cloned())
+    }
+
+    /// Write the saved mode, or the multizone mode if in use
+    pub(super) fn write_current_mode(&mut self) -> Result<(), RogError> {
+        let current = self.config.current_mode;
+        if self.config.multizone_on {
+            if let Some(zones) = self.config.get_multizone(current).cloned() {
+                return self.write_multizone(&zones);
+            }
+        } else if let Some(mode) = self.config.builtins.get(&current).cloned() {
+            return self.write_mode(&mode);
+        }
+        Ok(())
     }
 
     /// Should only be used if the bytes you are writing are verified correct
     #[inline]
     fn write_bytes(&self, message: &[u8]) -> Result<(), RogError> {
-        if let Some(led_node) = &self.led_node {
-            if let Ok(mut file) = OpenOptions::new().write(true).open(led_node) {
-                // println!("write: {:02x?}", &message);
-                return file
-                    .write_all(message)
-                    .map_err(|err| RogError::Write("write_bytes".into(), err));
-            }
+        if let Some(transport) = &self.transport {
+            return transport.write_bytes(message);
         }
         Err(RogError::NotSupported)
     }
 
+    /// Initialise the keyboard for custom per-key effects. This must be done every
+    /// time the mode switches from builtin to custom
+    pub(super) fn init_per_key(&mut self) -> Result<(), RogError> {
+        if !self.supported_modes.per_key {
+            return Err(RogError::NotSupported);
+        }
+        self.write_bytes(&KeyColourArray::get_init_msg())?;
+        self.per_key_mode_active = true;
+        Ok(())
+    }
+
+    /// Write a full per-key colour block, initialising custom mode first if
+    /// the keyboard is currently in a builtin mode
+    pub(super) fn write_per_key(&mut self, effect: &[Vec<u8>]) -> Result<(), RogError> {
+        if !KeyColourArray::is_valid_block(effect) {
+            return Err(RogError::InvalidPerKeyBlock);
+        }
+        if !self.per_key_mode_active {
+            self.init_per_key()?;
+        }
+        self.write_effect(effect)
+    }
+
     /// Write an effect block
     #[inline]
-    fn _write_effect(&mut self, effect: &[Vec<u8>]) -> Result<(), RogError> {
+    fn write_effect(&mut self, effect: &[Vec<u8>]) -> Result<(), RogError> {
         if self.flip_effect_write {
             for row in effect.iter().rev() {
                 self.write_bytes(row)?;
@@ -394,9 +667,10 @@ impl CtrlKbdLed {
             let next = self.supported_modes.standard[idx];
 
             self.config.read();
-            if let Some(data) = self.config.builtins.get(&next) {
-                self.write_mode(data)?;
+            if let Some(data) = self.config.builtins.get(&next).cloned() {
+                self.write_mode(&data)?;
                 self.config.current_mode = next;
+                self.config.multizone_on = false;
             }
             self.config.write();
         }
@@ -404,9 +678,22 @@ impl CtrlKbdLed {
         Ok(())
     }
 
-    #[inline]
-    fn write_mode(&self, mode: &AuraEffect) -> Result<(), RogError> {
+    /// Check the mode, and zone if any, can be used on this laptop. Zones are
+    /// only available for Static and Breathe
+    pub(super) fn is_mode_supported(&self, mode: &AuraEffect) -> bool {
         if !self.supported_modes.standard.contains(mode.mode()) {
+            return false;
+        }
+        if mode.zone() != AuraZone::None {
+            return self.supported_modes.multizone
+                && matches!(mode.mode(), AuraModeNum::Static | AuraModeNum::Breathe);
+        }
+        true
+    }
+
+    #[inline]
+    pub(super) fn write_mode(&mut self, mode: &AuraEffect) -> Result<(), RogError> {
+        if !self.is_mode_supported(mode) {
             return Err(RogError::NotSupported);
         }
         let bytes: [u8; LED_MSG_LEN] = mode.into();
@@ -414,6 +701,435 @@ impl CtrlKbdLed {
         self.write_bytes(&LED_SET)?;
         // Changes won't persist unless apply is set
         self.write_bytes(&LED_APPLY)?;
+        self.per_key_mode_active = false;
         Ok(())
     }
+
+    /// Write all four zones of a multizone mode, then set and apply once
+    fn write_multizone(&mut self, zones: &[AuraEffect; 4]) -> Result<(), RogError> {
+        for mode in zones.iter() {
+            if !self.is_mode_supported(mode) {
+                return Err(RogError::NotSupported);
+            }
+        }
+        for mode in zones.iter() {
+            let bytes: [u8; LED_MSG_LEN] = mode.into();
+            self.write_bytes(&bytes)?;
+        }
+        self.write_bytes(&LED_SET)?;
+        self.write_bytes(&LED_APPLY)?;
+        self.per_key_mode_active = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CtrlKbdLed;
+    use crate::{
+        ctrl_aura::{
+            battery::{BatteryCondition, BatteryRule, BatteryStatus},
+            config::AuraConfig,
+            schedule::ScheduleEntry,
+            transport::Recorder,
+        },
+        laptops::LaptopLedData,
+    };
+    use rog_aura::{
+        usb::{aura_brightness_bytes, LED_APPLY, LED_AWAKE_OFF_SLEEP_ON, LED_INIT, LED_SET},
+        AuraEffect, AuraModeNum, AuraPowerStates, AuraZone, Colour, Direction, KeyColourArray,
+        LedBrightness, PowerState, PowerZone, Speed, LED_MSG_LEN,
+    };
+    use rog_profiles::Profile;
+    use std::{
+        path::PathBuf,
+        time::{Duration, Instant},
+    };
+
+    const ALL_MODES: [AuraModeNum; 12] = [
+        AuraModeNum::Static,
+        AuraModeNum::Breathe,
+        AuraModeNum::Strobe,
+        AuraModeNum::Rainbow,
+        AuraModeNum::Star,
+        AuraModeNum::Rain,
+        AuraModeNum::Highlight,
+        AuraModeNum::Laser,
+        AuraModeNum::Ripple,
+        AuraModeNum::Pulse,
+        AuraModeNum::Comet,
+        AuraModeNum::Flash,
+    ];
+
+    fn controller(multizone: bool) -> (CtrlKbdLed, Recorder) {
+        let supported = LaptopLedData {
+            prod_family: "Test".into(),
+            board_names: vec!["Test".into()],
+            standard: ALL_MODES.to_vec(),
+            multizone,
+            per_key: true,
+            layout_name: None,
+            power_zones: vec![],
+        };
+        let recorder = Recorder::new();
+        let ctrl = CtrlKbdLed::with_transport(
+            supported,
+            AuraConfig::default(),
+            Some(Box::new(recorder.clone())),
+            String::new(),
+        );
+        (ctrl, recorder)
+    }
+
+    #[test]
+    fn mode_packets() {
+        let (mut ctrl, recorder) = controller(false);
+        for mode in ALL_MODES.iter() {
+            let effect = AuraEffect {
+                mode: *mode,
+                zone: AuraZone::None,
+                colour1: Colour(0x11, 0x22, 0x33),
+                colour2: Colour(0x44, 0x55, 0x66),
+                speed: Speed::High,
+                direction: Direction::Up,
+            };
+            ctrl.write_mode(&effect).unwrap();
+            let packets = recorder.take();
+            assert_eq!(
+                packets,
+                vec![
+                    vec![
+                        0x5d,
+                        0xb3,
+                        0x00,
+                        *mode as u8,
+                        0x11,
+                        0x22,
+                        0x33,
+                        0xf5,
+                        0x02,
+                        0x00,
+                        0x44,
+                        0x55,
+                        0x66,
+                        0x00,
+                        0x00,
+                        0x00,
+                        0x00
+                    ],
+                    LED_SET.to_vec(),
+                    LED_APPLY.to_vec(),
+                ],
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_mode_writes_nothing() {
+        let (mut ctrl, recorder) = controller(false);
+        ctrl.supported_modes.standard = vec![AuraModeNum::Static];
+        let effect = AuraEffect::default_with_mode(AuraModeNum::Rainbow);
+        assert!(ctrl.write_mode(&effect).is_err());
+
+        // Zones need a multizone keyboard
+        let effect = AuraEffect {
+            zone: AuraZone::Two,
+            ..Default::default()
+        };
+        assert!(ctrl.write_mode(&effect).is_err());
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn multizone_packets() {
+        let (mut ctrl, recorder) = controller(true);
+        let zones = [
+            AuraZone::One,
+            AuraZone::Two,
+            AuraZone::Three,
+            AuraZone::Four,
+        ];
+        let effects = [0, 1, 2, 3].map(|i| AuraEffect {
+            mode: AuraModeNum::Breathe,
+            zone: zones[i],
+            colour1: Colour(i as u8, 0, 0),
+            ..Default::default()
+        });
+        ctrl.write_multizone(&effects).unwrap();
+        let packets = recorder.take();
+        assert_eq!(packets.len(), 6);
+        for (i, packet) in packets[..4].iter().enumerate() {
+            assert_eq!(&packet[..5], &[0x5d, 0xb3, i as u8 + 1, 0x01, i as u8]);
+        }
+        assert_eq!(packets[4], LED_SET);
+        assert_eq!(packets[5], LED_APPLY);
+    }
+
+    #[test]
+    fn power_state_packets() {
+        let (mut ctrl, recorder) = controller(false);
+        let mut states = AuraPowerStates::default();
+        states.set(PowerZone::Keyboard, PowerState::Awake, false);
+        ctrl.set_power_states(&states).unwrap();
+        assert_eq!(
+            recorder.take(),
+            vec![
+                LED_AWAKE_OFF_SLEEP_ON.to_vec(),
+                LED_SET.to_vec(),
+                LED_APPLY.to_vec()
+            ]
+        );
+
+        ctrl.zoned_power = true;
+        ctrl.set_power_states(&states).unwrap();
+        assert_eq!(recorder.take()[0], states.to_zone_packet());
+    }
+
+    #[test]
+    fn per_key_inits_once_and_alternates() {
+        let (mut ctrl, recorder) = controller(false);
+        let block = KeyColourArray::new().to_vecs();
+        ctrl.write_per_key(&block).unwrap();
+        let packets = recorder.take();
+        assert_eq!(packets[0], KeyColourArray::get_init_msg());
+        assert_eq!(&packets[1..], block.as_slice());
+
+        ctrl.write_per_key(&block).unwrap();
+        let packets = recorder.take();
+        let reversed: Vec<Vec<u8>> = block.iter().rev().cloned().collect();
+        assert_eq!(packets, reversed);
+
+        // A builtin mode write leaves per-key mode
+        ctrl.write_mode(&AuraEffect::default()).unwrap();
+        recorder.take();
+        ctrl.write_per_key(&block).unwrap();
+        assert_eq!(recorder.take()[0], KeyColourArray::get_init_msg());
+    }
+
+    #[test]
+    fn restore_writes_all_state() {
+        let (mut ctrl, recorder) = controller(false);
+        let effect = AuraEffect::default_with_mode(AuraModeNum::Pulse);
+        ctrl.config.current_mode = AuraModeNum::Pulse;
+        ctrl.config.set_builtin(effect.clone());
+        ctrl.config
+            .power_states
+            .set(PowerZone::Keyboard, PowerState::Awake, false);
+
+        // Without a brightness node the brightness is sent as a packet
+        ctrl.restore_state().unwrap();
+        let packets = recorder.take();
+        let mode: [u8; LED_MSG_LEN] = (&effect).into();
+        let mut expected: Vec<Vec<u8>> = LED_INIT.iter().map(|p| p.to_vec()).collect();
+        expected.extend_from_slice(&[
+            LED_AWAKE_OFF_SLEEP_ON.to_vec(),
+            LED_SET.to_vec(),
+            LED_APPLY.to_vec(),
+            mode.to_vec(),
+            LED_SET.to_vec(),
+            LED_APPLY.to_vec(),
+            aura_brightness_bytes(ctrl.config.brightness as u8).to_vec(),
+        ]);
+        assert_eq!(packets, expected);
+    }
+
+    /// Put the config in a directory of its own, as the controller reads it
+    /// back before saving
+    fn config_dir(ctrl: &mut CtrlKbdLed, name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("asusd-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        ctrl.config.path = dir.join("aura.conf").to_string_lossy().into();
+        ctrl.config.write();
+        dir
+    }
+
+    #[test]
+    fn idle_level_is_not_saved() {
+        let (mut ctrl, _recorder) = controller(false);
+        let dir = config_dir(&mut ctrl, "idle");
+        ctrl.bright_node = dir.join("brightness").to_string_lossy().into();
+        ctrl.config.brightness = LedBrightness::Med;
+        ctrl.config.idle.timeout_ac = 30;
+        ctrl.config.write();
+        std::fs::write(&ctrl.bright_node, "2").unwrap();
+
+        let start = Instant::now();
+        ctrl.idle_check(start + Duration::from_secs(30), true)
+            .unwrap();
+        assert_eq!(ctrl.get_brightness().unwrap(), b'0');
+        assert_eq!(ctrl.config.brightness, LedBrightness::Med);
+
+        // Reads the config back while the node is still dimmed
+        ctrl.do_command(AuraEffect::default_with_mode(AuraModeNum::Pulse))
+            .unwrap();
+        assert_eq!(ctrl.sync_brightness().unwrap(), None);
+        assert_eq!(ctrl.config.brightness, LedBrightness::Med);
+
+        ctrl.idle_activity(start + Duration::from_secs(31)).unwrap();
+        assert_eq!(ctrl.get_brightness().unwrap(), b'2');
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn brightness_saved_without_node() {
+        let (mut ctrl, recorder) = controller(false);
+        let dir = config_dir(&mut ctrl, "no-node");
+        ctrl.set_brightness(LedBrightness::Low).unwrap();
+        assert_eq!(
+            recorder.take(),
+            vec![aura_brightness_bytes(LedBrightness::Low as u8).to_vec()]
+        );
+        assert_eq!(ctrl.get_brightness().unwrap(), b'1');
+        assert_eq!(ctrl.sync_brightness().unwrap(), None);
+
+        // Still set after the config is read again
+        ctrl.config.read();
+        assert_eq!(ctrl.config.brightness, LedBrightness::Low);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn profile_mode_is_not_saved() {
+        let (mut ctrl, recorder) = controller(false);
+        let saved = AuraEffect::default_with_mode(AuraModeNum::Pulse);
+        ctrl.config.set_builtin(saved.clone());
+        ctrl.config.current_mode = AuraModeNum::Pulse;
+        let bound = AuraEffect::default_with_mode(AuraModeNum::Strobe);
+        ctrl.config.profile_modes.enabled = true;
+        ctrl.config
+            .profile_modes
+            .set(Profile::Performance, Some(bound.clone()));
+
+        assert_eq!(
+            ctrl.apply_profile_mode(Profile::Performance).unwrap(),
+            Some(bound.clone())
+        );
+        let mode: [u8; LED_MSG_LEN] = (&bound).into();
+        assert_eq!(recorder.take()[0], mode.to_vec());
+        assert_eq!(ctrl.config.current_mode, AuraModeNum::Pulse);
+
+        // A profile without a mode goes back to the saved mode, once
+        assert_eq!(
+            ctrl.apply_profile_mode(Profile::Quiet).unwrap(),
+            Some(saved.clone())
+        );
+        let mode: [u8; LED_MSG_LEN] = (&saved).into();
+        assert_eq!(recorder.take()[0], mode.to_vec());
+        assert_eq!(ctrl.apply_profile_mode(Profile::Balanced).unwrap(), None);
+        assert!(recorder.take().is_empty());
+    }
+
+    #[test]
+    fn schedule_mode_is_not_saved() {
+        let (mut ctrl, recorder) = controller(false);
+        let dir = config_dir(&mut ctrl, "schedule");
+        let saved = AuraEffect::default_with_mode(AuraModeNum::Pulse);
+        ctrl.config.set_builtin(saved.clone());
+        ctrl.config.current_mode = AuraModeNum::Pulse;
+        let scheduled = AuraEffect {
+            colour1: Colour(0x28, 0, 0x50),
+            ..AuraEffect::default_with_mode(AuraModeNum::Pulse)
+        };
+        ctrl.config.schedule = vec![ScheduleEntry {
+            start: "22:00".parse().unwrap(),
+            end: "07:00".parse().unwrap(),
+            effect: scheduled.clone(),
+            brightness: LedBrightness::Low,
+        }];
+
+        ctrl.config.brightness = LedBrightness::High;
+        ctrl.config.write();
+
+        let shown = ctrl.apply_schedule("23:00".parse().unwrap()).unwrap();
+        assert_eq!(shown, Some((Some(scheduled.clone()), LedBrightness::Low)));
+        let mode: [u8; LED_MSG_LEN] = (&scheduled).into();
+        let packets = recorder.take();
+        assert_eq!(packets[0], mode.to_vec());
+        assert_eq!(
+            packets.last(),
+            Some(&aura_brightness_bytes(LedBrightness::Low as u8).to_vec())
+        );
+        assert_eq!(ctrl.sync_brightness().unwrap(), None);
+        ctrl.config.read();
+        assert_eq!(ctrl.config.builtins.get(&AuraModeNum::Pulse), Some(&saved));
+        assert_eq!(ctrl.config.brightness, LedBrightness::High);
+
+        // Back to the saved mode and brightness in the morning
+        let shown = ctrl.apply_schedule("07:00".parse().unwrap()).unwrap();
+        assert_eq!(shown, Some((Some(saved.clone()), LedBrightness::High)));
+        let mode: [u8; LED_MSG_LEN] = (&saved).into();
+        let packets = recorder.take();
+        assert_eq!(packets[0], mode.to_vec());
+        assert_eq!(
+            packets.last(),
+            Some(&aura_brightness_bytes(LedBrightness::High as u8).to_vec())
+        );
+        assert_eq!(ctrl.apply_schedule("08:00".parse().unwrap()).unwrap(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restored_to_base_mode() {
+        let (mut ctrl, recorder) = controller(false);
+        let shown = |recorder: &Recorder| recorder.take()[0].clone();
+        let packet = |effect: &AuraEffect| {
+            let mode: [u8; LED_MSG_LEN] = effect.into();
+            mode.to_vec()
+        };
+        let saved = AuraEffect::default_with_mode(AuraModeNum::Pulse);
+        ctrl.config.set_builtin(saved.clone());
+        ctrl.config.current_mode = AuraModeNum::Pulse;
+        let bound = AuraEffect::default_with_mode(AuraModeNum::Strobe);
+        ctrl.config.profile_modes.enabled = true;
+        ctrl.config
+            .profile_modes
+            .set(Profile::Performance, Some(bound.clone()));
+        let scheduled = AuraEffect::default_with_mode(AuraModeNum::Static);
+        ctrl.config.schedule = vec![ScheduleEntry {
+            start: "22:00".parse().unwrap(),
+            end: "07:00".parse().unwrap(),
+            effect: scheduled.clone(),
+            brightness: LedBrightness::Low,
+        }];
+        let battery = AuraEffect::default_with_mode(AuraModeNum::Breathe);
+        ctrl.config.battery = vec![BatteryRule {
+            condition: BatteryCondition::Unplugged,
+            effect: battery.clone(),
+        }];
+        let status = |on_ac: bool| BatteryStatus {
+            on_ac,
+            charging: false,
+            full: false,
+            capacity: 50,
+        };
+
+        ctrl.apply_profile_mode(Profile::Performance).unwrap();
+        assert_eq!(shown(&recorder), packet(&bound));
+        ctrl.apply_schedule("23:00".parse().unwrap()).unwrap();
+        assert_eq!(shown(&recorder), packet(&scheduled));
+
+        // The schedule entry is shown again after a battery rule, not the saved mode
+        ctrl.apply_battery(&status(false)).unwrap();
+        assert_eq!(shown(&recorder), packet(&battery));
+        ctrl.apply_battery(&status(true)).unwrap();
+        assert_eq!(shown(&recorder), packet(&scheduled));
+
+        // A profile change keeps the entry
+        ctrl.apply_profile_mode(Profile::Quiet).unwrap();
+        assert_eq!(shown(&recorder), packet(&scheduled));
+        ctrl.apply_profile_mode(Profile::Performance).unwrap();
+        assert_eq!(shown(&recorder), packet(&scheduled));
+
+        // Then the bound mode once the entry ends, and the saved mode after that
+        ctrl.apply_schedule("07:00".parse().unwrap()).unwrap();
+        assert_eq!(shown(&recorder), packet(&bound));
+        ctrl.apply_battery(&status(false)).unwrap();
+        assert_eq!(shown(&recorder), packet(&battery));
+        ctrl.apply_battery(&status(true)).unwrap();
+        assert_eq!(shown(&recorder), packet(&bound));
+        ctrl.apply_profile_mode(Profile::Quiet).unwrap();
+        assert_eq!(shown(&recorder), packet(&saved));
+    }
 }
@@ -0,0 +1,158 @@
+use crate::{error::RogError, laptops::ASUS_KEYBOARD_DEVICES, CtrlTask};
+use log::{info, warn};
+use nix::poll::{poll, PollFd, PollFlags};
+use std::os::unix::io::AsRawFd;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use super::controller::CtrlKbdLed;
+
+/// Time for a new device to be ready for writes
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// Spawn a thread which sends what `filter` returns for each udev event in
+/// `subsystem`
+pub fn spawn_udev_listener<T, F>(
+    subsystem: &'static str,
+    send: Sender<T>,
+    filter: F,
+) -> Result<(), RogError>
+where
+    T: Send + 'static,
+    F: Fn(&udev::Event) -> Option<T> + Send + 'static,
+{
+    thread::Builder::new()
+        .name(format!("{} events", subsystem))
+        .spawn(move || {
+            // The socket can't be moved between threads so is made here
+            let mut socket = match udev::MonitorBuilder::new()
+                .and_then(|builder| builder.match_subsystem(subsystem))
+                .and_then(|builder| builder.listen())
+            {
+                Ok(socket) => socket,
+                Err(err) => {
+                    warn!("{}", RogError::Udev(format!("{} monitor", subsystem), err));
+                    return;
+                }
+            };
+            loop {
+                let mut fds = [PollFd::new(socket.as_raw_fd(), PollFlags::POLLIN)];
+                if let Err(err) = poll(&mut fds, -1) {
+                    warn!("{} monitor: {}", subsystem, err);
+                    return;
+                }
+                for event in socket.by_ref() {
+                    if let Some(item) = filter(&event) {
+                        if send.send(item).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// A keyboard LED device appearing or going away, with its product ID
+#[derive(Debug, Clone, PartialEq)]
+pub enum HotplugEvent {
+    Added(&'static str),
+    Removed(&'static str),
+}
+
+/// The product ID of the keyboard a hidraw syspath belongs to. The path
+/// includes the HID device name, `BUS:VENDOR:PRODUCT.N`, so this works for
+/// removed devices which have no attributes left to read.
+pub fn keyboard_product(syspath: &str) -> Option<&'static str> {
+    let syspath = syspath.to_uppercase();
+    ASUS_KEYBOARD_DEVICES
+        .iter()
+        .find(|prod| syspath.contains(&format!(":0B05:{}.", prod.to_uppercase())))
+        .copied()
+}
+
+/// Restores the LED state when the keyboard device is re-enumerated, such as
+/// on dock and undock or after resume on some models. Only the devices found
+/// at start have controllers, as their dbus objects are added before the
+/// server runs.
+pub struct CtrlKbdHotplugTask {
+    inner: Vec<Arc<Mutex<CtrlKbdLed>>>,
+    events: Receiver<HotplugEvent>,
+}
+
+impl CtrlKbdHotplugTask {
+    /// `inner` is the controller of each LED device
+    pub fn new(inner: Vec<Arc<Mutex<CtrlKbdLed>>>) -> Result<Self, RogError> {
+        let (send, events) = std::sync::mpsc::channel();
+        spawn_udev_listener("hidraw", send, |event| {
+            let product = keyboard_product(&event.syspath().to_string_lossy())?;
+            match event.event_type() {
+                udev::EventType::Add => Some(HotplugEvent::Added(product)),
+                udev::EventType::Remove => Some(HotplugEvent::Removed(product)),
+                _ => None,
+            }
+        })?;
+        Ok(Self { inner, events })
+    }
+}
+
+impl CtrlTask for CtrlKbdHotplugTask {
+    fn do_task(&self) -> Result<(), RogError> {
+        let event = self
+            .events
+            .recv()
+            .map_err(|err| RogError::DoTask(format!("CtrlKbdHotplugTask: {}", err)))?;
+        match event {
+            HotplugEvent::Removed(product) => info!("LED device {} removed", product),
+            HotplugEvent::Added(product) => {
+                info!("LED device {} added", product);
+                sleep(SETTLE_TIME);
+                // A device often has several hidraw nodes, one restore is enough
+                while self.events.try_recv().is_ok() {}
+                // Node numbers can move around for all devices, so every
+                // controller finds its device again
+                let mut taken: Vec<String> = self
+                    .inner
+                    .iter()
+                    .filter_map(|inner| inner.lock().ok()?.device_id.clone())
+                    .collect();
+                for inner in self.inner.iter() {
+                    if let Ok(mut lock) = inner.lock() {
+                        lock.reconnect(&taken)
+                            .unwrap_or_else(|err| warn!("LED device {}: {}", product, err));
+                        taken.extend(lock.device_id.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keyboard_product;
+
+    #[test]
+    fn product_from_syspath() {
+        assert_eq!(
+            keyboard_product("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.0/0003:0B05:19B6.0001/hidraw/hidraw0"),
+            Some("19b6")
+        );
+        assert_eq!(
+            keyboard_product("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.2/0003:0b05:1866.0003/hidraw/hidraw2"),
+            Some("1866")
+        );
+        // Other ASUS devices, and other vendors with the same product ID
+        assert_eq!(
+            keyboard_product("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-4/1-4:1.0/0003:0B05:193B.0004/hidraw/hidraw3"),
+            None
+        );
+        assert_eq!(
+            keyboard_product("/sys/devices/pci0000:00/0000:00:14.0/usb1/1-5/1-5:1.0/0003:046D:1866.0005/hidraw/hidraw4"),
+            None
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{error::RogError, CtrlTask};
+use log::{info, warn};
+use rog_aura::LedBrightness;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{read_dir, read_to_string, OpenOptions};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use super::controller::CtrlKbdLed;
+
+/// Device names of the internal keyboard, and the part of the name all
+/// touchpads share
+const KEYBOARD_NAMES: [&str; 2] = ["AT Translated Set 2 keyboard", "Asus Keyboard"];
+const TOUCHPAD_NAME: &str = "Touchpad";
+/// How often to check the idle time when there is no input
+const IDLE_CHECK_TIME: Duration = Duration::from_millis(500);
+/// Time between each brightness step when fading out
+pub const FADE_STEP: Duration = Duration::from_millis(750);
+
+/// Turning the keyboard backlight off when there is no input
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct IdleConfig {
+    /// Seconds without input before dimming while on AC, 0 to never dim
+    pub timeout_ac: u32,
+    /// Seconds without input before dimming while on battery, 0 to never dim
+    pub timeout_battery: u32,
+    /// Step down one brightness level at a time instead of switching off
+    pub fade: bool,
+}
+
+impl IdleConfig {
+    pub fn timeout(&self, on_ac: bool) -> Option<Duration> {
+        let secs = if on_ac {
+            self.timeout_ac
+        } else {
+            self.timeout_battery
+        };
+        if secs == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(secs as u64))
+    }
+}
+
+/// Decides when to dim and restore the brightness from input activity. All
+/// times are given by the caller so that it can be driven by synthetic events.
+#[derive(Debug)]
+pub struct IdleDimmer {
+    last_activity: Instant,
+    last_step: Instant,
+    /// The brightness before dimming, `Some` while dimmed
+    restore: Option<LedBrightness>,
+    /// The brightness dimmed to, which is never saved in the config
+    level: Option<LedBrightness>,
+}
+
+impl IdleDimmer {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            last_activity: now,
+            last_step: now,
+            restore: None,
+            level: None,
+        }
+    }
+
+    pub fn is_dimmed(&self) -> bool {
+        self.restore.is_some()
+    }
+
+    /// The brightness set for being idle, `None` if not dimmed
+    pub fn level(&self) -> Option<LedBrightness> {
+        self.level
+    }
+
+    /// Input was seen, returns the brightness to restore if dimmed
+    pub fn activity(&mut self, now: Instant) -> Option<LedBrightness> {
+        self.last_activity = now;
+        self.level = None;
+        self.restore.take()
+    }
+
+    /// The brightness was set by something else, it is kept rather than
+    /// restored on the next input
+    pub fn cancel(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.restore = None;
+        self.level = None;
+    }
+
+    /// Returns the brightness to set if there has been no input for `timeout`.
+    /// `current` is the brightness now, which may already be part way dimmed.
+    /// A `timeout` of `None` disables dimming, and restores if dimmed.
+    pub fn check(
+        &mut self,
+        now: Instant,
+        timeout: Option<Duration>,
+        fade: bool,
+        current: LedBrightness,
+    ) -> Option<LedBrightness> {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => {
+                self.level = None;
+                return self.restore.take();
+            }
+        };
+        if now.saturating_duration_since(self.last_activity) < timeout
+            || current == LedBrightness::Off
+        {
+            return None;
+        }
+        if self.restore.is_some() && now.saturating_duration_since(self.last_step) < FADE_STEP {
+            return None;
+        }
+        if self.restore.is_none() {
+            self.restore = Some(current);
+        }
+        self.last_step = now;
+        self.level = if fade {
+            Some(LedBrightness::from(current as u32 - 1))
+        } else {
+            Some(LedBrightness::Off)
+        };
+        self.level
+    }
+}
+
+/// The event nodes of the internal keyboard and touchpads, parsed from the
+/// contents of `/proc/bus/input/devices`
+pub fn find_input_devices(devices: &str) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for block in devices.split("\n\n") {
+        let name = match block.lines().find_map(|l| l.strip_prefix("N: Name=")) {
+            Some(name) => name.trim_matches('"'),
+            None => continue,
+        };
+        if !KEYBOARD_NAMES.contains(&name) && !name.contains(TOUCHPAD_NAME) {
+            continue;
+        }
+        let handlers = block.lines().find_map(|l| l.strip_prefix("H: Handlers="));
+        if let Some(event) =
+            handlers.and_then(|h| h.split_whitespace().find(|h| h.starts_with("event")))
+        {
+            found.push(Path::new("/dev/input").join(event));
+        }
+    }
+    found
+}
+
+/// Is the laptop running from a charger. Assumed true if there is no mains
+/// power supply to check.
+pub fn on_ac_power() -> bool {
+    let supplies = match read_dir("/sys/class/power_supply") {
+        Ok(supplies) => supplies,
+        Err(_) => return true,
+    };
+    let read = |path: PathBuf| read_to_string(path).unwrap_or_default().trim().to_string();
+    let mut found_mains = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        if read(path.join("type")) == "Mains" {
+            found_mains = true;
+            if read(path.join("online")) == "1" {
+                return true;
+            }
+        }
+    }
+    !found_mains
+}
+
+/// Spawn a thread which sends on `send` each time there is input on `path`
+pub fn spawn_activity_listener(path: PathBuf, send: Sender<()>) -> Result<(), RogError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open(&path)
+        .map_err(|err| RogError::Path(path.to_string_lossy().into(), err))?;
+    thread::Builder::new()
+        .name("keyboard idle input".into())
+        .spawn(move || {
+            // Every read is at least one whole input_event
+            let mut buf = [0u8; 1024];
+            loop {
+                match file.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if send.send(()).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        warn!("{}: {}", path.display(), err);
+                        break;
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Turns the keyboard backlight off after a time without input, and back on
+/// at the next input
+pub struct CtrlKbdIdleTask {
+    inner: Arc<Mutex<CtrlKbdLed>>,
+    activity: Receiver<()>,
+}
+
+impl CtrlKbdIdleTask {
+    /// Listens to all internal keyboard and touchpad event nodes
+    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>) -> Self {
+        let (send, activity) = std::sync::mpsc::channel();
+        let devices = read_to_string("/proc/bus/input/devices").unwrap_or_default();
+        for path in find_input_devices(&devices) {
+            info!("Watching {} for keyboard idle", path.display());
+            spawn_activity_listener(path, send.clone())
+                .map_err(|err| warn!("{}", err))
+                .ok();
+        }
+        Self::with_receiver(inner, activity)
+    }
+
+    /// Create the task with activity from any source
+    pub fn with_receiver(inner: Arc<Mutex<CtrlKbdLed>>, activity: Receiver<()>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+impl CtrlTask for CtrlKbdIdleTask {
+    fn do_task(&self) -> Result<(), RogError> {
+        let active = match self.activity.recv_timeout(IDLE_CHECK_TIME) {
+            Ok(_) => {
+                // Only the latest input matters
+                while self.activity.try_recv().is_ok() {}
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                // No input devices, there will never be activity
+                sleep(IDLE_CHECK_TIME);
+                false
+            }
+        };
+
+        // Waits for the lock, a missed input would leave the keyboard dark
+        if let Ok(mut lock) = self.inner.lock() {
+            let now = Instant::now();
+            if active {
+                lock.idle_activity(now)?;
+            } else {
+                lock.idle_check(now, on_ac_power())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_input_devices, IdleConfig, IdleDimmer, FADE_STEP};
+    use rog_aura::LedBrightness;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(30));
+
+    #[test]
+    fn dim_and_restore() {
+        let start = Instant::now();
+        let secs = |s: u64| start + Duration::from_secs(s);
+        let mut idle = IdleDimmer::new(start);
+
+        assert_eq!(
+            idle.check(secs(29), TIMEOUT, false, LedBrightness::High),
+            None
+        );
+        idle.activity(secs(20));
+        assert_eq!(
+            idle.check(secs(49), TIMEOUT, false, LedBrightness::High),
+            None
+        );
+        assert_eq!(
+            idle.check(secs(50), TIMEOUT, false, LedBrightness::High),
+            Some(LedBrightness::Off)
+        );
+        assert!(idle.is_dimmed());
+        assert_eq!(
+            idle.check(secs(60), TIMEOUT, false, LedBrightness::Off),
+            None
+        );
+        assert_eq!(idle.activity(secs(70)), Some(LedBrightness::High));
+        assert_eq!(idle.activity(secs(71)), None);
+    }
+
+    #[test]
+    fn fade_steps_down() {
+        let start = Instant::now();
+        let mut idle = IdleDimmer::new(start);
+        let mut now = start + Duration::from_secs(30);
+        let mut current = LedBrightness::High;
+        let mut steps = Vec::new();
+        while let Some(next) = idle.check(now, TIMEOUT, true, current) {
+            steps.push(next);
+            current = next;
+            // Too soon for another step
+            assert_eq!(idle.check(now, TIMEOUT, true, current), None);
+            now += FADE_STEP;
+        }
+        assert_eq!(
+            steps,
+            vec![LedBrightness::Med, LedBrightness::Low, LedBrightness::Off]
+        );
+        assert_eq!(idle.activity(now), Some(LedBrightness::High));
+    }
+
+    #[test]
+    fn disabled_and_cancelled() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(60);
+        let mut idle = IdleDimmer::new(start);
+        assert_eq!(idle.check(later, None, false, LedBrightness::Med), None);
+
+        // Already off, nothing to restore
+        assert_eq!(idle.check(later, TIMEOUT, false, LedBrightness::Off), None);
+        assert!(!idle.is_dimmed());
+
+        idle.check(later, TIMEOUT, false, LedBrightness::Med);
+        idle.cancel(later);
+        assert_eq!(idle.activity(later), None);
+
+        // Disabling while dimmed restores
+        idle.check(
+            later + Duration::from_secs(60),
+            TIMEOUT,
+            false,
+            LedBrightness::Low,
+        );
+        assert_eq!(
+            idle.check(later, None, false, LedBrightness::Off),
+            Some(LedBrightness::Low)
+        );
+
+        let config = IdleConfig {
+            timeout_ac: 0,
+            timeout_battery: 10,
+            fade: false,
+        };
+        assert_eq!(config.timeout(true), None);
+        assert_eq!(config.timeout(false), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn input_devices() {
+        let devices = "I: Bus=0011 Vendor=0001 Product=0001 Version=ab41
+N: Name=\"AT Translated Set 2 keyboard\"
+H: Handlers=sysrq kbd leds event3
+
+I: Bus=0018 Vendor=04f3 Product=3098 Version=0100
+N: Name=\"ELAN1201:00 04F3:3098 Touchpad\"
+H: Handlers=mouse0 event12
+
+I: Bus=0003 Vendor=046d Product=c52b Version=0111
+N: Name=\"Logitech USB Receiver\"
+H: Handlers=sysrq kbd event20
+";
+        assert_eq!(
+            find_input_devices(devices),
+            vec![
+                PathBuf::from("/dev/input/event3"),
+                PathBuf::from("/dev/input/event12")
+            ]
+        );
+    }
+}
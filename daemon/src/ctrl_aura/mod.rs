@@ -1,3 +1,11 @@
+pub mod battery;
+pub mod brightness;
 pub mod config;
 pub mod controller;
+pub mod hotplug;
+pub mod idle;
+pub mod openrgb;
+pub mod profile_modes;
+pub mod schedule;
+pub mod transport;
 pub mod zbus;
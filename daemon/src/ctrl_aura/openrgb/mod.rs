@@ -0,0 +1,495 @@
+//! A local OpenRGB SDK server so that OpenRGB, and anything else speaking its
+//! network protocol, can drive the keyboard through the LED controller
+//! instead of opening the hidraw node itself.
+
+pub mod protocol;
+
+use crate::error::RogError;
+use log::{info, warn};
+use rog_aura::{
+    layouts::KeyboardLayout, AuraEffect, AuraModeNum, AuraZone, Colour, Direction, KeyColourArray,
+    Speed,
+};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use self::protocol::*;
+use super::controller::CtrlKbdLed;
+
+/// Mode value used for the per-key direct mode, builtin modes use their `AuraModeNum`
+const DIRECT_MODE_VALUE: i32 = 0x100;
+const SPEEDS: [Speed; 3] = [Speed::Low, Speed::Med, Speed::High];
+/// Directions in the order of the OpenRGB `MODE_DIRECTION_*` values
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Right,
+    Direction::Up,
+    Direction::Down,
+];
+
+pub struct OpenRgbServer {
+    inner: Arc<Mutex<CtrlKbdLed>>,
+    layout: KeyboardLayout,
+    /// The colours last set by any client, in the order of `layout.keys`
+    colours: Mutex<Vec<Colour>>,
+}
+
+impl OpenRgbServer {
+    pub fn new(inner: Arc<Mutex<CtrlKbdLed>>, layout: KeyboardLayout) -> Self {
+        let colours = Mutex::new(vec![Colour(0, 0, 0); layout.keys.len()]);
+        Self {
+            inner,
+            layout,
+            colours,
+        }
+    }
+
+    /// Listen on localhost only, serving each client on its own thread
+    pub fn start(self, port: u16) -> Result<(), RogError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        info!("OpenRGB SDK server listening on {}", listener.local_addr()?);
+        Arc::new(self).serve(listener)?;
+        Ok(())
+    }
+
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> Result<(), RogError> {
+        thread::Builder::new()
+            .name("OpenRGB server".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let server = self.clone();
+                            thread::Builder::new()
+                                .name("OpenRGB client".into())
+                                .spawn(move || server.handle_client(stream))
+                                .map_err(|err| warn!("OpenRGB: {}", err))
+                                .ok();
+                        }
+                        Err(err) => warn!("OpenRGB: {}", err),
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    fn handle_client(&self, mut stream: TcpStream) {
+        let mut protocol = 0;
+        loop {
+            match self.next_request(&mut stream, &mut protocol) {
+                Ok(true) => {}
+                Ok(false) => break,
+                // The stream is broken or out of sync
+                Err(RogError::Io(err)) => {
+                    warn!("OpenRGB client: {}", err);
+                    break;
+                }
+                // A request that couldn't be applied, the client can carry on
+                Err(err) => warn!("OpenRGB client: {}", err),
+            }
+        }
+    }
+
+    /// Handle one request, returns false when the client disconnects
+    fn next_request<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        protocol: &mut u32,
+    ) -> Result<bool, RogError> {
+        let header = match Header::read(stream)? {
+            Some(header) => header,
+            None => return Ok(false),
+        };
+        if header.len > MAX_PACKET_LEN {
+            return Err(RogError::OpenRgb(format!(
+                "packet of {} bytes is too large",
+                header.len
+            )));
+        }
+        let mut buf = vec![0u8; header.len as usize];
+        stream.read_exact(&mut buf)?;
+        let mut data = PacketReader::new(&buf);
+
+        let id = match PacketId::from_u32(header.id) {
+            Some(id) => id,
+            None => {
+                warn!("OpenRGB: ignoring unknown packet {}", header.id);
+                return Ok(true);
+            }
+        };
+        match id {
+            PacketId::RequestControllerCount => {
+                let reply = PacketWriter::default().u32(1).0.clone();
+                stream.write_all(&packet(0, id, &reply))?;
+            }
+            PacketId::RequestControllerData => {
+                let version = data.u32().unwrap_or(0).min(*protocol);
+                let reply = self.describe()?.to_bytes(version);
+                stream.write_all(&packet(header.device, id, &reply))?;
+            }
+            PacketId::RequestProtocolVersion => {
+                *protocol = data.u32().unwrap_or(0).min(PROTOCOL_VERSION);
+                let reply = PacketWriter::default().u32(PROTOCOL_VERSION).0.clone();
+                stream.write_all(&packet(0, id, &reply))?;
+            }
+            PacketId::SetClientName => {
+                let name = String::from_utf8_lossy(data.rest());
+                info!("OpenRGB client: {}", name.trim_end_matches('\0'));
+            }
+            PacketId::UpdateLeds => {
+                data.u32()?;
+                self.set_colours(0, &data.colours()?)?;
+            }
+            PacketId::UpdateZoneLeds => {
+                data.u32()?;
+                if data.u32()? == 0 {
+                    self.set_colours(0, &data.colours()?)?;
+                }
+            }
+            PacketId::UpdateSingleLed => {
+                let led = data.i32()?;
+                let colour = data.colour()?;
+                if led >= 0 {
+                    self.set_colours(led as usize, &[colour])?;
+                }
+            }
+            PacketId::SetCustomMode => {
+                if let Ok(mut ctrl) = self.inner.lock() {
+                    ctrl.init_per_key()?;
+                }
+            }
+            // Clients send UpdateMode for every edit, only SaveMode is kept
+            PacketId::UpdateMode | PacketId::SaveMode => {
+                data.u32()?;
+                data.i32()?;
+                let save = id == PacketId::SaveMode;
+                self.set_mode(&Mode::read(&mut data)?, save)?;
+            }
+            // Zones are a fixed size and there is only ever one device
+            PacketId::ResizeZone | PacketId::DeviceListUpdated => {}
+        }
+        Ok(true)
+    }
+
+    /// Set LEDs starting at `first`, then write every key to the keyboard
+    fn set_colours(&self, first: usize, new: &[Colour]) -> Result<(), RogError> {
+        let mut colours = self
+            .colours
+            .lock()
+            .map_err(|err| RogError::OpenRgb(err.to_string()))?;
+        for (colour, new) in colours.iter_mut().skip(first).zip(new.iter()) {
+            *colour = *new;
+        }
+
+        let mut block = KeyColourArray::new();
+        for (pos, colour) in self.layout.keys.iter().zip(colours.iter()) {
+            block.set_in(&self.layout, pos.key, colour.0, colour.1, colour.2);
+        }
+        if let Ok(mut ctrl) = self.inner.lock() {
+            ctrl.write_per_key(&block.to_vecs())?;
+        }
+        Ok(())
+    }
+
+    /// Switch to `mode`, and save it as the current mode if `save`
+    fn set_mode(&self, mode: &Mode, save: bool) -> Result<(), RogError> {
+        if mode.value == DIRECT_MODE_VALUE {
+            if let Ok(mut ctrl) = self.inner.lock() {
+                ctrl.init_per_key()?;
+            }
+            return Ok(());
+        }
+        let aura_mode = <AuraModeNum>::from(mode.value as u8);
+        if aura_mode as i32 != mode.value {
+            return Err(RogError::NotSupported);
+        }
+        let effect = AuraEffect {
+            mode: aura_mode,
+            zone: AuraZone::None,
+            colour1: mode.colors.first().copied().unwrap_or_default(),
+            colour2: mode.colors.get(1).copied().unwrap_or(Colour(0, 0, 0)),
+            speed: SPEEDS.get(mode.speed as usize).copied().unwrap_or_default(),
+            direction: DIRECTIONS
+                .get(mode.direction as usize)
+                .copied()
+                .unwrap_or_default(),
+        };
+        if let Ok(mut ctrl) = self.inner.lock() {
+            if save {
+                ctrl.do_command(effect)?;
+            } else {
+                ctrl.write_mode(&effect)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe the keyboard: one matrix zone with an LED per key in the
+    /// layout if per-key is supported, and a mode per builtin
+    fn describe(&self) -> Result<Device, RogError> {
+        let ctrl = self
+            .inner
+            .lock()
+            .map_err(|err| RogError::OpenRgb(err.to_string()))?;
+
+        let mut modes = Vec::new();
+        let mut active_mode = 0;
+        if ctrl.supported_modes.per_key {
+            if ctrl.per_key_mode_active {
+                active_mode = modes.len() as i32;
+            }
+            modes.push(Mode {
+                name: "Direct".into(),
+                value: DIRECT_MODE_VALUE,
+                flags: MODE_FLAG_HAS_PER_LED_COLOR,
+                speed_min: 0,
+                speed_max: 0,
+                colors_min: 0,
+                colors_max: 0,
+                speed: 0,
+                direction: 0,
+                color_mode: MODE_COLORS_PER_LED,
+                colors: vec![],
+            });
+        }
+        for num in &ctrl.supported_modes.standard {
+            if !ctrl.per_key_mode_active && *num == ctrl.config.current_mode {
+                active_mode = modes.len() as i32;
+            }
+            let effect = ctrl
+                .config
+                .builtins
+                .get(num)
+                .cloned()
+                .unwrap_or_else(|| AuraEffect::default_with_mode(*num));
+            modes.push(builtin_mode(&effect));
+        }
+
+        let mut zones = Vec::new();
+        let mut leds = Vec::new();
+        let mut colors = Vec::new();
+        if ctrl.supported_modes.per_key {
+            let rows = self.layout.rows();
+            let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+            let mut matrix = Vec::with_capacity(rows.len() * width);
+            for row in &rows {
+                for i in 0..width {
+                    let led = row
+                        .get(i)
+                        .and_then(|key| self.layout.keys.iter().position(|p| p.key == *key))
+                        .map_or(NO_LED, |led| led as u32);
+                    matrix.push(led);
+                }
+            }
+            zones.push(Zone {
+                name: "Keyboard".into(),
+                leds: self.layout.keys.len() as u32,
+                height: rows.len() as u32,
+                width: width as u32,
+                matrix,
+            });
+            leds = self
+                .layout
+                .keys
+                .iter()
+                .map(|pos| format!("Key: {:?}", pos.key))
+                .collect();
+            colors = self
+                .colours
+                .lock()
+                .map_err(|err| RogError::OpenRgb(err.to_string()))?
+                .clone();
+        }
+
+        Ok(Device {
+            name: format!("ASUS ROG {}", ctrl.supported_modes.prod_family),
+            vendor: "ASUS".into(),
+            description: "ASUS ROG keyboard served by asusd".into(),
+            version: crate::VERSION.into(),
+            serial: String::new(),
+            location: "asusd".into(),
+            modes,
+            active_mode,
+            zones,
+            leds,
+            colors,
+        })
+    }
+}
+
+/// Describe a builtin mode with the settings that the mode uses
+fn builtin_mode(effect: &AuraEffect) -> Mode {
+    let (speed, colours, direction) = match effect.mode() {
+        AuraModeNum::Static | AuraModeNum::Pulse | AuraModeNum::Comet | AuraModeNum::Flash => {
+            (false, 1, false)
+        }
+        AuraModeNum::Breathe | AuraModeNum::Star => (true, 2, false),
+        AuraModeNum::Strobe | AuraModeNum::Rain => (true, 0, false),
+        AuraModeNum::Rainbow => (true, 0, true),
+        AuraModeNum::Highlight | AuraModeNum::Laser | AuraModeNum::Ripple => (true, 1, false),
+    };
+
+    let mut flags = 0;
+    if speed {
+        flags |= MODE_FLAG_HAS_SPEED;
+    }
+    if direction {
+        flags |= MODE_FLAG_HAS_DIRECTION_LR | MODE_FLAG_HAS_DIRECTION_UD;
+    }
+    if colours > 0 {
+        flags |= MODE_FLAG_HAS_MODE_SPECIFIC_COLOR;
+    }
+    Mode {
+        name: effect.mode_name(),
+        value: effect.mode_num() as i32,
+        flags,
+        speed_min: 0,
+        speed_max: if speed { SPEEDS.len() as u32 - 1 } else { 0 },
+        colors_min: colours,
+        colors_max: colours,
+        speed: SPEEDS.iter().position(|s| *s == effect.speed).unwrap_or(1) as u32,
+        direction: DIRECTIONS
+            .iter()
+            .position(|d| *d == effect.direction)
+            .unwrap_or(0) as u32,
+        color_mode: if colours > 0 {
+            MODE_COLORS_MODE_SPECIFIC
+        } else {
+            MODE_COLORS_NONE
+        },
+        colors: [effect.colour1, effect.colour2][..colours as usize].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{protocol::*, OpenRgbServer};
+    use crate::{
+        ctrl_aura::{config::AuraConfig, controller::CtrlKbdLed, transport::Recorder},
+        laptops::LaptopLedData,
+    };
+    use rog_aura::{layouts::KeyboardLayout, AuraModeNum, Colour, Key, KeyColourArray};
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    fn start() -> (TcpStream, Recorder, Arc<Mutex<CtrlKbdLed>>) {
+        let supported = LaptopLedData {
+            prod_family: "Test".into(),
+            board_names: vec!["Test".into()],
+            standard: vec![AuraModeNum::Static, AuraModeNum::Rainbow],
+            multizone: false,
+            per_key: true,
+            layout_name: None,
+            power_zones: vec![],
+        };
+        let recorder = Recorder::new();
+        let ctrl = CtrlKbdLed::with_transport(
+            supported,
+            AuraConfig::default(),
+            Some(Box::new(recorder.clone())),
+            String::new(),
+        );
+        let ctrl = Arc::new(Mutex::new(ctrl));
+        let server = OpenRgbServer::new(ctrl.clone(), KeyboardLayout::default());
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        Arc::new(server).serve(listener).unwrap();
+        (TcpStream::connect(addr).unwrap(), recorder, ctrl)
+    }
+
+    fn request(stream: &mut TcpStream, id: PacketId, data: &[u8]) -> Vec<u8> {
+        stream.write_all(&packet(0, id, data)).unwrap();
+        let header = Header::read(stream).unwrap().unwrap();
+        assert_eq!(header.id, id.as_u32());
+        let mut buf = vec![0u8; header.len as usize];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn describes_keyboard() {
+        let (mut stream, _, _) = start();
+        let reply = request(&mut stream, PacketId::RequestProtocolVersion, &[9, 0, 0, 0]);
+        assert_eq!(reply, PROTOCOL_VERSION.to_le_bytes());
+        let reply = request(&mut stream, PacketId::RequestControllerCount, &[]);
+        assert_eq!(reply, 1u32.to_le_bytes());
+
+        let reply = request(&mut stream, PacketId::RequestControllerData, &[1, 0, 0, 0]);
+        let mut data = PacketReader::new(&reply);
+        assert_eq!(data.u32().unwrap() as usize, reply.len());
+        assert_eq!(data.i32().unwrap(), DEVICE_TYPE_KEYBOARD);
+        assert_eq!(data.string().unwrap(), "ASUS ROG Test");
+        assert_eq!(data.string().unwrap(), "ASUS");
+        for _ in 0..4 {
+            data.string().unwrap();
+        }
+        assert_eq!(data.u16().unwrap(), 3);
+        let active = data.i32().unwrap();
+        let modes: Vec<Mode> = (0..3).map(|_| Mode::read(&mut data).unwrap()).collect();
+        assert_eq!(modes[0].name, "Direct");
+        assert_eq!(modes[active as usize].name, "Static");
+        assert_eq!(modes[2].value, AuraModeNum::Rainbow as i32);
+        assert_eq!(data.u16().unwrap(), 1);
+        assert_eq!(data.string().unwrap(), "Keyboard");
+    }
+
+    #[test]
+    fn update_leds_writes_per_key() {
+        let (mut stream, recorder, _) = start();
+        let layout = KeyboardLayout::default();
+        let colours = vec![Colour(1, 2, 3); layout.keys.len()];
+        let mut data = PacketWriter::default();
+        data.u32(0).colours(&colours);
+        stream
+            .write_all(&packet(0, PacketId::UpdateLeds, &data.0))
+            .unwrap();
+        // Replies are in order, so once this arrives the update is done
+        request(&mut stream, PacketId::RequestControllerCount, &[]);
+
+        let packets = recorder.take();
+        assert_eq!(packets[0], KeyColourArray::get_init_msg());
+        let mut expected = KeyColourArray::new();
+        for pos in &layout.keys {
+            expected.set_in(&layout, pos.key, 1, 2, 3);
+        }
+        assert_eq!(&packets[1..], expected.to_vecs().as_slice());
+        assert_eq!(
+            expected.key_in(&layout, Key::Esc).map(|(r, _, _)| *r),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn update_mode_is_not_saved() {
+        let (mut stream, recorder, ctrl) = start();
+        let mode = Mode {
+            name: "Rainbow".into(),
+            value: AuraModeNum::Rainbow as i32,
+            flags: 0,
+            speed_min: 0,
+            speed_max: 0,
+            colors_min: 0,
+            colors_max: 0,
+            speed: 0,
+            direction: 0,
+            color_mode: 0,
+            colors: vec![],
+        };
+        let mut data = PacketWriter::default();
+        data.u32(0).i32(2);
+        mode.write(&mut data);
+        stream
+            .write_all(&packet(0, PacketId::UpdateMode, &data.0))
+            .unwrap();
+        request(&mut stream, PacketId::RequestControllerCount, &[]);
+
+        let packets = recorder.take();
+        assert_eq!(packets[0][3], AuraModeNum::Rainbow as u8);
+        let ctrl = ctrl.lock().unwrap();
+        assert_eq!(ctrl.config.current_mode, AuraModeNum::Static);
+        assert!(!ctrl.config.builtins.contains_key(&AuraModeNum::Rainbow));
+    }
+}
@@ -0,0 +1,362 @@
+//! Encoding and decoding of the OpenRGB SDK network protocol, up to protocol
+//! version 1. All values are little endian.
+
+use rog_aura::Colour;
+use std::io::{Error, ErrorKind, Read, Result};
+
+pub const MAGIC: [u8; 4] = *b"ORGB";
+pub const HEADER_LEN: usize = 16;
+/// The highest protocol version the server speaks. Clients use the lower of
+/// this and their own version.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Refuse packets larger than this, a full device description is a few KiB
+pub const MAX_PACKET_LEN: u32 = 1 << 20;
+
+/// `DEVICE_TYPE_KEYBOARD`
+pub const DEVICE_TYPE_KEYBOARD: i32 = 5;
+/// `ZONE_TYPE_MATRIX`
+pub const ZONE_TYPE_MATRIX: i32 = 2;
+/// Marks an empty position in a zone matrix
+pub const NO_LED: u32 = 0xffff_ffff;
+
+pub const MODE_FLAG_HAS_SPEED: u32 = 1 << 0;
+pub const MODE_FLAG_HAS_DIRECTION_LR: u32 = 1 << 1;
+pub const MODE_FLAG_HAS_DIRECTION_UD: u32 = 1 << 2;
+pub const MODE_FLAG_HAS_PER_LED_COLOR: u32 = 1 << 5;
+pub const MODE_FLAG_HAS_MODE_SPECIFIC_COLOR: u32 = 1 << 6;
+
+pub const MODE_COLORS_NONE: u32 = 0;
+pub const MODE_COLORS_PER_LED: u32 = 1;
+pub const MODE_COLORS_MODE_SPECIFIC: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PacketId {
+    RequestControllerCount,
+    RequestControllerData,
+    RequestProtocolVersion,
+    SetClientName,
+    DeviceListUpdated,
+    ResizeZone,
+    UpdateLeds,
+    UpdateZoneLeds,
+    UpdateSingleLed,
+    SetCustomMode,
+    UpdateMode,
+    SaveMode,
+}
+
+impl PacketId {
+    pub fn from_u32(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(PacketId::RequestControllerCount),
+            1 => Some(PacketId::RequestControllerData),
+            40 => Some(PacketId::RequestProtocolVersion),
+            50 => Some(PacketId::SetClientName),
+            100 => Some(PacketId::DeviceListUpdated),
+            1000 => Some(PacketId::ResizeZone),
+            1050 => Some(PacketId::UpdateLeds),
+            1051 => Some(PacketId::UpdateZoneLeds),
+            1052 => Some(PacketId::UpdateSingleLed),
+            1100 => Some(PacketId::SetCustomMode),
+            1101 => Some(PacketId::UpdateMode),
+            1102 => Some(PacketId::SaveMode),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            PacketId::RequestControllerCount => 0,
+            PacketId::RequestControllerData => 1,
+            PacketId::RequestProtocolVersion => 40,
+            PacketId::SetClientName => 50,
+            PacketId::DeviceListUpdated => 100,
+            PacketId::ResizeZone => 1000,
+            PacketId::UpdateLeds => 1050,
+            PacketId::UpdateZoneLeds => 1051,
+            PacketId::UpdateSingleLed => 1052,
+            PacketId::SetCustomMode => 1100,
+            PacketId::UpdateMode => 1101,
+            PacketId::SaveMode => 1102,
+        }
+    }
+}
+
+/// The header which starts every packet in both directions
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Header {
+    pub device: u32,
+    pub id: u32,
+    /// Length of the data following the header
+    pub len: u32,
+}
+
+impl Header {
+    /// Read a header, returns `None` if the stream closed cleanly
+    pub fn read<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        let mut buf = [0u8; HEADER_LEN];
+        match reader.read_exact(&mut buf) {
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        if buf[..4] != MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        let mut data = PacketReader::new(&buf[4..]);
+        Ok(Some(Self {
+            device: data.u32()?,
+            id: data.u32()?,
+            len: data.u32()?,
+        }))
+    }
+}
+
+/// Build a complete packet from its header fields and data
+pub fn packet(device: u32, id: PacketId, data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + data.len());
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&device.to_le_bytes());
+    buf.extend_from_slice(&id.as_u32().to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+    buf
+}
+
+#[inline]
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Appends protocol values to a buffer
+#[derive(Default)]
+pub struct PacketWriter(pub Vec<u8>);
+
+impl PacketWriter {
+    pub fn u16(&mut self, v: u16) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn i32(&mut self, v: i32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    /// Strings are a u16 length including the null terminator, then the bytes
+    pub fn string(&mut self, s: &str) -> &mut Self {
+        self.u16(s.len() as u16 + 1);
+        self.0.extend_from_slice(s.as_bytes());
+        self.0.push(0);
+        self
+    }
+
+    /// Colours are R, G, B and a padding byte
+    pub fn colour(&mut self, c: Colour) -> &mut Self {
+        self.0.extend_from_slice(&[c.0, c.1, c.2, 0]);
+        self
+    }
+
+    pub fn colours(&mut self, colours: &[Colour]) -> &mut Self {
+        self.u16(colours.len() as u16);
+        for c in colours {
+            self.colour(*c);
+        }
+        self
+    }
+}
+
+/// Reads protocol values from packet data
+pub struct PacketReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.pos + len > self.buf.len() {
+            return Err(invalid("packet too short"));
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        let bytes = self.take(len)?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        Ok(String::from_utf8_lossy(bytes).to_string())
+    }
+
+    pub fn colour(&mut self) -> Result<Colour> {
+        let b = self.take(4)?;
+        Ok(Colour(b[0], b[1], b[2]))
+    }
+
+    pub fn colours(&mut self) -> Result<Vec<Colour>> {
+        let count = self.u16()? as usize;
+        (0..count).map(|_| self.colour()).collect()
+    }
+
+    /// Data which is anything but a whole string, as sent by SetClientName
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        rest
+    }
+}
+
+/// A mode as described to clients, and as sent back by UpdateMode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mode {
+    pub name: String,
+    pub value: i32,
+    pub flags: u32,
+    pub speed_min: u32,
+    pub speed_max: u32,
+    pub colors_min: u32,
+    pub colors_max: u32,
+    pub speed: u32,
+    pub direction: u32,
+    pub color_mode: u32,
+    pub colors: Vec<Colour>,
+}
+
+impl Mode {
+    pub fn write(&self, out: &mut PacketWriter) {
+        out.string(&self.name)
+            .i32(self.value)
+            .u32(self.flags)
+            .u32(self.speed_min)
+            .u32(self.speed_max)
+            .u32(self.colors_min)
+            .u32(self.colors_max)
+            .u32(self.speed)
+            .u32(self.direction)
+            .u32(self.color_mode)
+            .colours(&self.colors);
+    }
+
+    pub fn read(data: &mut PacketReader) -> Result<Self> {
+        Ok(Self {
+            name: data.string()?,
+            value: data.i32()?,
+            flags: data.u32()?,
+            speed_min: data.u32()?,
+            speed_max: data.u32()?,
+            colors_min: data.u32()?,
+            colors_max: data.u32()?,
+            speed: data.u32()?,
+            direction: data.u32()?,
+            color_mode: data.u32()?,
+            colors: data.colours()?,
+        })
+    }
+}
+
+/// A zone with its LEDs laid out in a matrix of `height` rows. Each matrix
+/// entry is an LED index or `NO_LED`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    pub name: String,
+    pub leds: u32,
+    pub height: u32,
+    pub width: u32,
+    pub matrix: Vec<u32>,
+}
+
+impl Zone {
+    pub fn write(&self, out: &mut PacketWriter) {
+        out.string(&self.name)
+            .i32(ZONE_TYPE_MATRIX)
+            .u32(self.leds)
+            .u32(self.leds)
+            .u32(self.leds);
+        if self.matrix.is_empty() {
+            out.u16(0);
+        } else {
+            out.u16((8 + self.matrix.len() * 4) as u16)
+                .u32(self.height)
+                .u32(self.width);
+            for led in &self.matrix {
+                out.u32(*led);
+            }
+        }
+    }
+}
+
+/// The controller data for a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    pub name: String,
+    pub vendor: String,
+    pub description: String,
+    pub version: String,
+    pub serial: String,
+    pub location: String,
+    pub modes: Vec<Mode>,
+    pub active_mode: i32,
+    pub zones: Vec<Zone>,
+    pub leds: Vec<String>,
+    pub colors: Vec<Colour>,
+}
+
+impl Device {
+    /// Encode for a RequestControllerData reply, the vendor is only sent
+    /// from protocol version 1
+    pub fn to_bytes(&self, protocol: u32) -> Vec<u8> {
+        let mut out = PacketWriter::default();
+        out.i32(DEVICE_TYPE_KEYBOARD).string(&self.name);
+        if protocol >= 1 {
+            out.string(&self.vendor);
+        }
+        out.string(&self.description)
+            .string(&self.version)
+            .string(&self.serial)
+            .string(&self.location)
+            .u16(self.modes.len() as u16)
+            .i32(self.active_mode);
+        for mode in &self.modes {
+            mode.write(&mut out);
+        }
+        out.u16(self.zones.len() as u16);
+        for zone in &self.zones {
+            zone.write(&mut out);
+        }
+        out.u16(self.leds.len() as u16);
+        for (i, led) in self.leds.iter().enumerate() {
+            out.string(led).u32(i as u32);
+        }
+        out.colours(&self.colors);
+
+        // The data starts with its own length, including the length itself
+        let mut data = PacketWriter::default();
+        data.u32(out.0.len() as u32 + 4);
+        data.0.extend(out.0);
+        data.0
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{error::RogError, CtrlTask};
+use log::warn;
+use rog_aura::AuraEffect;
+use rog_profiles::Profile;
+use serde_derive::{Deserialize, Serialize};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use zbus::Connection;
+
+use super::controller::CtrlKbdLed;
+
+/// A mode for each platform profile, switched to when the profile changes
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ProfileModes {
+    /// Switch modes on profile changes
+    pub enabled: bool,
+    pub quiet: Option<AuraEffect>,
+    pub balanced: Option<AuraEffect>,
+    pub performance: Option<AuraEffect>,
+}
+
+impl ProfileModes {
+    pub fn get(&self, profile: Profile) -> Option<&AuraEffect> {
+        match profile {
+            Profile::Quiet => self.quiet.as_ref(),
+            Profile::Balanced => self.balanced.as_ref(),
+            Profile::Performance => self.performance.as_ref(),
+        }
+    }
+
+    /// Bind `effect` to `profile`, or unbind it with `None`
+    pub fn set(&mut self, profile: Profile, effect: Option<AuraEffect>) {
+        match profile {
+            Profile::Quiet => self.quiet = effect,
+            Profile::Balanced => self.balanced = effect,
+            Profile::Performance => self.performance = effect,
+        }
+    }
+
+    /// The mode to switch to for `profile`, `None` if not bound or disabled
+    pub fn mode_for(&self, profile: Profile) -> Option<&AuraEffect> {
+        if !self.enabled {
+            return None;
+        }
+        self.get(profile)
+    }
+}
+
+/// Switches to the mode bound to each new platform profile
+pub struct CtrlKbdProfileTask {
+    inner: Arc<Mutex<CtrlKbdLed>>,
+    /// The object server connection, used for signals
+    server: Connection,
+    profiles: Receiver<Profile>,
+}
+
+impl CtrlKbdProfileTask {
+    /// `profiles` should be added as a listener of `CtrlPlatformProfile`
+    pub fn new(
+        inner: Arc<Mutex<CtrlKbdLed>>,
+        server: Connection,
+        profiles: Receiver<Profile>,
+    ) -> Self {
+        Self {
+            inner,
+            server,
+            profiles,
+        }
+    }
+}
+
+impl CtrlTask for CtrlKbdProfileTask {
+    fn do_task(&self) -> Result<(), RogError> {
+        let mut profile = self
+            .profiles
+            .recv()
+            .map_err(|err| RogError::DoTask(format!("CtrlKbdProfileTask: {}", err)))?;
+        // Only the latest profile matters
+        while let Ok(next) = self.profiles.try_recv() {
+            profile = next;
+        }
+
+        // Waits for the lock, a missed change would leave the wrong mode
+        if let Ok(mut lock) = self.inner.lock() {
+            match lock.apply_profile_mode(profile) {
+                Ok(Some(effect)) => self
+                    .server
+                    .emit_signal(
+                        None,
+                        "/org/asuslinux/Led",
+                        "org.asuslinux.Daemon",
+                        "NotifyLed",
+                        &effect,
+                    )
+                    .unwrap_or_else(|err| warn!("NotifyLed: {}", err)),
+                Ok(None) => {}
+                Err(err) => warn!("LED profile mode: {}", err),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProfileModes;
+    use rog_aura::{AuraEffect, AuraModeNum};
+    use rog_profiles::Profile;
+
+    #[test]
+    fn bound_modes() {
+        let red = AuraEffect::default_with_mode(AuraModeNum::Static);
+        let mut modes = ProfileModes::default();
+        modes.set(Profile::Performance, Some(red.clone()));
+
+        // Disabled by default
+        assert_eq!(modes.mode_for(Profile::Performance), None);
+        modes.enabled = true;
+        assert_eq!(modes.mode_for(Profile::Performance), Some(&red));
+        assert_eq!(modes.mode_for(Profile::Quiet), None);
+
+        modes.set(Profile::Performance, None);
+        assert_eq!(modes.mode_for(Profile::Performance), None);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::error::RogError;
+use nix::libc;
+use rog_aura::{AuraEffect, LedBrightness};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A time of day to the minute, in local time
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u8, minute: u8) -> Result<Self, RogError> {
+        if hour > 23 || minute > 59 {
+            return Err(RogError::InvalidSchedule(format!(
+                "{}:{:02} is not a time of day",
+                hour, minute
+            )));
+        }
+        Ok(Self { hour, minute })
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = RogError;
+
+    /// Parse a 24 hour `HH:MM` time
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || RogError::InvalidSchedule(format!("{} is not a HH:MM time", s));
+        let (hour, minute) = s.trim().split_once(':').ok_or_else(err)?;
+        let hour = hour.parse().map_err(|_| err())?;
+        let minute = minute.parse().map_err(|_| err())?;
+        Self::new(hour, minute)
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// A mode and brightness to switch to at `start`. The entry is active until
+/// `end`, and may run past midnight.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ScheduleEntry {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    pub effect: AuraEffect,
+    pub brightness: LedBrightness,
+}
+
+impl ScheduleEntry {
+    /// An entry which starts and ends at the same time covers the whole day
+    pub fn contains(&self, time: TimeOfDay) -> bool {
+        if self.start <= self.end {
+            self.start == self.end || (self.start <= time && time < self.end)
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// The first entry which contains `time`, earlier entries take priority
+/// where they overlap
+pub fn active_entry(entries: &[ScheduleEntry], time: TimeOfDay) -> Option<usize> {
+    entries.iter().position(|entry| entry.contains(time))
+}
+
+/// Where the schedule gets the time from, so it can be tested with any time
+pub trait Clock: Send {
+    fn now(&self) -> TimeOfDay;
+}
+
+/// The system clock in the local timezone
+pub struct LocalClock;
+
+impl Clock for LocalClock {
+    fn now(&self) -> TimeOfDay {
+        // Safe as localtime_r only writes to the tm given, and is thread safe
+        unsafe {
+            let time = libc::time(std::ptr::null_mut());
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&time, &mut tm);
+            TimeOfDay {
+                hour: tm.tm_hour as u8,
+                minute: tm.tm_min as u8,
+            }
+        }
+    }
+}
+
+/// What the schedule should change when it is checked
+#[derive(Debug, PartialEq)]
+pub enum ScheduleChange<'a> {
+    /// Switch to the mode and brightness of this entry
+    Start(&'a ScheduleEntry),
+    /// The entry that was shown has ended with no other entry active, the
+    /// saved mode and brightness should be shown again
+    End,
+}
+
+/// Tracks what the schedule last applied
+#[derive(Debug, Default)]
+pub struct ScheduleState {
+    /// The active entry when the schedule was last checked, `None` if it
+    /// must be checked and applied again
+    pub applied: Option<Option<usize>>,
+    /// Don't apply the schedule again until the next boundary
+    pub overridden: bool,
+    /// The mode of the entry on the keyboard, kept over `reset()` so that an
+    /// entry which ended while asleep is still undone
+    pub shown: Option<AuraEffect>,
+    /// The brightness of the shown entry, which is never saved. `None` once
+    /// the brightness is set by anything else.
+    pub brightness: Option<LedBrightness>,
+}
+
+impl ScheduleState {
+    /// Decide what must change at `time`. The active entry is applied when
+    /// it changes, or after `reset()`, unless overridden. Crossing a boundary
+    /// always clears the override.
+    pub fn due<'a>(
+        &mut self,
+        entries: &'a [ScheduleEntry],
+        time: TimeOfDay,
+    ) -> Option<ScheduleChange<'a>> {
+        let active = active_entry(entries, time);
+        match self.applied {
+            Some(applied) if applied == active => return None,
+            Some(_) => self.overridden = false,
+            None => {}
+        }
+        self.applied = Some(active);
+        if self.overridden {
+            return None;
+        }
+        match active {
+            Some(idx) => {
+                self.shown = Some(entries[idx].effect.clone());
+                self.brightness = Some(entries[idx].brightness);
+                Some(ScheduleChange::Start(&entries[idx]))
+            }
+            None if self.shown.is_some() => {
+                self.shown = None;
+                self.brightness = None;
+                Some(ScheduleChange::End)
+            }
+            None => None,
+        }
+    }
+
+    /// Apply the active entry again on the next check, such as after resume
+    /// or when the entries are edited
+    pub fn reset(&mut self) {
+        self.applied = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScheduleChange, ScheduleEntry, ScheduleState, TimeOfDay};
+    use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+
+    fn entry(start: &str, end: &str, mode: AuraModeNum) -> ScheduleEntry {
+        ScheduleEntry {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            effect: AuraEffect::default_with_mode(mode),
+            brightness: LedBrightness::Low,
+        }
+    }
+
+    fn at(time: &str) -> TimeOfDay {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_times() {
+        assert_eq!(at("07:05"), TimeOfDay::new(7, 5).unwrap());
+        assert_eq!(at("23:59").to_string(), "23:59");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("12:60".parse::<TimeOfDay>().is_err());
+        assert!("noon".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        let night = entry("22:00", "07:00", AuraModeNum::Static);
+        assert!(night.contains(at("23:30")));
+        assert!(night.contains(at("00:00")));
+        assert!(night.contains(at("06:59")));
+        assert!(!night.contains(at("07:00")));
+        assert!(!night.contains(at("21:59")));
+
+        let day = entry("07:00", "22:00", AuraModeNum::Breathe);
+        assert!(day.contains(at("07:00")));
+        assert!(!day.contains(at("22:00")));
+
+        let always = entry("12:00", "12:00", AuraModeNum::Breathe);
+        assert!(always.contains(at("03:00")));
+    }
+
+    #[test]
+    fn applied_at_boundaries() {
+        let entries = vec![
+            entry("22:00", "07:00", AuraModeNum::Static),
+            entry("07:00", "22:00", AuraModeNum::Breathe),
+        ];
+        let start = |idx: usize| Some(ScheduleChange::Start(&entries[idx]));
+        let mut state = ScheduleState::default();
+
+        // Always applied on the first check
+        assert_eq!(state.due(&entries, at("12:00")), start(1));
+        assert_eq!(state.due(&entries, at("12:01")), None);
+        assert_eq!(state.due(&entries, at("22:00")), start(0));
+        assert_eq!(state.due(&entries, at("03:00")), None);
+        assert_eq!(state.brightness, Some(LedBrightness::Low));
+
+        state.reset();
+        assert_eq!(state.due(&entries, at("03:00")), start(0));
+
+        // Overriding skips re-applying until the next boundary
+        state.overridden = true;
+        state.reset();
+        assert_eq!(state.due(&entries, at("04:00")), None);
+        assert_eq!(state.due(&entries, at("07:00")), start(1));
+        assert!(!state.overridden);
+    }
+
+    #[test]
+    fn gaps_restore_the_saved_mode() {
+        let entries = vec![entry("22:00", "07:00", AuraModeNum::Static)];
+        let mut state = ScheduleState::default();
+        // Nothing was shown, so there is nothing to undo
+        assert_eq!(state.due(&entries, at("12:00")), None);
+        assert_eq!(
+            state.due(&entries, at("22:00")),
+            Some(ScheduleChange::Start(&entries[0]))
+        );
+        assert_eq!(state.due(&entries, at("07:00")), Some(ScheduleChange::End));
+        assert_eq!(state.shown, None);
+        assert_eq!(state.brightness, None);
+        assert_eq!(state.due(&entries, at("07:01")), None);
+
+        // Ended while asleep
+        state.due(&entries, at("23:00"));
+        state.reset();
+        assert_eq!(state.due(&entries, at("08:00")), Some(ScheduleChange::End));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::error::RogError;
+use log::info;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Anything that can carry LED packets to the keyboard. `CtrlKbdLed` writes
+/// every packet through this so that it can be run without hardware.
+pub trait LedTransport: Send {
+    /// Should only be used if the bytes you are writing are verified correct
+    fn write_bytes(&self, message: &[u8]) -> Result<(), RogError>;
+}
+
+/// The keyboard USB HID device, written through its hidraw node
+pub struct HidRaw(String);
+
+/// An LED device found on the USB bus
+pub struct LedDevice {
+    /// Names the device on dbus and in the CLI
+    pub id: String,
+    pub product: &'static str,
+    /// Built in to the laptop rather than plugged in, from the USB
+    /// `removable` attribute
+    pub fixed: bool,
+    pub node: HidRaw,
+}
+
+/// The ID of a device from its product ID and USB port. The port is always
+/// part of it so that the ID doesn't change when another device with the
+/// same product ID is plugged in.
+pub fn device_id(product: &str, port: &str) -> String {
+    format!("{}@{}", product, port)
+}
+
+/// Take the laptop keyboard from `devices`, which is the first device built
+/// in to the laptop, or the first device if none are known to be built in
+pub fn take_laptop_keyboard(devices: &mut Vec<LedDevice>) -> Option<LedDevice> {
+    if devices.is_empty() {
+        return None;
+    }
+    let index = devices.iter().position(|d| d.fixed).unwrap_or(0);
+    Some(devices.remove(index))
+}
+
+impl HidRaw {
+    pub fn new(dev_node: &str) -> Self {
+        Self(dev_node.to_string())
+    }
+
+    /// Find a hidraw node for each USB device with a product ID in `products`,
+    /// ordered as in `products`
+    pub fn find_all(products: &[&'static str]) -> Result<Vec<LedDevice>, RogError> {
+        let mut enumerator = udev::Enumerator::new()
+            .map_err(|err| RogError::Udev("enumerator failed".into(), err))?;
+        enumerator
+            .match_subsystem("hidraw")
+            .map_err(|err| RogError::Udev("match_subsystem failed".into(), err))?;
+
+        // The first node of each USB device
+        let mut found: Vec<(LedDevice, String)> = Vec::new();
+        for device in enumerator
+            .scan_devices()
+            .map_err(|err| RogError::Udev("scan_devices failed".into(), err))?
+        {
+            let parent = match device.parent_with_subsystem_devtype("usb", "usb_device") {
+                Ok(Some(parent)) => parent,
+                _ => continue,
+            };
+            let product = match parent
+                .attribute_value("idProduct")
+                .and_then(|id| products.iter().find(|p| **p == id))
+            {
+                Some(product) => *product,
+                None => continue,
+            };
+            let port = parent.sysname().to_string_lossy().to_string();
+            if found.iter().any(|(_, p)| *p == port) {
+                continue;
+            }
+            if let Some(dev_node) = device.devnode() {
+                let fixed = parent.attribute_value("removable") == Some("fixed".as_ref());
+                found.push((
+                    LedDevice {
+                        id: device_id(product, &port),
+                        product,
+                        fixed,
+                        node: HidRaw(dev_node.to_string_lossy().to_string()),
+                    },
+                    port,
+                ));
+            }
+        }
+        found.sort_by_key(|(device, port)| {
+            (
+                products.iter().position(|p| *p == device.product),
+                port.clone(),
+            )
+        });
+
+        Ok(found
+            .into_iter()
+            .map(|(device, _)| {
+                info!("Found LED device {} at: {}", device.id, device.node.0);
+                device
+            })
+            .collect())
+    }
+}
+
+impl LedTransport for HidRaw {
+    fn write_bytes(&self, message: &[u8]) -> Result<(), RogError> {
+        if let Ok(mut file) = OpenOptions::new().write(true).open(&self.0) {
+            // println!("write: {:02x?}", &message);
+            return file
+                .write_all(message)
+                .map_err(|err| RogError::Write("write_bytes".into(), err));
+        }
+        Err(RogError::NotSupported)
+    }
+}
+
+/// Keeps every packet written in memory instead of sending it anywhere.
+/// Clones share the same record, so keep one to inspect what a controller wrote.
+#[derive(Clone, Default)]
+pub struct Recorder(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take all packets recorded so far, clearing the record
+    pub fn take(&self) -> Vec<Vec<u8>> {
+        self.0
+            .lock()
+            .map(|mut packets| std::mem::take(&mut *packets))
+            .unwrap_or_default()
+    }
+}
+
+impl LedTransport for Recorder {
+    fn write_bytes(&self, message: &[u8]) -> Result<(), RogError> {
+        if let Ok(mut packets) = self.0.lock() {
+            packets.push(message.to_vec());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{device_id, take_laptop_keyboard, HidRaw, LedDevice};
+
+    fn device(product: &'static str, port: &str, fixed: bool) -> LedDevice {
+        LedDevice {
+            id: device_id(product, port),
+            product,
+            fixed,
+            node: HidRaw::new("/dev/null"),
+        }
+    }
+
+    #[test]
+    fn ids() {
+        assert_eq!(device_id("1866", "3-2"), "1866@3-2");
+    }
+
+    #[test]
+    fn laptop_keyboard_is_built_in() {
+        let mut devices = vec![
+            device("1866", "1-1", false),
+            device("1866", "3-1", true),
+            device("19b6", "1-3", true),
+        ];
+        let keyboard = take_laptop_keyboard(&mut devices).unwrap();
+        assert_eq!(keyboard.id, "1866@3-1");
+        assert_eq!(devices.len(), 2);
+
+        // Nothing known to be built in
+        let mut devices = vec![device("19b6", "1-3", false), device("1866", "1-1", false)];
+        assert_eq!(take_laptop_keyboard(&mut devices).unwrap().id, "19b6@1-3");
+        assert!(take_laptop_keyboard(&mut Vec::new()).is_none());
+    }
+}
@@ -1,14 +1,21 @@
 use log::{error, warn};
-use rog_aura::{AuraEffect, LedBrightness, LedPowerStates};
+use rog_aura::{AuraEffect, AuraPowerStates, LedBrightness, LedPowerStates, PowerState, PowerZone};
+use rog_profiles::Profile;
 use zbus::dbus_interface;
 use zvariant::ObjectPath;
 
+use crate::error::RogError;
+
+use super::battery::BatteryRule;
 use super::controller::CtrlKbdLedZbus;
+use super::idle::IdleConfig;
+use super::schedule::{ScheduleEntry, TimeOfDay};
 
 impl crate::ZbusAdd for CtrlKbdLedZbus {
     fn add_to_server(self, server: &mut zbus::ObjectServer) {
+        let path = self.path().to_string();
         server
-            .at(&ObjectPath::from_str_unchecked("/org/asuslinux/Led"), self)
+            .at(&ObjectPath::from_str_unchecked(&path), self)
             .map_err(|err| {
                 error!("DbusKbdLed: add_to_server {}", err);
             })
@@ -23,56 +30,74 @@ impl crate::ZbusAdd for CtrlKbdLedZbus {
 impl CtrlKbdLedZbus {
     /// Set the keyboard brightness level (0-3)
     fn set_brightness(&mut self, brightness: LedBrightness) {
-        if let Ok(ctrl) = self.0.try_lock() {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            let before = ctrl.shown_brightness();
+            // Stored, so the brightness watcher doesn't signal this change again
             ctrl.set_brightness(brightness)
                 .map_err(|err| warn!("{}", err))
                 .ok();
+            if ctrl.shown_brightness() != before {
+                self.notify_brightness(ctrl.shown_brightness())
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
         }
     }
 
     /// Set the keyboard LED to enabled while the device is awake
     fn set_awake_enabled(&mut self, enabled: bool) {
-        if let Ok(mut ctrl) = self.0.try_lock() {
-            ctrl.set_states_enabled(enabled, ctrl.config.sleep_anim_enabled)
-                .map_err(|err| warn!("{}", err))
-                .ok();
-            ctrl.config.awake_enabled = enabled;
-            ctrl.config.write();
-
-            let states = LedPowerStates {
-                enabled: ctrl.config.awake_enabled,
-                sleep_anim_enabled: ctrl.config.sleep_anim_enabled,
-            };
-            self.notify_power_states(&states)
-                .unwrap_or_else(|err| warn!("{}", err));
-        }
+        self.set_power_state(PowerZone::Keyboard, PowerState::Awake, enabled)
+            .map_err(|err| warn!("{}", err))
+            .ok();
     }
 
     /// Set the keyboard LED suspend animation to enabled while the device is suspended
     fn set_sleep_enabled(&mut self, enabled: bool) {
+        self.set_power_state(PowerZone::Keyboard, PowerState::Sleep, enabled)
+            .map_err(|err| warn!("{}", err))
+            .ok();
+    }
+
+    /// Set if a zone is lit in a power state. The zone must be in the
+    /// supported `power_zones`
+    fn set_power_state(
+        &mut self,
+        zone: PowerZone,
+        state: PowerState,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
         if let Ok(mut ctrl) = self.0.try_lock() {
-            ctrl.set_states_enabled(ctrl.config.awake_enabled, enabled)
-                .map_err(|err| warn!("{}", err))
-                .ok();
-            ctrl.config.sleep_anim_enabled = enabled;
+            if !ctrl.supported_modes.supported_power_zones().contains(&zone) {
+                return Err(RogError::NotSupported.into());
+            }
+            let mut states = ctrl.config.power_states;
+            states.set(zone, state, enabled);
+            ctrl.set_power_states(&states)?;
+            ctrl.config.power_states = states;
             ctrl.config.write();
-            let states = LedPowerStates {
-                enabled: ctrl.config.awake_enabled,
-                sleep_anim_enabled: ctrl.config.sleep_anim_enabled,
-            };
-            self.notify_power_states(&states)
-                .unwrap_or_else(|err| warn!("{}", err));
+
+            if zone == PowerZone::Keyboard {
+                let states = LedPowerStates {
+                    enabled: states.get(PowerZone::Keyboard, PowerState::Awake),
+                    sleep_anim_enabled: states.get(PowerZone::Keyboard, PowerState::Sleep),
+                };
+                self.notify_power_states(&states)
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+            return Ok(());
         }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to set power state".to_string(),
+        ))
     }
 
+    /// Set a builtin mode. On multizone keyboards an effect with a zone
+    /// other than `None` sets only that zone of Static or Breathe
     fn set_led_mode(&mut self, effect: AuraEffect) {
         if let Ok(mut ctrl) = self.0.try_lock() {
-            match ctrl.do_command(effect) {
+            match ctrl.do_command(effect.clone()) {
                 Ok(_) => {
-                    if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
-                        self.notify_led(mode.clone())
-                            .unwrap_or_else(|err| warn!("{}", err));
-                    }
+                    self.notify_led(effect)
+                        .unwrap_or_else(|err| warn!("{}", err));
                 }
                 Err(err) => {
                     warn!("{}", err);
@@ -81,6 +106,163 @@ impl CtrlKbdLedZbus {
         }
     }
 
+    /// Initialise the keyboard for per-key effects. This is also done
+    /// automatically by `SetPerKey` if the keyboard is in a builtin mode
+    fn init_per_key(&mut self) -> zbus::fdo::Result<()> {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.init_per_key()?;
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to initialise per-key mode".to_string(),
+        ))
+    }
+
+    /// Write a full block of per-key packets as produced by `KeyColourArray`
+    fn set_per_key(&mut self, effect: Vec<Vec<u8>>) -> zbus::fdo::Result<()> {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.write_per_key(&effect)?;
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to write per-key block".to_string(),
+        ))
+    }
+
+    /// Add a schedule entry which switches to `effect` and `brightness` at
+    /// `start`, and is active until `end`. Times are 24 hour `HH:MM` local time.
+    fn add_schedule_entry(
+        &mut self,
+        start: &str,
+        end: &str,
+        effect: AuraEffect,
+        brightness: LedBrightness,
+    ) -> zbus::fdo::Result<()> {
+        let start: TimeOfDay = start.parse()?;
+        let end: TimeOfDay = end.parse()?;
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            if !ctrl.is_mode_supported(&effect) {
+                return Err(RogError::NotSupported.into());
+            }
+            ctrl.config.read();
+            ctrl.config.schedule.push(ScheduleEntry {
+                start,
+                end,
+                effect,
+                brightness,
+            });
+            ctrl.config.write();
+            ctrl.schedule.reset();
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to add schedule entry".to_string(),
+        ))
+    }
+
+    /// Remove the schedule entry at this index of `Schedule`
+    fn remove_schedule_entry(&mut self, index: u32) -> zbus::fdo::Result<()> {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.config.read();
+            if index as usize >= ctrl.config.schedule.len() {
+                return Err(RogError::InvalidSchedule(format!("no entry {}", index)).into());
+            }
+            ctrl.config.schedule.remove(index as usize);
+            ctrl.config.write();
+            ctrl.schedule.reset();
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to remove schedule entry".to_string(),
+        ))
+    }
+
+    /// Keep the current mode and brightness until the next schedule
+    /// boundary, instead of re-applying the active entry after resume or
+    /// when the schedule is edited
+    fn set_schedule_override(&mut self, enabled: bool) {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.schedule.overridden = enabled;
+            if !enabled {
+                ctrl.schedule.reset();
+            }
+        }
+    }
+
+    /// Turn the keyboard backlight off after this many seconds without
+    /// keyboard or touchpad input, separately for AC and battery. 0 disables.
+    /// With `fade` the brightness steps down a level at a time.
+    fn set_idle_timeout(&mut self, timeout_ac: u32, timeout_battery: u32, fade: bool) {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.config.read();
+            ctrl.config.idle = IdleConfig {
+                timeout_ac,
+                timeout_battery,
+                fade,
+            };
+            ctrl.config.write();
+        }
+    }
+
+    /// Replace the battery rules with a JSON array of `BatteryRule`. The
+    /// effect of the first rule matching the battery state is shown in place
+    /// of the current mode.
+    fn set_battery_effects(&mut self, rules: &str) -> zbus::fdo::Result<()> {
+        let rules: Vec<BatteryRule> = serde_json::from_str(rules)
+            .map_err(|err| zbus::fdo::Error::InvalidArgs(err.to_string()))?;
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            if rules
+                .iter()
+                .any(|rule| !ctrl.is_mode_supported(&rule.effect))
+            {
+                return Err(RogError::NotSupported.into());
+            }
+            ctrl.config.read();
+            ctrl.config.battery = rules;
+            ctrl.config.write();
+            ctrl.battery.reset();
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to set battery effects".to_string(),
+        ))
+    }
+
+    /// Switch to `effect` whenever the platform profile changes to `profile`
+    fn set_profile_mode(&mut self, profile: Profile, effect: AuraEffect) -> zbus::fdo::Result<()> {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            if !ctrl.is_mode_supported(&effect) {
+                return Err(RogError::NotSupported.into());
+            }
+            ctrl.config.read();
+            ctrl.config.profile_modes.set(profile, Some(effect));
+            ctrl.config.write();
+            return Ok(());
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to set profile mode".to_string(),
+        ))
+    }
+
+    /// Leave the mode as it is when the platform profile changes to `profile`
+    fn remove_profile_mode(&mut self, profile: Profile) {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.config.read();
+            ctrl.config.profile_modes.set(profile, None);
+            ctrl.config.write();
+        }
+    }
+
+    /// Turn switching modes on profile changes on or off, the bound modes
+    /// are kept
+    fn set_profile_modes_enabled(&mut self, enabled: bool) {
+        if let Ok(mut ctrl) = self.0.try_lock() {
+            ctrl.config.read();
+            ctrl.config.profile_modes.enabled = enabled;
+            ctrl.config.write();
+        }
+    }
+
     fn next_led_mode(&self) {
         if let Ok(mut ctrl) = self.0.try_lock() {
             ctrl.toggle_mode(false)
@@ -107,22 +289,33 @@ impl CtrlKbdLedZbus {
 
     fn next_led_brightness(&self) {
         if let Ok(mut ctrl) = self.0.try_lock() {
-            ctrl.next_brightness()
-                .unwrap_or_else(|err| warn!("{}", err));
+            match ctrl.next_brightness() {
+                Ok(_) => self
+                    .notify_brightness(ctrl.config.brightness)
+                    .unwrap_or_else(|err| warn!("{}", err)),
+                Err(err) => warn!("{}", err),
+            }
         }
     }
 
     fn prev_led_brightness(&self) {
         if let Ok(mut ctrl) = self.0.try_lock() {
-            ctrl.prev_brightness()
-                .unwrap_or_else(|err| warn!("{}", err));
+            match ctrl.prev_brightness() {
+                Ok(_) => self
+                    .notify_brightness(ctrl.config.brightness)
+                    .unwrap_or_else(|err| warn!("{}", err)),
+                Err(err) => warn!("{}", err),
+            }
         }
     }
 
     #[dbus_interface(property)]
     fn awake_enabled(&self) -> bool {
         if let Ok(ctrl) = self.0.try_lock() {
-            return ctrl.config.awake_enabled;
+            return ctrl
+                .config
+                .power_states
+                .get(PowerZone::Keyboard, PowerState::Awake);
         }
         true
     }
@@ -130,11 +323,32 @@ impl CtrlKbdLedZbus {
     #[dbus_interface(property)]
     fn sleep_enabled(&self) -> bool {
         if let Ok(ctrl) = self.0.try_lock() {
-            return ctrl.config.sleep_anim_enabled;
+            return ctrl
+                .config
+                .power_states
+                .get(PowerZone::Keyboard, PowerState::Sleep);
         }
         true
     }
 
+    /// Which zones are lit in which power states, the bits of `AuraPowerStates`
+    #[dbus_interface(property)]
+    fn power_states(&self) -> u32 {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return ctrl.config.power_states.bits();
+        }
+        AuraPowerStates::default().bits()
+    }
+
+    /// Is the keyboard currently in custom per-key mode
+    #[dbus_interface(property)]
+    fn per_key_active(&self) -> bool {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return ctrl.per_key_mode_active;
+        }
+        false
+    }
+
     /// Return the current mode data
     #[dbus_interface(property)]
     fn led_mode(&self) -> String {
@@ -149,6 +363,81 @@ impl CtrlKbdLedZbus {
         "SetKeyBacklight could not deserialise".to_string()
     }
 
+    /// Return the multizone settings, this is `null` if the keyboard has no zones
+    fn multizone(&self) -> zbus::fdo::Result<String> {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return serde_json::to_string(&ctrl.config.multizone).map_err(|err| {
+                zbus::fdo::Error::Failed(format!(
+                    "Could not serialise the multizone settings: {}",
+                    err
+                ))
+            });
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to read the multizone settings".to_string(),
+        ))
+    }
+
+    /// Return the schedule entries as JSON
+    fn schedule(&self) -> zbus::fdo::Result<String> {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return serde_json::to_string(&ctrl.config.schedule).map_err(|err| {
+                zbus::fdo::Error::Failed(format!("Could not serialise the schedule: {}", err))
+            });
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to read the schedule".to_string(),
+        ))
+    }
+
+    /// Is the schedule held until its next boundary
+    #[dbus_interface(property)]
+    fn schedule_override(&self) -> bool {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return ctrl.schedule.overridden;
+        }
+        false
+    }
+
+    /// Return the idle timeout settings as JSON
+    fn idle_timeout(&self) -> zbus::fdo::Result<String> {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return serde_json::to_string(&ctrl.config.idle).map_err(|err| {
+                zbus::fdo::Error::Failed(format!("Could not serialise the idle timeout: {}", err))
+            });
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to read the idle timeout".to_string(),
+        ))
+    }
+
+    /// Return the battery rules as JSON
+    fn battery_effects(&self) -> zbus::fdo::Result<String> {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return serde_json::to_string(&ctrl.config.battery).map_err(|err| {
+                zbus::fdo::Error::Failed(format!(
+                    "Could not serialise the battery effects: {}",
+                    err
+                ))
+            });
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to read the battery effects".to_string(),
+        ))
+    }
+
+    /// Return the modes bound to each profile as JSON
+    fn profile_modes(&self) -> zbus::fdo::Result<String> {
+        if let Ok(ctrl) = self.0.try_lock() {
+            return serde_json::to_string(&ctrl.config.profile_modes).map_err(|err| {
+                zbus::fdo::Error::Failed(format!("Could not serialise the profile modes: {}", err))
+            });
+        }
+        Err(zbus::fdo::Error::Failed(
+            "Failed to read the profile modes".to_string(),
+        ))
+    }
+
     /// Return a list of available modes
     #[dbus_interface(property)]
     fn led_modes(&self) -> String {
@@ -174,6 +463,10 @@ impl CtrlKbdLedZbus {
     #[dbus_interface(signal)]
     fn notify_led(&self, data: AuraEffect) -> zbus::Result<()>;
 
+    /// Emitted when the brightness changes, including by hotkeys
+    #[dbus_interface(signal)]
+    fn notify_brightness(&self, data: LedBrightness) -> zbus::Result<()>;
+
     #[dbus_interface(signal)]
     fn notify_power_states(&self, data: &LedPowerStates) -> zbus::Result<()>;
 }
@@ -4,7 +4,7 @@ use log::{info, warn};
 use rog_supported::ChargeSupportedFunctions;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 use zbus::dbus_interface;
@@ -108,6 +108,13 @@ impl CtrlCharge {
         }
     }
 
+    /// The power_supply directory of the battery with charge control
+    pub(crate) fn get_battery_dir() -> Result<PathBuf, RogError> {
+        let path = Path::new(Self::get_battery_path()?);
+        // The threshold is always inside the battery directory
+        Ok(path.parent().unwrap_or(path).to_path_buf())
+    }
+
     pub(super) fn set(&self, limit: u8, config: &mut Config) -> Result<(), RogError> {
         if !(20..=100).contains(&limit) {
             return Err(RogError::ChargeLimit(limit));
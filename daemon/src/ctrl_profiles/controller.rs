@@ -1,3 +1,4 @@
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 
 use crate::error::RogError;
@@ -11,6 +12,9 @@ use super::config::ProfileConfig;
 
 pub struct CtrlPlatformProfile {
     pub config: ProfileConfig,
+    /// Sent the active profile each time it changes
+    listeners: Vec<Sender<Profile>>,
+    notified_profile: Profile,
 }
 
 impl GetSupported for CtrlPlatformProfile {
@@ -74,14 +78,31 @@ impl CtrlPlatformProfile {
                 info!("Device has fan curves available");
             }
 
-            return Ok(CtrlPlatformProfile { config });
+            let notified_profile = config.active_profile;
+            return Ok(CtrlPlatformProfile {
+                config,
+                listeners: Vec::new(),
+                notified_profile,
+            });
         }
 
         Err(ProfileError::NotSupported.into())
     }
 
-    pub fn save_config(&self) {
+    /// Save the config, and send the profile to listeners if it changed
+    pub fn save_config(&mut self) {
         self.config.write();
+        if self.notified_profile != self.config.active_profile {
+            let profile = self.config.active_profile;
+            self.notified_profile = profile;
+            self.listeners.retain(|send| send.send(profile).is_ok());
+        }
+    }
+
+    /// Send each change of the active profile on `send`, including changes
+    /// made outside of asusd
+    pub fn add_profile_listener(&mut self, send: Sender<Profile>) {
+        self.listeners.push(send);
     }
 
     /// Toggle to next profile in list. This will first read the config, switch, then write out
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::io::Write;
+use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread::sleep;
@@ -13,10 +14,17 @@ use log::{error, info, warn};
 use daemon::ctrl_anime::config::AnimeConfig;
 use daemon::ctrl_anime::zbus::CtrlAnimeZbus;
 use daemon::ctrl_anime::*;
-use daemon::ctrl_aura::config::AuraConfig;
+use daemon::ctrl_aura::battery::CtrlKbdBatteryTask;
+use daemon::ctrl_aura::brightness::CtrlKbdBrightnessTask;
+use daemon::ctrl_aura::config::{device_config_path, AuraConfig};
 use daemon::ctrl_aura::controller::{
     CtrlKbdLed, CtrlKbdLedReloader, CtrlKbdLedTask, CtrlKbdLedZbus,
 };
+use daemon::ctrl_aura::hotplug::CtrlKbdHotplugTask;
+use daemon::ctrl_aura::idle::CtrlKbdIdleTask;
+use daemon::ctrl_aura::openrgb::OpenRgbServer;
+use daemon::ctrl_aura::profile_modes::CtrlKbdProfileTask;
+use daemon::ctrl_aura::transport::HidRaw;
 use daemon::ctrl_charge::CtrlCharge;
 use daemon::ctrl_profiles::config::ProfileConfig;
 use daemon::ctrl_profiles::controller::CtrlProfileTask;
@@ -27,9 +35,10 @@ use daemon::{
 };
 use daemon::{
     ctrl_profiles::{controller::CtrlPlatformProfile, zbus::ProfileZbus},
-    laptops::LaptopLedData,
+    laptops::{LaptopLedData, ASUS_KEYBOARD_DEVICES},
 };
 use daemon::{CtrlTask, Reloadable, ZbusAdd};
+use rog_aura::layouts::{KeyboardLayout, DEFAULT_LAYOUT};
 use rog_dbus::DBUS_NAME;
 use rog_profiles::Profile;
 
@@ -80,6 +89,7 @@ fn start_daemon() -> Result<(), Box<dyn Error>> {
     let mut object_server = ObjectServer::new(&connection);
 
     let config = Config::load();
+    let openrgb_port = config.openrgb_port;
     let config = Arc::new(Mutex::new(config));
 
     supported.add_to_server(&mut object_server);
@@ -110,12 +120,17 @@ fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // Profile changes for the keyboard LED mode bindings
+    let mut profile_recv = None;
     if Profile::is_platform_profile_supported() {
         let profile_config = ProfileConfig::load(PROFILE_CONFIG_PATH.into());
         match CtrlPlatformProfile::new(profile_config) {
             Ok(mut ctrl) => {
                 ctrl.reload()
                     .unwrap_or_else(|err| warn!("Profile control: {}", err));
+                let (send, recv) = channel();
+                ctrl.add_profile_listener(send);
+                profile_recv = Some(recv);
 
                 let tmp = Arc::new(Mutex::new(ctrl));
                 ProfileZbus::new(tmp.clone()).add_to_server(&mut object_server);
@@ -166,9 +181,21 @@ fn start_daemon() -> Result<(), Box<dyn Error>> {
 
     let laptop = LaptopLedData::get_data();
     let aura_config = AuraConfig::load(&laptop);
-    match CtrlKbdLed::new(laptop, aura_config) {
+    let layout_name = laptop
+        .layout_name
+        .clone()
+        .unwrap_or_else(|| DEFAULT_LAYOUT.to_string());
+    let mut led_devices = HidRaw::find_all(&ASUS_KEYBOARD_DEVICES).unwrap_or_else(|err| {
+        warn!("LED devices: {}", err);
+        Vec::new()
+    });
+    // Controllers of every LED device, for restoring them when re-enumerated
+    let mut led_ctrls = Vec::new();
+    match CtrlKbdLed::new(laptop.clone(), aura_config, &mut led_devices) {
         Ok(ctrl) => {
+            let device_id = ctrl.device_id.clone();
             let inner = Arc::new(Mutex::new(ctrl));
+            led_ctrls.push(inner.clone());
 
             let mut reload = CtrlKbdLedReloader(inner.clone());
             reload
@@ -176,8 +203,74 @@ fn start_daemon() -> Result<(), Box<dyn Error>> {
                 .unwrap_or_else(|err| warn!("Keyboard LED control: {}", err));
 
             CtrlKbdLedZbus::new(inner.clone()).add_to_server(&mut object_server);
+            if let Some(id) = device_id {
+                CtrlKbdLedZbus::for_device(inner.clone(), &id).add_to_server(&mut object_server);
+            }
+
+            if let Some(port) = openrgb_port {
+                let layout = KeyboardLayout::load(&layout_name).unwrap_or_else(|err| {
+                    warn!("OpenRGB: {}, using the default layout", err);
+                    KeyboardLayout::default()
+                });
+                OpenRgbServer::new(inner.clone(), layout)
+                    .start(port)
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+
+            let task = CtrlKbdBrightnessTask::new(inner.clone(), connection.clone());
+            thread::Builder::new()
+                .name("keyboard brightness".into())
+                .spawn(move || -> Result<(), RogError> {
+                    loop {
+                        task.do_task()?;
+                    }
+                })?;
+
+            match CtrlKbdBatteryTask::new(inner.clone()) {
+                Ok(task) => {
+                    thread::Builder::new()
+                        .name("keyboard battery".into())
+                        .spawn(move || -> Result<(), RogError> {
+                            loop {
+                                task.do_task()?;
+                            }
+                        })?;
+                }
+                Err(err) => info!("LED battery effects disabled: {}", err),
+            }
 
-            let task = CtrlKbdLedTask::new(inner);
+            if let Some(profiles) = profile_recv {
+                // Only changes are sent, so show the mode bound to the profile at start
+                match Profile::get_active_profile() {
+                    Ok(profile) => {
+                        if let Ok(mut lock) = inner.lock() {
+                            lock.apply_profile_mode(profile)
+                                .map_err(|err| warn!("LED profile mode: {}", err))
+                                .ok();
+                        }
+                    }
+                    Err(err) => warn!("LED profile mode: {}", err),
+                }
+                let task = CtrlKbdProfileTask::new(inner.clone(), connection.clone(), profiles);
+                thread::Builder::new()
+                    .name("keyboard profile".into())
+                    .spawn(move || -> Result<(), RogError> {
+                        loop {
+                            task.do_task()?;
+                        }
+                    })?;
+            }
+
+            let task = CtrlKbdIdleTask::new(inner.clone());
+            thread::Builder::new().name("keyboard idle".into()).spawn(
+                move || -> Result<(), RogError> {
+                    loop {
+                        task.do_task()?;
+                    }
+                },
+            )?;
+
+            let task = CtrlKbdLedTask::new(inner, connection.clone());
             thread::Builder::new().name("keyboard tasks".into()).spawn(
                 move || -> Result<(), RogError> {
                     loop {
@@ -191,6 +284,36 @@ fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    for device in led_devices {
+        let id = device.id.clone();
+        let supported = LaptopLedData::for_device(device.product, &laptop);
+        let config = AuraConfig::load_from(&device_config_path(&id), &supported);
+        let inner = Arc::new(Mutex::new(CtrlKbdLed::for_device(
+            supported, config, device,
+        )));
+        led_ctrls.push(inner.clone());
+
+        CtrlKbdLedReloader(inner.clone())
+            .reload()
+            .unwrap_or_else(|err| warn!("LED device {}: {}", id, err));
+        CtrlKbdLedZbus::for_device(inner, &id).add_to_server(&mut object_server);
+    }
+
+    if !led_ctrls.is_empty() {
+        match CtrlKbdHotplugTask::new(led_ctrls) {
+            Ok(task) => {
+                thread::Builder::new()
+                    .name("keyboard hotplug".into())
+                    .spawn(move || -> Result<(), RogError> {
+                        loop {
+                            task.do_task()?;
+                        }
+                    })?;
+            }
+            Err(err) => warn!("LED device hotplug: {}", err),
+        }
+    }
+
     // Request dbus name after finishing initalizing all functions
     fdo_connection.request_name(DBUS_NAME, fdo::RequestNameFlags::ReplaceExisting.into())?;
 
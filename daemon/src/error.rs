@@ -23,6 +23,9 @@ pub enum RogError {
     Io(std::io::Error),
     Zbus(zbus::Error),
     ChargeLimit(u8),
+    InvalidPerKeyBlock,
+    OpenRgb(String),
+    InvalidSchedule(String),
 }
 
 impl fmt::Display for RogError {
@@ -48,6 +51,9 @@ impl fmt::Display for RogError {
             RogError::Io(detail) => write!(f, "std::io error: {}", detail),
             RogError::Zbus(detail) => write!(f, "Zbus error: {}", detail),
             RogError::ChargeLimit(value) => write!(f, "Invalid charging limit, not in range 20-100%: {}", value),
+            RogError::InvalidPerKeyBlock => write!(f, "Per-key block must be 11 custom mode packets of 64 bytes"),
+            RogError::OpenRgb(deets) => write!(f, "OpenRGB server: {}", deets),
+            RogError::InvalidSchedule(deets) => write!(f, "Invalid LED schedule: {}", deets),
         }
     }
 }
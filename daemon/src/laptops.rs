@@ -1,5 +1,5 @@
 use log::{info, warn};
-use rog_aura::AuraModeNum;
+use rog_aura::{AuraModeNum, PowerZone};
 use serde_derive::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use std::io::Read;
@@ -35,6 +35,20 @@ pub fn print_modes(supported_modes: &[u8]) {
 #[derive(Debug, Deserialize, Serialize)]
 struct LedSupportFile {
     led_data: Vec<LaptopLedData>,
+    #[serde(default)]
+    device_data: Vec<DeviceLedData>,
+}
+
+/// The modes of an LED device other than the laptop keyboard, matched by
+/// USB product ID
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct DeviceLedData {
+    product: String,
+    standard: Vec<AuraModeNum>,
+    #[serde(default)]
+    multizone: bool,
+    #[serde(default)]
+    power_zones: Vec<PowerZone>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -44,9 +58,23 @@ pub struct LaptopLedData {
     pub standard: Vec<AuraModeNum>,
     pub multizone: bool,
     pub per_key: bool,
+    /// Name of the per-key layout file in `/usr/share/asusd/layouts/`
+    #[serde(default)]
+    pub layout_name: Option<String>,
+    /// Zones with separate power states, the keyboard is used if this is empty
+    #[serde(default)]
+    pub power_zones: Vec<PowerZone>,
 }
 
 impl LaptopLedData {
+    /// The zones that can have their power states set, at least the keyboard
+    pub fn supported_power_zones(&self) -> Vec<PowerZone> {
+        if self.power_zones.is_empty() {
+            return vec![PowerZone::Keyboard];
+        }
+        self.power_zones.clone()
+    }
+
     pub fn get_data() -> Self {
         let dmi = sysfs_class::DmiId::default();
         let board_name = dmi.board_name().expect("Could not get board_name");
@@ -64,7 +92,29 @@ impl LaptopLedData {
             standard: vec![],
             multizone: false,
             per_key: false,
+            layout_name: None,
+            power_zones: vec![],
+        }
+    }
+}
+
+impl LaptopLedData {
+    /// The modes of a device other than the laptop keyboard. Devices without
+    /// an entry are assumed to support the same modes as the laptop keyboard,
+    /// but never per-key effects.
+    pub fn for_device(product: &str, laptop: &LaptopLedData) -> Self {
+        let mut data = laptop.clone();
+        data.per_key = false;
+        data.layout_name = None;
+        if let Some(device) = LedSupportFile::load_from_config()
+            .and_then(|file| file.device_data.into_iter().find(|d| d.product == product))
+        {
+            info!("Matched LED device {}", product);
+            data.standard = device.standard;
+            data.multizone = device.multizone;
+            data.power_zones = device.power_zones;
         }
+        data
     }
 }
 
@@ -0,0 +1,541 @@
+use std::{
+    f32::consts::PI,
+    io::{self, Read},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{data::run_frames, error::AnimeError, AnimTime, AnimeDataBuffer, AnimeDiagonal};
+
+/// Samples in each FFT, the most recent are always used
+const FFT_SIZE: usize = 2048;
+/// Frames drawn each second of audio
+const FPS: u32 = 30;
+/// Frequency range split in to bands
+const MIN_FREQ: f32 = 50.0;
+const MAX_FREQ: f32 = 16000.0;
+/// The quietest level shown, in dB below full scale
+const FLOOR_DB: f32 = -60.0;
+/// The diagonal columns which bands are spread over. Column 2 is the first to
+/// reach the bottom of the display, and columns past 51 do not reach it.
+const FIRST_COLUMN: usize = 2;
+const COLUMNS: usize = 50;
+
+/// Where the samples for an `AudioEq` come from
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum AudioSource {
+    /// The monitor of the default output in the user's PipeWire or PulseAudio
+    /// session
+    Monitor,
+    /// A 16 bit PCM WAV file
+    Wav(PathBuf),
+    /// A 16 bit PCM WAV read from stdin
+    Stdin,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum EqStyle {
+    /// A bar for each frequency band
+    Spectrum,
+    /// Every bar shows the overall level
+    Vu,
+}
+
+/// Mono samples for an `AudioEq`
+pub trait SampleSource {
+    fn sample_rate(&self) -> u32;
+
+    /// Fill `buf` with samples from -1.0 to 1.0, returning how many were read.
+    /// Reading none means the source has ended.
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, AnimeError>;
+}
+
+/// Interleaved signed 16 bit little endian samples, mixed down to mono
+#[derive(Debug)]
+pub struct PcmSource<R: Read> {
+    reader: R,
+    rate: u32,
+    channels: u16,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> PcmSource<R> {
+    pub fn new(reader: R, rate: u32, channels: u16) -> Self {
+        Self {
+            reader,
+            rate,
+            channels: channels.max(1),
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Read the header of a WAV, leaving the reader at the start of the samples
+    pub fn from_wav(mut reader: R) -> Result<Self, AnimeError> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(AnimeError::Audio("not a WAV file".into()));
+        }
+
+        let mut format = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk)?;
+            let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+            match &chunk[0..4] {
+                b"fmt " => {
+                    let mut fmt = [0u8; 16];
+                    reader.read_exact(&mut fmt)?;
+                    let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    let rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                    // 0xFFFE is WAVE_FORMAT_EXTENSIBLE, which is PCM at 16 bits
+                    if !matches!(tag, 1 | 0xFFFE) || bits != 16 {
+                        return Err(AnimeError::Audio(
+                            "only 16 bit PCM WAV files are supported".into(),
+                        ));
+                    }
+                    format = Some((rate, channels));
+                    skip(&mut reader, size.saturating_sub(16) + size % 2)?;
+                }
+                b"data" => break,
+                _ => skip(&mut reader, size + size % 2)?,
+            }
+        }
+
+        let (rate, channels) =
+            format.ok_or_else(|| AnimeError::Audio("WAV has no format".into()))?;
+        Ok(Self::new(reader, rate, channels))
+    }
+}
+
+fn skip<R: Read>(reader: &mut R, bytes: u64) -> Result<(), AnimeError> {
+    io::copy(&mut reader.take(bytes), &mut io::sink())?;
+    Ok(())
+}
+
+impl<R: Read> SampleSource for PcmSource<R> {
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, AnimeError> {
+        let frame_len = self.channels as usize * 2;
+        self.bytes.resize(buf.len() * frame_len, 0);
+        let mut filled = 0;
+        while filled < self.bytes.len() {
+            match self.reader.read(&mut self.bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let frames = filled / frame_len;
+        let read = self.bytes[..frames * frame_len].chunks_exact(frame_len);
+        for (sample, frame) in buf.iter_mut().zip(read) {
+            let sum: f32 = frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32)
+                .sum();
+            *sample = sum / self.channels as f32 / 32768.0;
+        }
+        Ok(frames)
+    }
+}
+
+/// In place radix-2 FFT, the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// An amplitude where 1.0 is full scale, as a level from 0.0 at `FLOOR_DB`
+/// to 1.0
+fn level_of(amplitude: f32) -> f32 {
+    let db = 20.0 * amplitude.max(f32::MIN_POSITIVE).log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// The level of each of `bands` log spaced frequency bands. A full scale sine
+/// is a level of 1.0 in its band. Too few samples to have any bands is silent.
+pub fn spectrum(samples: &[f32], rate: u32, bands: usize) -> Vec<f32> {
+    if samples.len() < 4 {
+        return vec![0.0; bands];
+    }
+    let n = samples.len().next_power_of_two();
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    // Hann window
+    for (i, sample) in samples.iter().enumerate() {
+        re[i] = sample * (0.5 - 0.5 * (2.0 * PI * i as f32 / samples.len() as f32).cos());
+    }
+    fft(&mut re, &mut im);
+
+    // Scaled for the window's gain of 0.5
+    let scale = 4.0 / samples.len() as f32;
+    let bin_hz = rate as f32 / n as f32;
+    let max_freq = MAX_FREQ.min(rate as f32 / 2.0);
+    let ratio = max_freq / MIN_FREQ;
+    (0..bands)
+        .map(|band| {
+            let low = MIN_FREQ * ratio.powf(band as f32 / bands as f32);
+            let high = MIN_FREQ * ratio.powf((band + 1) as f32 / bands as f32);
+            let first = ((low / bin_hz).round() as usize).clamp(1, n / 2 - 1);
+            let last = ((high / bin_hz).round() as usize).clamp(first + 1, n / 2);
+            let peak = (first..last)
+                .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale)
+                .fold(0.0, f32::max);
+            level_of(peak)
+        })
+        .collect()
+}
+
+/// The RMS level of the samples
+pub fn vu_level(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let power: f32 = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    level_of(power.sqrt())
+}
+
+/// A spectrum or VU meter of the samples from an `AudioSource`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AudioEq {
+    pub source: AudioSource,
+    pub style: EqStyle,
+    /// Number of bars, up to 50
+    pub bands: u32,
+    /// Fraction of the full height a bar falls each second
+    pub decay: f32,
+    /// How long the peak of each bar is held before falling. Zero shows no
+    /// peaks.
+    pub peak_hold: Duration,
+    pub time: AnimTime,
+    pub brightness: f32,
+}
+
+/// The bars and held peaks of an `AudioEq` as they fall
+#[derive(Debug, Clone)]
+pub struct EqMeter {
+    bars: Vec<f32>,
+    peaks: Vec<f32>,
+    held: Vec<Duration>,
+}
+
+impl EqMeter {
+    pub fn new(bands: usize) -> Self {
+        Self {
+            bars: vec![0.0; bands],
+            peaks: vec![0.0; bands],
+            held: vec![Duration::default(); bands],
+        }
+    }
+
+    pub fn bars(&self) -> &[f32] {
+        &self.bars
+    }
+
+    pub fn peaks(&self) -> &[f32] {
+        &self.peaks
+    }
+
+    /// Raise the bars to the new levels, or let them fall by the decay over
+    /// `elapsed` if lower
+    pub fn update(&mut self, eq: &AudioEq, levels: &[f32], elapsed: Duration) {
+        let fall = eq.decay.max(0.0) * elapsed.as_secs_f32();
+        for (i, level) in levels.iter().enumerate().take(self.bars.len()) {
+            let bar = level.max(self.bars[i] - fall).max(0.0);
+            self.bars[i] = bar;
+            if bar >= self.peaks[i] {
+                self.peaks[i] = bar;
+                self.held[i] = Duration::default();
+            } else {
+                self.held[i] += elapsed;
+                if self.held[i] > eq.peak_hold {
+                    self.peaks[i] = (self.peaks[i] - fall).max(bar);
+                }
+            }
+        }
+    }
+}
+
+impl AudioEq {
+    fn band_count(&self) -> usize {
+        (self.bands as usize).clamp(1, COLUMNS)
+    }
+
+    /// Draw each band as bars up the diagonal columns from the bottom of the
+    /// display
+    pub fn render(&self, meter: &EqMeter) -> AnimeDiagonal {
+        let bright = (self.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+        let mut image = AnimeDiagonal::new(None);
+        let rows = image.get_mut();
+        let height = rows.len();
+        let bands = meter.bars.len();
+        let gap = if COLUMNS / bands > 1 { 1 } else { 0 };
+
+        for (band, (bar, peak)) in meter.bars.iter().zip(meter.peaks.iter()).enumerate() {
+            let left = FIRST_COLUMN + band * COLUMNS / bands;
+            let right = FIRST_COLUMN + (band + 1) * COLUMNS / bands - gap;
+            let lit = (bar * height as f32).round() as usize;
+            let peak = (peak * height as f32).round() as usize;
+            for (y, row) in rows.iter_mut().enumerate() {
+                let from_bottom = height - y;
+                let show_peak = self.peak_hold > Duration::default() && from_bottom == peak;
+                if from_bottom <= lit || show_peak {
+                    for pixel in row[left..right].iter_mut() {
+                        *pixel = bright;
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Runs the meter as a blocking loop by using the `callback` to write data,
+/// drawing a frame for each 30th of a second read from the `source`. A source
+/// which reads faster than real time, such as a file, is slowed to match.
+/// Ends early when the source does.
+pub fn run_audio_eq(
+    eq: &AudioEq,
+    source: &mut dyn SampleSource,
+    do_early_return: Arc<AtomicBool>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    let rate = source.sample_rate();
+    if rate == 0 {
+        return Err(AnimeError::Audio("sample rate is zero".into()));
+    }
+    let bands = eq.band_count();
+    let mut meter = EqMeter::new(bands);
+    let mut window = vec![0.0; FFT_SIZE];
+    let mut buf = vec![0.0; (rate / FPS).max(1) as usize];
+    let mut total = 0;
+    let mut error = None;
+
+    let result = run_frames(
+        eq.time,
+        do_early_return,
+        &mut |elapsed| {
+            let read = match source.read(&mut buf) {
+                Ok(0) => return None,
+                Ok(read) => read,
+                Err(err) => {
+                    error = Some(err);
+                    return None;
+                }
+            };
+            let new = &buf[read.saturating_sub(FFT_SIZE)..read];
+            window.rotate_left(new.len());
+            window[FFT_SIZE - new.len()..].copy_from_slice(new);
+            total += read;
+
+            let levels = match eq.style {
+                EqStyle::Spectrum => spectrum(&window, rate, bands),
+                EqStyle::Vu => vec![vu_level(new); bands],
+            };
+            meter.update(
+                eq,
+                &levels,
+                Duration::from_secs_f64(read as f64 / rate as f64),
+            );
+
+            let played = Duration::from_secs_f64(total as f64 / rate as f64);
+            Some(((&eq.render(&meter)).into(), played.saturating_sub(elapsed)))
+        },
+        callback,
+    );
+    match error {
+        Some(err) => Err(err),
+        None => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        f32::consts::PI,
+        io::Cursor,
+        sync::{atomic::AtomicBool, Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{
+        run_audio_eq, spectrum, vu_level, AudioEq, AudioSource, EqMeter, EqStyle, PcmSource,
+        SampleSource,
+    };
+    use crate::AnimTime;
+
+    fn sine(freq: f32, rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    fn eq(bands: u32, peak_hold: Duration) -> AudioEq {
+        AudioEq {
+            source: AudioSource::Stdin,
+            style: EqStyle::Spectrum,
+            bands,
+            decay: 1.0,
+            peak_hold,
+            time: AnimTime::Infinite,
+            brightness: 1.0,
+        }
+    }
+
+    fn wav(channels: u16, bits: u16, samples: &[i16]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        // An unknown chunk of odd length, which is padded
+        data.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        data.extend_from_slice(b"fmt \x10\0\0\0\x01\0");
+        data.extend_from_slice(&channels.to_le_bytes());
+        data.extend_from_slice(&8000u32.to_le_bytes());
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&bits.to_le_bytes());
+        data.extend_from_slice(b"data\0\0\0\0");
+        for sample in samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn wav_is_mixed_to_mono() {
+        let data = wav(2, 16, &[16384, 0, -32768, -32768, 100]);
+        let mut source = PcmSource::from_wav(Cursor::new(data)).unwrap();
+        assert_eq!(source.sample_rate(), 8000);
+        let mut buf = [0.0; 4];
+        // The last half frame is dropped
+        assert_eq!(source.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [0.25, -1.0]);
+        assert_eq!(source.read(&mut buf).unwrap(), 0);
+
+        assert!(PcmSource::from_wav(Cursor::new(wav(1, 8, &[]))).is_err());
+        assert!(PcmSource::from_wav(Cursor::new(b"RIFF\0\0\0\0AVI ".to_vec())).is_err());
+    }
+
+    #[test]
+    fn sine_is_in_one_band() {
+        let levels = spectrum(&sine(1000.0, 44100, 2048), 44100, 16);
+        let loudest = levels
+            .iter()
+            .enumerate()
+            .fold(0, |max, (i, l)| if *l > levels[max] { i } else { max });
+        // 1kHz is 0.52 of the way through 50Hz to 16kHz on a log scale
+        assert_eq!(loudest, 8);
+        assert!(levels[8] > 0.95, "{}", levels[8]);
+        assert!(levels[1] < 0.3, "{}", levels[1]);
+        assert!(levels[15] < 0.3, "{}", levels[15]);
+
+        assert_eq!(spectrum(&[], 44100, 4), [0.0; 4]);
+        assert_eq!(spectrum(&[1.0, -1.0], 44100, 2), [0.0; 2]);
+        assert_eq!(spectrum(&[0.0; 4], 44100, 2), [0.0; 2]);
+
+        assert!(vu_level(&sine(1000.0, 44100, 2048)) > 0.9);
+        assert_eq!(vu_level(&[0.0; 64]), 0.0);
+    }
+
+    #[test]
+    fn bars_decay_and_peaks_hold() {
+        let eq = eq(2, Duration::from_millis(500));
+        let mut meter = EqMeter::new(2);
+        meter.update(&eq, &[1.0, 0.5], Duration::from_millis(100));
+        meter.update(&eq, &[0.0, 0.0], Duration::from_millis(250));
+        assert_eq!(meter.bars(), [0.75, 0.25]);
+        assert_eq!(meter.peaks(), [1.0, 0.5]);
+        meter.update(&eq, &[0.0, 0.0], Duration::from_millis(250));
+        assert_eq!(meter.bars(), [0.5, 0.0]);
+        assert_eq!(meter.peaks(), [1.0, 0.5]);
+        // Held for long enough, so now falls with the bars
+        meter.update(&eq, &[0.0, 0.0], Duration::from_millis(250));
+        assert_eq!(meter.peaks(), [0.75, 0.25]);
+    }
+
+    #[test]
+    fn bars_are_drawn_from_the_bottom() {
+        let eq = eq(2, Duration::from_secs(1));
+        let mut meter = EqMeter::new(2);
+        meter.update(&eq, &[0.5, 1.0], Duration::default());
+        meter.update(&eq, &[0.25, 1.0], Duration::from_millis(100));
+        let mut image = eq.render(&meter);
+        let rows = image.get_mut();
+        let bottom = rows.len() - 1;
+        // First band is columns 2 to 26 less the gap, at 0.4 of 36 rows with
+        // a peak at 18
+        assert_eq!(rows[bottom][1], 0);
+        assert_eq!(rows[bottom][2], 255);
+        assert_eq!(rows[bottom][26], 0);
+        assert_eq!(rows[bottom - 13][2], 255);
+        assert_eq!(rows[bottom - 14][2], 0);
+        assert_eq!(rows[bottom - 17][2], 255);
+        assert_eq!(rows[bottom - 18][2], 0);
+        assert_eq!(rows[0][27], 255);
+    }
+
+    #[test]
+    fn ends_with_the_source() {
+        let samples: Vec<i16> = sine(500.0, 3000, 300)
+            .iter()
+            .map(|s| (s * 16000.0) as i16)
+            .collect();
+        let mut data = Vec::new();
+        for sample in samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        let mut source = PcmSource::new(Cursor::new(data), 3000, 1);
+        let frames = Mutex::new(0);
+        run_audio_eq(
+            &eq(8, Duration::default()),
+            &mut source,
+            Arc::new(AtomicBool::new(false)),
+            &|_| {
+                *frames.lock().unwrap() += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        // 100 samples a frame
+        assert_eq!(*frames.lock().unwrap(), 3);
+    }
+}
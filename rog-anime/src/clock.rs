@@ -0,0 +1,228 @@
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use glam::Vec2;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    data::run_frames, error::AnimeError, font::text_width, AnimTime, AnimeDataBuffer, AnimeGrid,
+    GLYPH_HEIGHT, ROWS_PER_PIXEL,
+};
+
+/// Rows between the time and the date
+const DATE_GAP: i32 = 2;
+
+/// A date and time in the local timezone
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct LocalTime {
+    pub year: i32,
+    /// 1-12
+    pub month: u8,
+    /// 1-31
+    pub day: u8,
+    /// 0-23
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Nanoseconds into the second
+    pub nanos: u32,
+}
+
+impl LocalTime {
+    pub fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let time = since_epoch.as_secs() as libc::time_t;
+        // Safe as localtime_r only writes to the tm given, and is thread safe
+        let tm = unsafe {
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&time, &mut tm);
+            tm
+        };
+        Self {
+            year: tm.tm_year + 1900,
+            month: (tm.tm_mon + 1) as u8,
+            day: tm.tm_mday as u8,
+            hour: tm.tm_hour as u8,
+            minute: tm.tm_min as u8,
+            second: tm.tm_sec as u8,
+            nanos: since_epoch.subsec_nanos(),
+        }
+    }
+}
+
+/// Shows the local time, and optionally the date, in the builtin font
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Clock {
+    /// 12 hour time, without AM or PM
+    pub hour_12: bool,
+    pub seconds: bool,
+    /// Show the date as `MM-DD` under the time
+    pub date: bool,
+    /// Top left of the time, as column and row on the display
+    pub translation: Vec2,
+    pub time: AnimTime,
+    pub brightness: f32,
+}
+
+impl Clock {
+    /// The time as shown, e.g. `21:05`, or `9:05` in 12 hour time
+    pub fn time_text(&self, now: &LocalTime) -> String {
+        let mut text = if self.hour_12 {
+            let hour = match now.hour % 12 {
+                0 => 12,
+                hour => hour,
+            };
+            format!("{}:{:02}", hour, now.minute)
+        } else {
+            format!("{:02}:{:02}", now.hour, now.minute)
+        };
+        if self.seconds {
+            text.push_str(&format!(":{:02}", now.second));
+        }
+        text
+    }
+
+    pub fn date_text(&self, now: &LocalTime) -> String {
+        format!("{:02}-{:02}", now.month, now.day)
+    }
+
+    pub fn render(&self, now: &LocalTime) -> AnimeGrid {
+        let bright = (self.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+        let x = self.translation.x.round() as i32;
+        let y = self.translation.y.round() as i32;
+        let mut grid = AnimeGrid::new(None);
+        let time = self.time_text(now);
+        grid.draw_text(&time, x, y, bright);
+        if self.date {
+            // Right aligned to the time, as the display narrows to the left
+            // going down
+            let date = self.date_text(now);
+            let dx = text_width(&time) as i32 - text_width(&date) as i32;
+            let dy = (GLYPH_HEIGHT * ROWS_PER_PIXEL) as i32 + DATE_GAP;
+            grid.draw_text(&date, x + dx, y + dy, bright);
+        }
+        grid
+    }
+
+    /// Time until the shown text next changes
+    pub fn until_change(&self, now: &LocalTime) -> Duration {
+        let into_second = Duration::from_nanos(now.nanos as u64);
+        let step = if self.seconds {
+            Duration::from_secs(1)
+        } else {
+            Duration::from_secs(60 - now.second.min(59) as u64)
+        };
+        step.saturating_sub(into_second)
+    }
+}
+
+/// Runs the clock as a blocking loop by using the `callback` to write data,
+/// redrawing only when the time shown changes
+pub fn run_clock(
+    clock: &Clock,
+    do_early_return: Arc<AtomicBool>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    run_frames(
+        clock.time,
+        do_early_return,
+        &mut |_| {
+            let now = LocalTime::now();
+            Some((clock.render(&now).into(), clock.until_change(&now)))
+        },
+        callback,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Arc, Mutex},
+        time::Duration,
+    };
+
+    use glam::Vec2;
+
+    use super::{Clock, LocalTime};
+    use crate::{run_frames, AnimTime, AnimeDataBuffer, AnimeGrid};
+
+    fn clock(hour_12: bool, seconds: bool) -> Clock {
+        Clock {
+            hour_12,
+            seconds,
+            date: true,
+            translation: Vec2::new(2.0, 0.0),
+            time: AnimTime::Infinite,
+            brightness: 1.0,
+        }
+    }
+
+    fn at(hour: u8, minute: u8, second: u8) -> LocalTime {
+        LocalTime {
+            year: 2021,
+            month: 10,
+            day: 8,
+            hour,
+            minute,
+            second,
+            nanos: 250_000_000,
+        }
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(clock(false, false).time_text(&at(21, 5, 0)), "21:05");
+        assert_eq!(clock(false, true).time_text(&at(7, 5, 9)), "07:05:09");
+        assert_eq!(clock(true, false).time_text(&at(21, 5, 0)), "9:05");
+        assert_eq!(clock(true, false).time_text(&at(0, 30, 0)), "12:30");
+        assert_eq!(clock(true, false).time_text(&at(12, 0, 0)), "12:00");
+        assert_eq!(clock(false, false).date_text(&at(0, 0, 0)), "10-08");
+    }
+
+    #[test]
+    fn updates() {
+        assert_eq!(
+            clock(false, true).until_change(&at(1, 2, 3)),
+            Duration::from_millis(750)
+        );
+        assert_eq!(
+            clock(false, false).until_change(&at(1, 2, 50)),
+            Duration::from_millis(9750)
+        );
+    }
+
+    #[test]
+    fn renders_time_and_date() {
+        let grid = clock(false, false).render(&at(10, 0, 0));
+        let mut expected = AnimeGrid::new(None);
+        expected.draw_text("10:00", 2, 0, 255);
+        // The date is 2 columns wider than the time
+        expected.draw_text("10-08", 0, 12, 255);
+        assert_eq!(grid.get(), expected.get());
+    }
+
+    #[test]
+    fn frames_are_counted() {
+        let shown = Mutex::new(0);
+        let mut drawn = Vec::new();
+        run_frames(
+            AnimTime::Count(3),
+            Arc::new(AtomicBool::new(false)),
+            &mut |elapsed| {
+                drawn.push(elapsed);
+                Some((AnimeDataBuffer::new(), Duration::from_millis(1)))
+            },
+            &|_| {
+                *shown.lock().unwrap() += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(drawn.len(), 3);
+        assert_eq!(*shown.lock().unwrap(), 3);
+    }
+}
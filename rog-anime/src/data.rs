@@ -91,6 +91,91 @@ impl From<AnimeDataBuffer> for AnimePacketType {
     }
 }
 
+/// The longest time between frames while fading, for a smooth fade
+const FADE_STEP: Duration = Duration::from_millis(50);
+/// How often a long wait between frames checks for an early return
+const WAIT_STEP: Duration = Duration::from_millis(10);
+
+/// Runs an action which draws each frame when it is shown, such as the clock,
+/// as a blocking loop. `frame` is given the time since the action started and
+/// returns the frame and how long to show it, or `None` to end the action
+/// early. The `duration` is as for animations, except that `AnimTime::Count`
+/// counts frames, and a fade without a `show_for` time fades in then shows
+/// until an early return.
+pub fn run_frames(
+    duration: AnimTime,
+    do_early_return: Arc<AtomicBool>,
+    frame: &mut dyn FnMut(Duration) -> Option<(AnimeDataBuffer, Duration)>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    let start = Instant::now();
+    let (run_time, fade) = match duration {
+        AnimTime::Time(time) => (Some(time), None),
+        AnimTime::Fade(fade) => (
+            fade.show_for()
+                .map(|show| fade.fade_in() + show + fade.fade_out()),
+            Some(fade),
+        ),
+        AnimTime::Count(_) | AnimTime::Infinite => (None, None),
+    };
+
+    let mut count = 0;
+    loop {
+        if do_early_return.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let elapsed = start.elapsed();
+        if matches!(run_time, Some(run_time) if elapsed >= run_time) {
+            break;
+        }
+
+        let (mut output, mut delay) = match frame(elapsed) {
+            Some(frame) => frame,
+            None => break,
+        };
+        if let Some(fade) = fade {
+            let mut level = 1.0;
+            if elapsed < fade.fade_in() {
+                level = elapsed.as_secs_f32() / fade.fade_in().as_secs_f32();
+                delay = delay.min(FADE_STEP);
+            } else if let Some(run_time) = run_time {
+                let fade_out_at = run_time - fade.fade_out();
+                if elapsed >= fade_out_at {
+                    level = (run_time - elapsed).as_secs_f32() / fade.fade_out().as_secs_f32();
+                    delay = delay.min(FADE_STEP);
+                } else {
+                    // Wake up for the start of the fade out
+                    delay = delay.min(fade_out_at - elapsed);
+                }
+            }
+            if level < 1.0 {
+                for pixel in output.get_mut() {
+                    *pixel = (*pixel as f32 * level) as u8;
+                }
+            }
+        }
+        if let Some(run_time) = run_time {
+            delay = delay.min(run_time - elapsed);
+        }
+        callback(output)?;
+
+        // Wait in steps so that a slow updating action can return early
+        let wake = Instant::now() + delay;
+        while let Some(left) = wake.checked_duration_since(Instant::now()) {
+            if left.is_zero() || do_early_return.load(Ordering::SeqCst) {
+                break;
+            }
+            sleep(left.min(WAIT_STEP));
+        }
+
+        count += 1;
+        if matches!(duration, AnimTime::Count(times) if count >= times) {
+            break;
+        }
+    }
+    Ok(())
+}
+
 /// This runs the animations as a blocking loop by using the `callback` to write data
 pub fn run_animation(
     frames: &AnimeGif,
@@ -13,6 +13,7 @@ pub enum AnimeError {
     /// The input was incorrect size, expected size is `IncorrectSize(width, height)`
     IncorrectSize(u32, u32),
     Dbus(String),
+    Audio(String),
 }
 
 impl fmt::Display for AnimeError {
@@ -30,6 +31,7 @@ impl fmt::Display for AnimeError {
                 width, height
             ),
             AnimeError::Dbus(detail) => write!(f, "{}", detail),
+            AnimeError::Audio(detail) => write!(f, "Audio error: {}", detail),
         }
     }
 }
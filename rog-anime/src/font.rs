@@ -0,0 +1,143 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// The height of every glyph in font pixels
+pub const GLYPH_HEIGHT: usize = 5;
+/// Font pixels between glyphs
+pub const GLYPH_SPACING: usize = 1;
+/// The display rows each font pixel covers. Rows are much closer together
+/// than columns, so two rows are needed for a roughly square pixel.
+pub const ROWS_PER_PIXEL: usize = 2;
+
+/// The bundled font sizes. Both use the same glyphs, the large font draws
+/// each font pixel as a two by two block.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum FontSize {
+    /// 10 rows high, fitting five lines on the display
+    Small,
+    /// 20 rows high
+    Large,
+}
+
+impl FontSize {
+    /// Columns and rows per font pixel, before `ROWS_PER_PIXEL`
+    pub fn scale(self) -> usize {
+        match self {
+            FontSize::Small => 1,
+            FontSize::Large => 2,
+        }
+    }
+
+    /// Height of a line of text in display rows
+    pub fn height(self) -> usize {
+        GLYPH_HEIGHT * ROWS_PER_PIXEL * self.scale()
+    }
+
+    /// Width of `text` in display columns
+    pub fn width(self, text: &str) -> usize {
+        text_width(text) * self.scale()
+    }
+}
+
+/// Each glyph is its rows from the top, `#` is lit. Glyphs are 3 pixels wide
+/// except for some punctuation.
+#[rustfmt::skip]
+static GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 56] = [
+    (' ', ["..", "..", "..", "..", ".."]),
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["##.", "..#", ".#.", "#..", "###"]),
+    ('3', ["##.", "..#", ".#.", "..#", "##."]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "##.", "..#", "##."]),
+    ('6', [".##", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", ".#.", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "##."]),
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["##.", "#.#", "#.#", "#.#", "#.#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "##.", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    (':', [".", "#", ".", "#", "."]),
+    ('.', [".", ".", ".", ".", "#"]),
+    (',', [".", ".", ".", "#", "#"]),
+    ('!', ["#", "#", "#", ".", "#"]),
+    ('\'', ["#", "#", ".", ".", "."]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    ('_', ["...", "...", "...", "...", "###"]),
+    ('=', ["...", "###", "...", "###", "..."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('%', ["#.#", "..#", ".#.", "#..", "#.#"]),
+    ('#', ["#.#", "###", "#.#", "###", "#.#"]),
+    ('*', ["...", "#.#", ".#.", "#.#", "..."]),
+    ('(', [".#", "#.", "#.", "#.", ".#"]),
+    (')', ["#.", ".#", ".#", ".#", "#."]),
+    ('<', ["..#", ".#.", "#..", ".#.", "..#"]),
+    ('>', ["#..", ".#.", "..#", ".#.", "#.."]),
+    ('"', ["#.#", "#.#", "...", "...", "..."]),
+];
+
+/// The rows of the glyph for `c`. Lower case letters use the upper case
+/// glyphs, and characters without a glyph are shown as `?`.
+pub fn glyph(c: char) -> &'static [&'static str; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| GLYPHS.iter().find(|(g, _)| *g == '?'))
+        .map(|(_, rows)| rows)
+        .expect("font has a ? glyph")
+}
+
+/// The width of `text` in font pixels, which is also display columns
+pub fn text_width(text: &str) -> usize {
+    let glyphs: usize = text.chars().map(|c| glyph(c)[0].len()).sum();
+    glyphs + text.chars().count().saturating_sub(1) * GLYPH_SPACING
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph, text_width, FontSize, GLYPHS, GLYPH_HEIGHT};
+
+    #[test]
+    fn glyphs_are_rectangular() {
+        for (c, rows) in GLYPHS.iter() {
+            assert_eq!(rows.len(), GLYPH_HEIGHT);
+            assert!(rows.iter().all(|row| row.len() == rows[0].len()), "{}", c);
+        }
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('~'), glyph('?'));
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("12:34"), 3 * 4 + 1 + 4);
+        assert_eq!(text_width("1 2"), 3 + 2 + 3 + 2);
+        assert_eq!(FontSize::Large.width("12"), 14);
+        assert_eq!(FontSize::Large.height(), 20);
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{GeneratorState, Rng};
+use crate::{
+    grid::{HEIGHT, WIDTH},
+    AnimeGrid,
+};
+
+/// The shortest time between generations
+const MIN_STEP: Duration = Duration::from_millis(10);
+
+/// Conway's Game of Life, wrapping at the edges of the grid. The cells are
+/// seeded again once they die out or settle in to a still or blinking pattern.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Life {
+    /// Time between generations
+    pub step: Duration,
+    /// Fraction of the cells alive when seeded
+    pub fill: f32,
+    #[serde(skip)]
+    cells: Vec<bool>,
+    #[serde(skip)]
+    previous: Vec<bool>,
+    /// Time since the last generation
+    #[serde(skip)]
+    since: Duration,
+    #[serde(skip)]
+    rng: Rng,
+}
+
+impl Default for Life {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_millis(150),
+            fill: 0.3,
+            cells: Vec::new(),
+            previous: Vec::new(),
+            since: Duration::default(),
+            rng: Rng::default(),
+        }
+    }
+}
+
+impl Life {
+    fn seed_cells(&mut self) {
+        let fill = self.fill;
+        let rng = &mut self.rng;
+        self.cells = (0..WIDTH * HEIGHT).map(|_| rng.unit() < fill).collect();
+        self.previous.clear();
+    }
+
+    fn neighbours(&self, x: usize, y: usize) -> usize {
+        let mut count = 0;
+        for dy in [HEIGHT - 1, 0, 1] {
+            for dx in [WIDTH - 1, 0, 1] {
+                if (dx, dy) == (0, 0) {
+                    continue;
+                }
+                let nx = (x + dx) % WIDTH;
+                let ny = (y + dy) % HEIGHT;
+                if self.cells[ny * WIDTH + nx] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn generation(&mut self) {
+        let next: Vec<bool> = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let alive = self.cells[i];
+                matches!(
+                    (alive, self.neighbours(i % WIDTH, i / WIDTH)),
+                    (true, 2) | (_, 3)
+                )
+            })
+            .collect();
+
+        if next == self.cells || next == self.previous || !next.contains(&true) {
+            self.seed_cells();
+        } else {
+            self.previous = std::mem::replace(&mut self.cells, next);
+        }
+    }
+}
+
+impl GeneratorState for Life {
+    fn reset(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.since = Duration::default();
+        self.seed_cells();
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        if self.cells.is_empty() {
+            self.seed_cells();
+        }
+        let step = self.step.max(MIN_STEP);
+        self.since += dt;
+        while self.since >= step {
+            self.since -= step;
+            self.generation();
+        }
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8) {
+        for (y, row) in grid.get_mut().iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                if self.cells.get(y * WIDTH + x) == Some(&true) {
+                    *pixel = bright;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Life;
+    use crate::{
+        generators::GeneratorState,
+        grid::{HEIGHT, WIDTH},
+    };
+
+    fn with_cells(cells: &[(usize, usize)]) -> Life {
+        let mut life = Life {
+            step: Duration::from_millis(100),
+            ..Default::default()
+        };
+        life.reset(0);
+        life.cells = vec![false; WIDTH * HEIGHT];
+        for (x, y) in cells {
+            life.cells[y * WIDTH + x] = true;
+        }
+        life
+    }
+
+    fn alive(life: &Life) -> Vec<(usize, usize)> {
+        (0..WIDTH * HEIGHT)
+            .filter(|i| life.cells[*i])
+            .map(|i| (i % WIDTH, i / WIDTH))
+            .collect()
+    }
+
+    #[test]
+    fn glider_moves_and_wraps() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut life = with_cells(&glider);
+        // Four generations move the glider one right and one down
+        life.advance(Duration::from_millis(450));
+        let mut expected: Vec<(usize, usize)> =
+            glider.iter().map(|(x, y)| (x + 1, y + 1)).collect();
+        expected.sort_by_key(|(x, y)| (*y, *x));
+        assert_eq!(alive(&life), expected);
+
+        // Across the corner of the grid
+        let mut life = with_cells(
+            &glider
+                .iter()
+                .map(|(x, y)| ((x + WIDTH - 2) % WIDTH, (y + HEIGHT - 2) % HEIGHT))
+                .collect::<Vec<_>>(),
+        );
+        life.advance(Duration::from_millis(400));
+        assert_eq!(alive(&life).len(), 5);
+    }
+
+    #[test]
+    fn blinker_is_seeded_again() {
+        let mut life = with_cells(&[(5, 5), (6, 5), (7, 5)]);
+        life.advance(Duration::from_millis(100));
+        assert_eq!(alive(&life), [(6, 4), (6, 5), (6, 6)]);
+        // Back to the first pattern, so the cells are seeded
+        life.advance(Duration::from_millis(100));
+        assert!(alive(&life).len() > 100);
+    }
+}
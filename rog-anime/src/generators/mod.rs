@@ -0,0 +1,203 @@
+//! Procedural effects for the display. Each generator is seeded, then advanced
+//! by a time step and drawn, so that the same seed and steps always draw the
+//! same frames.
+
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{data::run_frames, error::AnimeError, AnimTime, AnimeDataBuffer, AnimeGrid};
+
+mod life;
+pub use life::*;
+
+mod plasma;
+pub use plasma::*;
+
+mod rain;
+pub use rain::*;
+
+mod starfield;
+pub use starfield::*;
+
+/// The step each frame is advanced by when run on the display
+const FRAME_TIME: Duration = Duration::from_millis(33);
+
+/// Implemented by all generators
+pub trait GeneratorState {
+    /// Start again from the state given by `seed`
+    fn reset(&mut self, seed: u64);
+
+    /// Advance the state by `dt`
+    fn advance(&mut self, dt: Duration);
+
+    /// Draw the current state, with `bright` as the brightest value
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8);
+
+    /// Advance by `dt` and draw the next frame
+    fn next_frame(&mut self, dt: Duration, bright: u8) -> AnimeDataBuffer {
+        self.advance(dt);
+        let mut grid = AnimeGrid::new(None);
+        self.draw(&mut grid, bright);
+        grid.into()
+    }
+}
+
+/// All of the generators, selectable in configs
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Generator {
+    Life(Life),
+    Plasma(Plasma),
+    Rain(Rain),
+    Starfield(Starfield),
+}
+
+impl Generator {
+    #[inline]
+    fn inner(&self) -> &dyn GeneratorState {
+        match self {
+            Generator::Life(g) => g,
+            Generator::Plasma(g) => g,
+            Generator::Rain(g) => g,
+            Generator::Starfield(g) => g,
+        }
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut dyn GeneratorState {
+        match self {
+            Generator::Life(g) => g,
+            Generator::Plasma(g) => g,
+            Generator::Rain(g) => g,
+            Generator::Starfield(g) => g,
+        }
+    }
+}
+
+impl GeneratorState for Generator {
+    fn reset(&mut self, seed: u64) {
+        self.inner_mut().reset(seed)
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        self.inner_mut().advance(dt)
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8) {
+        self.inner().draw(grid, bright)
+    }
+}
+
+/// A generator as it is run on the display
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Procedural {
+    pub generator: Generator,
+    pub seed: u64,
+    pub time: AnimTime,
+    pub brightness: f32,
+}
+
+/// Runs the generator from its seed as a blocking loop by using the
+/// `callback` to write data
+pub fn run_procedural(
+    procedural: &Procedural,
+    do_early_return: Arc<AtomicBool>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    let bright = (procedural.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+    let mut generator = procedural.generator.clone();
+    generator.reset(procedural.seed);
+    run_frames(
+        procedural.time,
+        do_early_return,
+        &mut |_| Some((generator.next_frame(FRAME_TIME, bright), FRAME_TIME)),
+        callback,
+    )
+}
+
+/// Seeded random numbers for the generators (splitmix64)
+#[derive(Debug, Clone, Default)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to but not including `n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    /// A number from 0.0 up to but not including 1.0
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Generator, GeneratorState, Life, Plasma, Rain, Starfield};
+    use crate::AnimeDataBuffer;
+
+    fn generators() -> Vec<Generator> {
+        vec![
+            Generator::Life(Life::default()),
+            Generator::Plasma(Plasma::default()),
+            Generator::Rain(Rain::default()),
+            Generator::Starfield(Starfield::default()),
+        ]
+    }
+
+    fn frames(generator: &Generator, seed: u64) -> Vec<AnimeDataBuffer> {
+        let mut generator = generator.clone();
+        generator.reset(seed);
+        (0..40)
+            .map(|_| generator.next_frame(Duration::from_millis(50), 255))
+            .collect()
+    }
+
+    #[test]
+    fn seeded_frames_repeat() {
+        for generator in generators() {
+            let first = frames(&generator, 7);
+            let again = frames(&generator, 7);
+            let other = frames(&generator, 8);
+            assert!(first
+                .iter()
+                .zip(again.iter())
+                .all(|(a, b)| a.get() == b.get()));
+            assert!(first
+                .iter()
+                .zip(other.iter())
+                .any(|(a, b)| a.get() != b.get()));
+            // Something is drawn
+            assert!(first.last().unwrap().get().iter().any(|b| *b > 0));
+        }
+    }
+
+    #[test]
+    fn reset_restarts() {
+        for mut generator in generators() {
+            let first = frames(&generator, 3);
+            generator.reset(3);
+            generator.advance(Duration::from_secs(3));
+            generator.reset(3);
+            let frame = generator.next_frame(Duration::from_millis(50), 255);
+            assert_eq!(frame.get(), first[0].get());
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::{f32::consts::PI, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{GeneratorState, Rng};
+use crate::{AnimeGrid, ROWS_PER_PIXEL};
+
+/// Smoothly moving waves of brightness made from several sine waves
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Plasma {
+    /// Radians each wave moves each second
+    pub speed: f32,
+    /// Width of the waves in columns
+    pub scale: f32,
+    #[serde(skip)]
+    time: f32,
+    /// Starting phase of each wave
+    #[serde(skip)]
+    phases: [f32; 4],
+}
+
+impl Default for Plasma {
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            scale: 4.0,
+            time: 0.0,
+            phases: [0.0; 4],
+        }
+    }
+}
+
+impl Plasma {
+    /// The level from 0.0 to 1.0 at a column and row
+    fn level(&self, x: usize, y: usize) -> f32 {
+        let scale = self.scale.max(f32::EPSILON);
+        let x = x as f32 / scale;
+        // Rows are closer together than columns
+        let y = y as f32 / (scale * ROWS_PER_PIXEL as f32);
+        let t = self.time;
+        let p = &self.phases;
+        let v = (x + t + p[0]).sin()
+            + (y + t * 0.7 + p[1]).sin()
+            + ((x + y) * 0.5 + t * 1.3 + p[2]).sin()
+            + ((x * x + y * y).sqrt() - t + p[3]).sin();
+        (v / 4.0 + 1.0) / 2.0
+    }
+}
+
+impl GeneratorState for Plasma {
+    fn reset(&mut self, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for phase in self.phases.iter_mut() {
+            *phase = rng.unit() * 2.0 * PI;
+        }
+        self.time = 0.0;
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        self.time += self.speed * dt.as_secs_f32();
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8) {
+        for (y, row) in grid.get_mut().iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = (bright as f32 * self.level(x, y)) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Plasma;
+    use crate::{generators::GeneratorState, AnimeGrid};
+
+    #[test]
+    fn waves_move() {
+        let mut plasma = Plasma::default();
+        plasma.reset(5);
+        let mut before = AnimeGrid::new(None);
+        plasma.draw(&mut before, 255);
+        plasma.advance(Duration::from_millis(200));
+        let mut after = AnimeGrid::new(None);
+        plasma.draw(&mut after, 255);
+        assert_ne!(before.get(), after.get());
+
+        // Covers most of the range
+        let values: Vec<u8> = after.get().iter().flatten().copied().collect();
+        assert!(values.iter().any(|v| *v < 64));
+        assert!(values.iter().any(|v| *v > 192));
+    }
+}
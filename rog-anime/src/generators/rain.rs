@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{GeneratorState, Rng};
+use crate::{
+    grid::{HEIGHT, WIDTH},
+    AnimeGrid,
+};
+
+/// The slowest drop speed in rows a second, so every drop falls off the grid
+const MIN_SPEED: f32 = 1.0;
+/// The most drops falling at once
+const MAX_DROPS: usize = WIDTH * 8;
+
+#[derive(Debug, Clone)]
+struct Drop {
+    x: usize,
+    /// Row of the head
+    y: f32,
+    /// Rows per second
+    speed: f32,
+}
+
+/// Drops falling down the columns with a fading trail, like digital rain
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Rain {
+    /// Rows per second, each drop is between half and one and a half times
+    /// this, and at least one row a second
+    pub speed: f32,
+    /// Length of the trail in rows
+    pub trail: u32,
+    /// New drops each second in each column
+    pub density: f32,
+    #[serde(skip)]
+    drops: Vec<Drop>,
+    /// Part of a drop that is still to be started
+    #[serde(skip)]
+    pending: f32,
+    #[serde(skip)]
+    rng: Rng,
+}
+
+impl Default for Rain {
+    fn default() -> Self {
+        Self {
+            speed: 30.0,
+            trail: 12,
+            density: 0.4,
+            drops: Vec::new(),
+            pending: 0.0,
+            rng: Rng::default(),
+        }
+    }
+}
+
+impl GeneratorState for Rain {
+    fn reset(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.drops.clear();
+        self.pending = 0.0;
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        for drop in self.drops.iter_mut() {
+            drop.y += drop.speed * dt;
+        }
+        let trail = self.trail as f32;
+        self.drops.retain(|drop| drop.y - trail < HEIGHT as f32);
+
+        self.pending += self.density.max(0.0) * WIDTH as f32 * dt;
+        while self.pending >= 1.0 {
+            if self.drops.len() >= MAX_DROPS {
+                self.pending = 0.0;
+                break;
+            }
+            self.pending -= 1.0;
+            self.drops.push(Drop {
+                x: self.rng.below(WIDTH),
+                y: 0.0,
+                speed: (self.speed * (0.5 + self.rng.unit())).max(MIN_SPEED),
+            });
+        }
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8) {
+        let rows = grid.get_mut();
+        for drop in &self.drops {
+            let head = drop.y.floor() as i32;
+            for i in 0..=self.trail as i32 {
+                let y = head - i;
+                if !(0..HEIGHT as i32).contains(&y) {
+                    continue;
+                }
+                let level = 1.0 - i as f32 / (self.trail + 1) as f32;
+                let pixel = &mut rows[y as usize][drop.x];
+                *pixel = (*pixel).max((bright as f32 * level) as u8);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Rain;
+    use crate::{generators::GeneratorState, AnimeGrid};
+
+    #[test]
+    fn drops_fall_with_a_trail() {
+        let mut rain = Rain {
+            speed: 10.0,
+            trail: 3,
+            // One and a half drops in the first step
+            density: 1.5 / 3.3,
+            ..Default::default()
+        };
+        rain.reset(1);
+        rain.advance(Duration::from_millis(100));
+        assert_eq!(rain.drops.len(), 1);
+        let x = rain.drops[0].x;
+        rain.drops[0].speed = 10.0;
+        rain.density = 0.0;
+        rain.advance(Duration::from_millis(500));
+
+        let mut grid = AnimeGrid::new(None);
+        rain.draw(&mut grid, 200);
+        let column: Vec<u8> = grid.get().iter().map(|row| row[x]).take(7).collect();
+        assert_eq!(column, [0, 0, 50, 100, 150, 200, 0]);
+    }
+
+    #[test]
+    fn drops_are_limited() {
+        let mut rain = Rain {
+            speed: -5.0,
+            density: 1000.0,
+            ..Default::default()
+        };
+        rain.reset(2);
+        for _ in 0..100 {
+            rain.advance(Duration::from_millis(100));
+            assert!(rain.drops.len() <= super::MAX_DROPS);
+        }
+        // Every drop still falls off the bottom
+        rain.density = 0.0;
+        rain.advance(Duration::from_secs(100));
+        assert!(rain.drops.is_empty());
+    }
+}
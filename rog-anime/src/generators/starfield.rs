@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{GeneratorState, Rng};
+use crate::{
+    grid::{HEIGHT, WIDTH},
+    AnimeGrid,
+};
+
+/// Stars closer than this are replaced with a new distant star
+const NEAREST: f32 = 0.05;
+/// The dimmest a star is drawn, as a fraction of the brightness
+const DIMMEST: f32 = 0.2;
+
+#[derive(Debug, Clone)]
+struct Star {
+    /// Position across the view, -1.0 to 1.0
+    x: f32,
+    y: f32,
+    /// Distance from the viewer, 1.0 is furthest
+    z: f32,
+}
+
+/// Stars flying out from the centre of the display, brightening as they near
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Starfield {
+    pub count: u32,
+    /// Fraction of the furthest distance a star travels each second
+    pub speed: f32,
+    #[serde(skip)]
+    stars: Vec<Star>,
+    #[serde(skip)]
+    rng: Rng,
+}
+
+impl Default for Starfield {
+    fn default() -> Self {
+        Self {
+            count: 40,
+            speed: 0.5,
+            stars: Vec::new(),
+            rng: Rng::default(),
+        }
+    }
+}
+
+impl Starfield {
+    fn new_star(&mut self, z: f32) -> Star {
+        Star {
+            x: self.rng.unit() * 2.0 - 1.0,
+            y: self.rng.unit() * 2.0 - 1.0,
+            z,
+        }
+    }
+
+    /// Column and row of a star, which may be outside of the grid
+    fn project(star: &Star) -> (f32, f32) {
+        let centre_x = WIDTH as f32 / 2.0;
+        let centre_y = HEIGHT as f32 / 2.0;
+        (
+            centre_x + star.x / star.z * centre_x,
+            centre_y + star.y / star.z * centre_y,
+        )
+    }
+
+    fn on_grid(star: &Star) -> bool {
+        let (x, y) = Self::project(star);
+        (0.0..WIDTH as f32).contains(&x) && (0.0..HEIGHT as f32).contains(&y)
+    }
+}
+
+impl GeneratorState for Starfield {
+    fn reset(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.stars = (0..self.count)
+            .map(|_| {
+                let z = self.rng.unit().max(NEAREST);
+                self.new_star(z)
+            })
+            .collect();
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        let travel = self.speed * dt.as_secs_f32();
+        for i in 0..self.stars.len() {
+            self.stars[i].z -= travel;
+            if self.stars[i].z <= NEAREST || !Self::on_grid(&self.stars[i]) {
+                self.stars[i] = self.new_star(1.0);
+            }
+        }
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, bright: u8) {
+        let rows = grid.get_mut();
+        for star in self.stars.iter().filter(|star| Self::on_grid(star)) {
+            let (x, y) = Self::project(star);
+            let level = (1.0 - star.z).max(DIMMEST);
+            let pixel = &mut rows[y as usize][x as usize];
+            *pixel = (*pixel).max((bright as f32 * level) as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Star, Starfield};
+    use crate::generators::GeneratorState;
+
+    #[test]
+    fn stars_fly_outwards() {
+        let mut field = Starfield {
+            count: 1,
+            speed: 0.5,
+            ..Default::default()
+        };
+        field.reset(0);
+        field.stars[0] = Star {
+            x: 0.1,
+            y: -0.1,
+            z: 1.0,
+        };
+        let (x, y) = Starfield::project(&field.stars[0]);
+        field.advance(Duration::from_secs(1));
+        let (x2, y2) = Starfield::project(&field.stars[0]);
+        assert!(x2 > x && y2 < y);
+        assert_eq!(field.stars[0].z, 0.5);
+
+        // Passes the viewer and starts again far away
+        field.advance(Duration::from_secs(1));
+        assert_eq!(field.stars[0].z, 1.0);
+    }
+}
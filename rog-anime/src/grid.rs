@@ -1,10 +1,11 @@
 use std::time::Duration;
 
 use crate::data::{AnimeDataBuffer, ANIME_DATA_LEN};
+use crate::font::{glyph, FontSize, GLYPH_SPACING, ROWS_PER_PIXEL};
 use crate::image::LED_IMAGE_POSITIONS;
 
-const WIDTH: usize = 33;
-const HEIGHT: usize = 55;
+pub(crate) const WIDTH: usize = 33;
+pub(crate) const HEIGHT: usize = 55;
 
 /// Helper structure for writing images.
 ///
@@ -55,6 +56,45 @@ impl AnimeGrid {
         }
     }
 
+    /// Fill a `width` by `height` block with the top left at column `x` and
+    /// row `y`, clipped to the grid
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, b: u8) {
+        let columns = x.max(0)..(x + width as i32).min(WIDTH as i32);
+        for row in y.max(0)..(y + height as i32).min(HEIGHT as i32) {
+            for column in columns.clone() {
+                self.0[row as usize][column as usize] = b;
+            }
+        }
+    }
+
+    /// Draw `text` in the small builtin font with the top left at column `x`
+    /// and row `y`, clipped to the grid. Each font pixel covers
+    /// `ROWS_PER_PIXEL` rows.
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, b: u8) {
+        self.draw_text_sized(text, x, y, FontSize::Small, b)
+    }
+
+    /// As `draw_text()`, in any of the font sizes
+    pub fn draw_text_sized(&mut self, text: &str, x: i32, y: i32, size: FontSize, b: u8) {
+        let scale = size.scale();
+        let mut left = x;
+        for c in text.chars() {
+            let rows = glyph(c);
+            for (gy, row) in rows.iter().enumerate() {
+                for (gx, _) in row.chars().enumerate().filter(|(_, p)| *p == '#') {
+                    self.fill_rect(
+                        left + (gx * scale) as i32,
+                        y + (gy * ROWS_PER_PIXEL * scale) as i32,
+                        scale as u32,
+                        (ROWS_PER_PIXEL * scale) as u32,
+                        b,
+                    );
+                }
+            }
+            left += ((rows[0].len() + GLYPH_SPACING) * scale) as i32;
+        }
+    }
+
     // pub fn debug_print(&self) {
     //     // this is the index from right. It is used to progressively shorten rows
     //     let mut prog_row_len = WIDTH - 2;
@@ -173,4 +213,38 @@ mod tests {
         ];
         assert_eq!(matrix.get(), &data_cmp);
     }
+
+    #[test]
+    fn text_is_drawn_and_clipped() {
+        let mut grid = AnimeGrid::new(None);
+        grid.draw_text("1:", 0, 0, 200);
+        // The stem of the 1 covers two rows per font pixel
+        assert_eq!(grid.get()[0][1], 200);
+        assert_eq!(grid.get()[1][1], 200);
+        assert_eq!(grid.get()[0][0], 0);
+        assert_eq!(grid.get()[2][0], 200);
+        // The colon starts after the 3 wide glyph and a space
+        assert_eq!(grid.get()[2][4], 200);
+        assert_eq!(grid.get()[0][4], 0);
+
+        // Nothing outside the grid
+        let mut grid = AnimeGrid::new(None);
+        grid.draw_text("88", -2, HEIGHT as i32 - 2, 255);
+        assert_eq!(grid.get()[HEIGHT - 1][0], 255);
+        assert_eq!(grid.get()[HEIGHT - 2][4], 255);
+    }
+
+    #[test]
+    fn rect_is_clipped() {
+        let mut grid = AnimeGrid::new(None);
+        grid.fill_rect(WIDTH as i32 - 2, -1, 4, 3, 9);
+        let lit: usize = grid
+            .get()
+            .iter()
+            .map(|row| row.iter().filter(|b| **b == 9).count())
+            .sum();
+        assert_eq!(lit, 4);
+        assert_eq!(grid.get()[1][WIDTH - 1], 9);
+        assert_eq!(grid.get()[2][WIDTH - 1], 0);
+    }
 }
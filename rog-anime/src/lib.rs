@@ -21,6 +21,30 @@ pub use diagonal::*;
 mod gif;
 pub use crate::gif::*;
 
+/// A small bitmap font for drawing text on the grid
+mod font;
+pub use font::*;
+
+/// Static and scrolling lines of text in the builtin fonts
+mod text;
+pub use text::*;
+
+/// The local time and date drawn in the builtin font
+mod clock;
+pub use clock::*;
+
+/// Gauges of CPU load, temperatures and fan speeds
+mod sysinfo;
+pub use sysinfo::*;
+
+/// Spectrum and VU meters of audio samples
+mod audio;
+pub use audio::*;
+
+/// Seeded procedural effects such as digital rain and the Game of Life
+mod generators;
+pub use generators::*;
+
 /// A container of images/grids/gifs/pauses which can be iterated over to generate
 /// cool effects
 mod sequencer;
@@ -3,7 +3,11 @@ use std::{path::PathBuf, time::Duration};
 use glam::Vec2;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::{error::AnimeError, AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage};
+use crate::{
+    error::AnimeError, AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AudioEq,
+    AudioSource, Clock, EqStyle, FontSize, Gauge, Generator, Procedural, SystemInfo, Text,
+    TextMode,
+};
 
 /// All the possible AniMe actions that can be used. This enum is intended to be
 /// a helper for loading up `ActionData`.
@@ -38,6 +42,55 @@ pub enum ActionLoader {
         time: AnimTime,
         brightness: f32,
     },
+    /// A line of text, either static or scrolling
+    Text {
+        text: String,
+        font: FontSize,
+        /// Row of the top of the text
+        row: u32,
+        mode: TextMode,
+        time: AnimTime,
+        brightness: f32,
+    },
+    /// The local time, and optionally the date, drawn in the builtin font
+    TimeDate {
+        /// 12 hour time, without AM or PM
+        hour_12: bool,
+        seconds: bool,
+        date: bool,
+        /// Top left of the time, as column and row on the display
+        translation: Vec2,
+        time: AnimTime,
+        brightness: f32,
+    },
+    /// A spectrum or VU meter of an audio source
+    AudioEq {
+        source: AudioSource,
+        style: EqStyle,
+        /// Number of bars, up to 50
+        bands: u32,
+        /// Fraction of the full height a bar falls each second
+        decay: f32,
+        /// How long the peak of each bar is held before falling
+        peak_hold: Duration,
+        time: AnimTime,
+        brightness: f32,
+    },
+    /// Live gauges of system sensors
+    SystemInfo {
+        gauges: Vec<Gauge>,
+        /// How often the sensors are read and the gauges redrawn
+        refresh: Duration,
+        time: AnimTime,
+        brightness: f32,
+    },
+    /// A procedural effect, started from `seed` each time it is shown
+    Matrix {
+        generator: Generator,
+        seed: u64,
+        time: AnimTime,
+        brightness: f32,
+    },
     /// A pause to be used between sequences
     Pause(Duration),
 }
@@ -52,14 +105,16 @@ pub enum ActionData {
     Image(Box<AnimeDataBuffer>),
     /// A pause to be used between sequences
     Pause(Duration),
-    /// Placeholder
-    AudioEq,
-    /// Placeholder
-    SystemInfo,
-    /// Placeholder
-    TimeDate,
-    /// Placeholder
-    Matrix,
+    /// A meter which reads its audio source as it is shown
+    AudioEq(AudioEq),
+    /// Gauges which read the system sensors as they are shown
+    SystemInfo(SystemInfo),
+    /// A line of text, scrolled as it is shown
+    Text(Text),
+    /// The local time, drawn as it is shown
+    TimeDate(Clock),
+    /// A procedural effect, generated as it is shown
+    Matrix(Procedural),
 }
 
 impl ActionData {
@@ -137,6 +192,75 @@ impl ActionData {
                     )?),
                 }
             }
+            ActionLoader::Text {
+                text,
+                font,
+                row,
+                mode,
+                time,
+                brightness,
+            } => ActionData::Text(Text {
+                text: text.clone(),
+                font: *font,
+                row: *row,
+                mode: *mode,
+                time: *time,
+                brightness: *brightness,
+            }),
+            ActionLoader::TimeDate {
+                hour_12,
+                seconds,
+                date,
+                translation,
+                time,
+                brightness,
+            } => ActionData::TimeDate(Clock {
+                hour_12: *hour_12,
+                seconds: *seconds,
+                date: *date,
+                translation: *translation,
+                time: *time,
+                brightness: *brightness,
+            }),
+            ActionLoader::AudioEq {
+                source,
+                style,
+                bands,
+                decay,
+                peak_hold,
+                time,
+                brightness,
+            } => ActionData::AudioEq(AudioEq {
+                source: source.clone(),
+                style: *style,
+                bands: *bands,
+                decay: *decay,
+                peak_hold: *peak_hold,
+                time: *time,
+                brightness: *brightness,
+            }),
+            ActionLoader::SystemInfo {
+                gauges,
+                refresh,
+                time,
+                brightness,
+            } => ActionData::SystemInfo(SystemInfo {
+                gauges: gauges.clone(),
+                refresh: *refresh,
+                time: *time,
+                brightness: *brightness,
+            }),
+            ActionLoader::Matrix {
+                generator,
+                seed,
+                time,
+                brightness,
+            } => ActionData::Matrix(Procedural {
+                generator: generator.clone(),
+                seed: *seed,
+                time: *time,
+                brightness: *brightness,
+            }),
             ActionLoader::Pause(duration) => ActionData::Pause(*duration),
         };
         Ok(a)
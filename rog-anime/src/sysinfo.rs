@@ -0,0 +1,436 @@
+use std::{
+    collections::VecDeque,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use glam::Vec2;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    data::run_frames, error::AnimeError, font::text_width, AnimTime, AnimeDataBuffer, AnimeGrid,
+    GLYPH_SPACING,
+};
+
+/// The fastest the gauges will be redrawn
+const MIN_REFRESH: Duration = Duration::from_millis(100);
+/// Brightness of the unlit part of a bar, as a fraction of the gauge brightness
+const TRACK_LEVEL: f32 = 0.1;
+
+/// hwmon names which report a CPU temperature as `temp1_input`
+const CPU_HWMON: [&str; 4] = ["k10temp", "coretemp", "zenpower", "acpitz"];
+/// hwmon names which report a GPU temperature as `temp1_input`
+const GPU_HWMON: [&str; 3] = ["amdgpu", "nouveau", "radeon"];
+/// The ASUS platform hwmon devices, which report the CPU fan as `fan1_input`
+/// and the GPU fan as `fan2_input`. The fan curves are on the same platform.
+const FAN_HWMON: [&str; 2] = ["asus", "asus_custom_fan_curve"];
+
+/// A value that a gauge can show
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Sensor {
+    /// Fraction of CPU time busy across all cores
+    CpuLoad,
+    /// Degrees celsius
+    CpuTemp,
+    /// Degrees celsius
+    GpuTemp,
+    /// RPM
+    CpuFan,
+    /// RPM
+    GpuFan,
+}
+
+impl Sensor {
+    /// The value at which the gauge is empty, and the value at which it is full
+    pub fn range(self) -> (f32, f32) {
+        match self {
+            Sensor::CpuLoad => (0.0, 1.0),
+            Sensor::CpuTemp | Sensor::GpuTemp => (30.0, 100.0),
+            Sensor::CpuFan | Sensor::GpuFan => (0.0, 6000.0),
+        }
+    }
+}
+
+/// One read of every sensor. A sensor that could not be read is `None`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SensorReadings {
+    pub cpu_load: Option<f32>,
+    pub cpu_temp: Option<f32>,
+    pub gpu_temp: Option<f32>,
+    pub cpu_fan: Option<f32>,
+    pub gpu_fan: Option<f32>,
+}
+
+impl SensorReadings {
+    pub fn get(&self, sensor: Sensor) -> Option<f32> {
+        match sensor {
+            Sensor::CpuLoad => self.cpu_load,
+            Sensor::CpuTemp => self.cpu_temp,
+            Sensor::GpuTemp => self.gpu_temp,
+            Sensor::CpuFan => self.cpu_fan,
+            Sensor::GpuFan => self.gpu_fan,
+        }
+    }
+}
+
+/// Where the gauges get their readings from. `SysfsSensors` reads the running
+/// system, other sources can be used to replay or fake readings.
+pub trait SensorSource {
+    /// Read every sensor, called once per refresh
+    fn read(&mut self) -> SensorReadings;
+}
+
+/// Reads the CPU load from `/proc/stat`, and temperatures and fan speeds from
+/// the hwmon devices in `/sys/class/hwmon`
+#[derive(Debug, Clone)]
+pub struct SysfsSensors {
+    proc_stat: PathBuf,
+    hwmon: PathBuf,
+    /// Busy and total CPU time at the last read
+    last_cpu: Option<(u64, u64)>,
+}
+
+impl Default for SysfsSensors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysfsSensors {
+    pub fn new() -> Self {
+        Self::with_paths("/proc/stat", "/sys/class/hwmon")
+    }
+
+    pub fn with_paths(proc_stat: impl Into<PathBuf>, hwmon: impl Into<PathBuf>) -> Self {
+        Self {
+            proc_stat: proc_stat.into(),
+            hwmon: hwmon.into(),
+            last_cpu: None,
+        }
+    }
+
+    fn cpu_load(&mut self) -> Option<f32> {
+        let (busy, total) = read_to_string(&self.proc_stat)
+            .ok()
+            .and_then(|stat| parse_cpu_times(&stat))?;
+        // The first read is the average since boot
+        let (last_busy, last_total) = self.last_cpu.replace((busy, total)).unwrap_or((0, 0));
+        let total = total.saturating_sub(last_total);
+        if total == 0 {
+            return None;
+        }
+        Some(busy.saturating_sub(last_busy) as f32 / total as f32)
+    }
+
+    /// The first hwmon device with a name in `names`
+    fn hwmon_named(&self, names: &[&str]) -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = read_dir(&self.hwmon)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        dirs.sort();
+        dirs.into_iter().find(|dir| {
+            read_to_string(dir.join("name"))
+                .map(|name| names.contains(&name.trim()))
+                .unwrap_or(false)
+        })
+    }
+
+    fn hwmon_value(&self, names: &[&str], attribute: &str) -> Option<f32> {
+        self.hwmon_named(names)
+            .and_then(|dir| read_value(&dir.join(attribute)))
+    }
+}
+
+impl SensorSource for SysfsSensors {
+    fn read(&mut self) -> SensorReadings {
+        SensorReadings {
+            cpu_load: self.cpu_load(),
+            // Temperatures are in millidegrees
+            cpu_temp: self
+                .hwmon_value(&CPU_HWMON, "temp1_input")
+                .map(|t| t / 1000.0),
+            gpu_temp: self
+                .hwmon_value(&GPU_HWMON, "temp1_input")
+                .map(|t| t / 1000.0),
+            cpu_fan: self.hwmon_value(&FAN_HWMON, "fan1_input"),
+            gpu_fan: self.hwmon_value(&FAN_HWMON, "fan2_input"),
+        }
+    }
+}
+
+fn read_value(path: &Path) -> Option<f32> {
+    read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Busy and total CPU time from the `cpu` line of `/proc/stat`
+fn parse_cpu_times(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let times: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    if times.len() < 4 {
+        return None;
+    }
+    // Guest time is already counted in user and nice
+    let total: u64 = times.iter().take(8).sum();
+    // idle and iowait
+    let idle = times[3] + times.get(4).unwrap_or(&0);
+    Some((total - idle, total))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum GaugeStyle {
+    /// A horizontal bar filled from the left
+    Bar,
+    /// A column per reading, newest on the right
+    Sparkline,
+}
+
+/// A single gauge drawn on the display
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Gauge {
+    pub sensor: Sensor,
+    pub style: GaugeStyle,
+    /// Drawn in the builtin font to the left of the gauge
+    pub label: Option<String>,
+    /// Top left of the gauge, or the label if there is one, as column and row
+    /// on the display
+    pub translation: Vec2,
+    /// Size of the gauge in columns
+    pub width: u32,
+    /// Size of the gauge in rows
+    pub height: u32,
+}
+
+impl Gauge {
+    /// Where a reading falls in the sensor's range, from 0.0 to 1.0
+    fn level(&self, value: f32) -> f32 {
+        let (min, max) = self.sensor.range();
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    }
+
+    fn draw(&self, grid: &mut AnimeGrid, history: &VecDeque<Option<f32>>, bright: u8) {
+        let mut x = self.translation.x.round() as i32;
+        let y = self.translation.y.round() as i32;
+        if let Some(label) = &self.label {
+            grid.draw_text(label, x, y, bright);
+            x += (text_width(label) + GLYPH_SPACING) as i32;
+        }
+
+        match self.style {
+            GaugeStyle::Bar => {
+                let track = (bright as f32 * TRACK_LEVEL) as u8;
+                grid.fill_rect(x, y, self.width, self.height, track);
+                if let Some(Some(value)) = history.back() {
+                    let filled = (self.level(*value) * self.width as f32).round() as u32;
+                    grid.fill_rect(x, y, filled, self.height, bright);
+                }
+            }
+            GaugeStyle::Sparkline => {
+                // Right align so the newest reading is always at the end
+                let start = x + self.width as i32 - history.len() as i32;
+                for (i, value) in history.iter().enumerate() {
+                    if let Some(value) = value {
+                        let lit = (self.level(*value) * self.height as f32).round() as u32;
+                        let top = y + (self.height - lit) as i32;
+                        grid.fill_rect(start + i as i32, top, 1, lit, bright);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Gauges showing live system sensors
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SystemInfo {
+    pub gauges: Vec<Gauge>,
+    /// How often the sensors are read and the gauges redrawn
+    pub refresh: Duration,
+    pub time: AnimTime,
+    pub brightness: f32,
+}
+
+impl SystemInfo {
+    /// An empty history for each gauge, to be filled by `update`
+    pub fn new_history(&self) -> Vec<VecDeque<Option<f32>>> {
+        self.gauges
+            .iter()
+            .map(|gauge| VecDeque::with_capacity(gauge.width as usize))
+            .collect()
+    }
+
+    /// Add the readings to the history of each gauge, dropping any that no
+    /// longer fit
+    pub fn update(&self, history: &mut [VecDeque<Option<f32>>], readings: &SensorReadings) {
+        for (gauge, values) in self.gauges.iter().zip(history.iter_mut()) {
+            values.push_back(readings.get(gauge.sensor));
+            while values.len() > gauge.width.max(1) as usize {
+                values.pop_front();
+            }
+        }
+    }
+
+    pub fn render(&self, history: &[VecDeque<Option<f32>>]) -> AnimeGrid {
+        let bright = (self.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+        let mut grid = AnimeGrid::new(None);
+        for (gauge, values) in self.gauges.iter().zip(history.iter()) {
+            gauge.draw(&mut grid, values, bright);
+        }
+        grid
+    }
+}
+
+/// Runs the gauges as a blocking loop by using the `callback` to write data,
+/// reading the `source` once per refresh
+pub fn run_system_info(
+    info: &SystemInfo,
+    source: &mut dyn SensorSource,
+    do_early_return: Arc<AtomicBool>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    let mut history = info.new_history();
+    run_frames(
+        info.time,
+        do_early_return,
+        &mut |_| {
+            info.update(&mut history, &source.read());
+            Some((info.render(&history).into(), info.refresh.max(MIN_REFRESH)))
+        },
+        callback,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{atomic::AtomicBool, Arc, Mutex},
+        time::Duration,
+    };
+
+    use glam::Vec2;
+
+    use super::{
+        parse_cpu_times, run_system_info, Gauge, GaugeStyle, Sensor, SensorReadings, SensorSource,
+        SystemInfo,
+    };
+    use crate::{AnimTime, AnimeGrid};
+
+    /// Replays readings in order, repeating the last
+    struct Canned(VecDeque<SensorReadings>);
+
+    impl SensorSource for Canned {
+        fn read(&mut self) -> SensorReadings {
+            if self.0.len() > 1 {
+                self.0.pop_front().unwrap()
+            } else {
+                self.0[0]
+            }
+        }
+    }
+
+    fn load(load: f32) -> SensorReadings {
+        SensorReadings {
+            cpu_load: Some(load),
+            ..Default::default()
+        }
+    }
+
+    fn info(style: GaugeStyle, label: Option<&str>) -> SystemInfo {
+        SystemInfo {
+            gauges: vec![Gauge {
+                sensor: Sensor::CpuLoad,
+                style,
+                label: label.map(|l| l.to_string()),
+                translation: Vec2::new(4.0, 10.0),
+                width: 10,
+                height: 4,
+            }],
+            refresh: Duration::from_secs(1),
+            time: AnimTime::Infinite,
+            brightness: 1.0,
+        }
+    }
+
+    #[test]
+    fn cpu_times() {
+        let stat = "cpu  100 5 50 800 45 0 0 0 10 0\ncpu0 1 2 3 4\n";
+        assert_eq!(parse_cpu_times(stat), Some((155, 1000)));
+        assert_eq!(parse_cpu_times("intr 1 2 3"), None);
+    }
+
+    #[test]
+    fn bar_fills_to_level() {
+        let info = info(GaugeStyle::Bar, None);
+        let mut history = info.new_history();
+        info.update(&mut history, &load(0.3));
+        let grid = info.render(&history);
+        let row = &grid.get()[11];
+        assert!(row[4..7].iter().all(|b| *b == 255));
+        assert!(row[7..14].iter().all(|b| *b == 25));
+        assert_eq!(row[14], 0);
+        assert_eq!(grid.get()[13][4], 255);
+        assert_eq!(grid.get()[14][4], 0);
+    }
+
+    #[test]
+    fn missing_reading_leaves_the_track() {
+        let info = info(GaugeStyle::Bar, None);
+        let mut history = info.new_history();
+        info.update(&mut history, &SensorReadings::default());
+        assert_eq!(info.render(&history).get()[10][4], 25);
+    }
+
+    #[test]
+    fn sparkline_scrolls() {
+        let info = info(GaugeStyle::Sparkline, Some("C"));
+        let mut history = info.new_history();
+        for i in 0..12 {
+            info.update(&mut history, &load(if i == 11 { 1.0 } else { 0.5 }));
+        }
+        assert_eq!(history[0].len(), 10);
+        let grid = info.render(&history);
+
+        let mut label = AnimeGrid::new(None);
+        label.draw_text("C", 4, 10, 255);
+        assert_eq!(grid.get()[10][..8], label.get()[10][..8]);
+        // The gauge starts after the label and a space
+        let x = 8;
+        // Half full columns, then the newest full column
+        assert_eq!(grid.get()[11][x], 0);
+        assert_eq!(grid.get()[12][x], 255);
+        assert_eq!(grid.get()[10][x + 9], 255);
+        assert_eq!(grid.get()[10][x + 8], 0);
+    }
+
+    #[test]
+    fn reads_once_per_frame() {
+        let info = SystemInfo {
+            time: AnimTime::Count(2),
+            refresh: Duration::from_millis(0),
+            ..info(GaugeStyle::Bar, None)
+        };
+        let frames = Mutex::new(Vec::new());
+        let mut source = Canned(vec![load(0.0), load(1.0)].into());
+        run_system_info(
+            &info,
+            &mut source,
+            Arc::new(AtomicBool::new(false)),
+            &|frame| {
+                frames.lock().unwrap().push(frame);
+                Ok(())
+            },
+        )
+        .unwrap();
+        let frames = frames.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].get().iter().all(|b| *b < 255));
+        assert!(frames[1].get().contains(&255));
+    }
+}
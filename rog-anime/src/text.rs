@@ -0,0 +1,252 @@
+use std::{
+    ops::Range,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    data::run_frames,
+    error::AnimeError,
+    grid::{HEIGHT, WIDTH},
+    image::LED_IMAGE_POSITIONS,
+    AnimTime, AnimeDataBuffer, AnimeGrid, FontSize,
+};
+
+/// How often static text is redrawn
+const STATIC_STEP: Duration = Duration::from_secs(1);
+/// The shortest time between marquee frames
+const MIN_STEP: Duration = Duration::from_millis(20);
+/// The range of marquee speeds in columns a second, slower or faster speeds
+/// are clamped to it
+const MIN_SPEED: f32 = 0.5;
+const MAX_SPEED: f32 = 1000.0;
+
+/// The grid columns which have an LED on every one of `rows`. The display
+/// narrows from the left going down, so lower rows have fewer columns.
+pub fn visible_columns(rows: Range<usize>) -> Range<i32> {
+    let mut first = 0;
+    let mut end = WIDTH as i32;
+    for row in rows {
+        let (min, max) = LED_IMAGE_POSITIONS
+            .iter()
+            .flatten()
+            .filter(|led| led.y().ceil() as usize == row)
+            .map(|led| led.x().ceil() as i32)
+            .fold((i32::MAX, i32::MIN), |(min, max), x| {
+                (min.min(x), max.max(x))
+            });
+        if min > max {
+            // Below the display
+            return 0..0;
+        }
+        first = first.max(min);
+        end = end.min(max + 1);
+    }
+    first..end.max(first)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum TextMode {
+    /// Centred in the visible columns, or from the left if it does not fit
+    Static,
+    /// Scrolls in from the right and out to the left at `speed` columns a
+    /// second
+    Marquee { speed: f32 },
+}
+
+/// A line of text in one of the builtin fonts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Text {
+    pub text: String,
+    pub font: FontSize,
+    /// Row of the top of the text
+    pub row: u32,
+    pub mode: TextMode,
+    pub time: AnimTime,
+    pub brightness: f32,
+}
+
+impl Text {
+    /// The columns the text is shown in, which are the columns visible on
+    /// all of its rows
+    pub fn columns(&self) -> Range<i32> {
+        let top = (self.row as usize).min(HEIGHT);
+        visible_columns(top..top + self.font.height())
+    }
+
+    /// The marquee speed, `None` for static text or if the speed is not
+    /// above zero
+    fn speed(&self) -> Option<f32> {
+        match self.mode {
+            TextMode::Marquee { speed } if speed > 0.0 => Some(speed.clamp(MIN_SPEED, MAX_SPEED)),
+            _ => None,
+        }
+    }
+
+    /// Columns from the text first appearing to it having gone
+    fn travel(&self) -> i32 {
+        let columns = self.columns();
+        columns.end - columns.start + self.font.width(&self.text) as i32
+    }
+
+    /// Time for marquee text to scroll all of the way past
+    pub fn pass_time(&self) -> Option<Duration> {
+        self.speed()
+            .map(|speed| Duration::from_secs_f32(self.travel() as f32 / speed))
+    }
+
+    /// The text as shown at `elapsed` after it started
+    pub fn render(&self, elapsed: Duration) -> AnimeGrid {
+        let bright = (self.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+        let columns = self.columns();
+        let width = self.font.width(&self.text) as i32;
+        let x = match self.speed() {
+            Some(speed) => {
+                let offset = (elapsed.as_secs_f32() * speed) as i32 % self.travel().max(1);
+                columns.end - offset
+            }
+            None => columns.start + ((columns.end - columns.start - width) / 2).max(0),
+        };
+
+        let mut text = AnimeGrid::new(None);
+        text.draw_text_sized(&self.text, x, self.row as i32, self.font, bright);
+        // Only the columns visible on every row, so the text has straight edges
+        let mut grid = AnimeGrid::new(None);
+        let visible = columns.start as usize..columns.end as usize;
+        for (row, text_row) in grid.get_mut().iter_mut().zip(text.get().iter()) {
+            row[visible.clone()].copy_from_slice(&text_row[visible.clone()]);
+        }
+        grid
+    }
+}
+
+/// Runs the text as a blocking loop by using the `callback` to write data.
+/// With `AnimTime::Count` marquee text scrolls past that many times, and
+/// static text is shown for that many seconds.
+pub fn run_text(
+    text: &Text,
+    do_early_return: Arc<AtomicBool>,
+    callback: &dyn Fn(AnimeDataBuffer) -> Result<(), AnimeError>,
+) -> Result<(), AnimeError> {
+    let (time, end) = match (text.time, text.pass_time()) {
+        (AnimTime::Count(passes), Some(pass)) => (AnimTime::Infinite, Some(pass * passes)),
+        (time, _) => (time, None),
+    };
+    let step = match text.speed() {
+        Some(speed) => Duration::from_secs_f32(1.0 / speed).max(MIN_STEP),
+        None => STATIC_STEP,
+    };
+    run_frames(
+        time,
+        do_early_return,
+        &mut |elapsed| {
+            if matches!(end, Some(end) if elapsed >= end) {
+                return None;
+            }
+            Some((text.render(elapsed).into(), step))
+        },
+        callback,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{run_text, visible_columns, Text, TextMode};
+    use crate::{AnimTime, AnimeGrid, FontSize};
+
+    fn text(mode: TextMode, font: FontSize, row: u32) -> Text {
+        Text {
+            text: "HI".into(),
+            font,
+            row,
+            mode,
+            time: AnimTime::Count(1),
+            brightness: 1.0,
+        }
+    }
+
+    /// Columns with anything lit
+    fn lit_columns(grid: &AnimeGrid) -> Vec<usize> {
+        (0..grid.get()[0].len())
+            .filter(|x| grid.get().iter().any(|row| row[*x] > 0))
+            .collect()
+    }
+
+    #[test]
+    fn columns_follow_the_display() {
+        assert_eq!(visible_columns(0..7), 0..33);
+        assert_eq!(visible_columns(0..10), 2..33);
+        assert_eq!(visible_columns(45..55), 24..33);
+        assert_eq!(visible_columns(50..60), 0..0);
+    }
+
+    #[test]
+    fn static_text_is_centred() {
+        let grid = text(TextMode::Static, FontSize::Small, 0).render(Duration::default());
+        // 7 columns wide, in the 31 columns from 2
+        let lit = lit_columns(&grid);
+        assert_eq!(lit.first(), Some(&14));
+        assert_eq!(lit.last(), Some(&20));
+
+        let grid = text(TextMode::Static, FontSize::Large, 20).render(Duration::default());
+        let mut expected = AnimeGrid::new(None);
+        // 14 columns wide, in the 16 columns from 17
+        expected.draw_text_sized("HI", 18, 20, FontSize::Large, 255);
+        assert_eq!(grid.get(), expected.get());
+    }
+
+    #[test]
+    fn marquee_scrolls_and_is_clipped() {
+        let text = text(TextMode::Marquee { speed: 10.0 }, FontSize::Small, 45);
+        assert_eq!(text.pass_time(), Some(Duration::from_secs_f32(1.6)));
+        assert!(lit_columns(&text.render(Duration::default())).is_empty());
+        // Two columns of the H are in from the right
+        assert_eq!(
+            lit_columns(&text.render(Duration::from_millis(200))),
+            [31, 32]
+        );
+        // Leaving on the left, with none drawn past the visible columns
+        assert_eq!(lit_columns(&text.render(Duration::from_millis(1500))), [24]);
+        assert!(lit_columns(&text.render(Duration::from_millis(1600))).is_empty());
+    }
+
+    #[test]
+    fn marquee_count_is_passes() {
+        let text = text(TextMode::Marquee { speed: 400.0 }, FontSize::Small, 0);
+        let frames = Mutex::new(0);
+        run_text(&text, Arc::new(AtomicBool::new(false)), &|_| {
+            *frames.lock().unwrap() += 1;
+            Ok(())
+        })
+        .unwrap();
+        // 38 columns at 400 a second, with 20ms frames
+        let frames = *frames.lock().unwrap();
+        assert!((4..=6).contains(&frames), "{}", frames);
+    }
+
+    #[test]
+    fn marquee_speed_is_limited() {
+        let slow = text(TextMode::Marquee { speed: 1e-30 }, FontSize::Small, 0);
+        assert_eq!(slow.pass_time(), Some(Duration::from_secs(76)));
+        let fast = text(
+            TextMode::Marquee {
+                speed: f32::INFINITY,
+            },
+            FontSize::Small,
+            0,
+        );
+        assert!(fast.pass_time().is_some());
+        for speed in [0.0, -1.0, f32::NAN] {
+            let text = text(TextMode::Marquee { speed }, FontSize::Small, 0);
+            assert_eq!(text.pass_time(), None);
+            lit_columns(&text.render(Duration::from_secs(1)));
+        }
+    }
+}
@@ -9,7 +9,7 @@ use std::str::FromStr;
 #[cfg(feature = "dbus")]
 use zvariant_derive::Type;
 
-use crate::{error::Error, LED_MSG_LEN};
+use crate::{error::Error, Colour, LED_MSG_LEN};
 
 #[cfg_attr(feature = "dbus", derive(Type))]
 #[derive(Debug, PartialEq, Copy, Clone, Deserialize, Serialize)]
@@ -46,30 +46,6 @@ impl From<u32> for LedBrightness {
     }
 }
 
-#[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Debug, Clone, PartialEq, Copy, Deserialize, Serialize)]
-pub struct Colour(pub u8, pub u8, pub u8);
-
-impl Default for Colour {
-    fn default() -> Self {
-        Colour(166, 0, 0)
-    }
-}
-
-impl FromStr for Colour {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() < 6 {
-            return Err(Error::ParseColour);
-        }
-        let r = u8::from_str_radix(&s[0..2], 16).or(Err(Error::ParseColour))?;
-        let g = u8::from_str_radix(&s[2..4], 16).or(Err(Error::ParseColour))?;
-        let b = u8::from_str_radix(&s[4..6], 16).or(Err(Error::ParseColour))?;
-        Ok(Colour(r, g, b))
-    }
-}
-
 #[cfg_attr(feature = "dbus", derive(Type))]
 #[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
 pub enum Speed {
@@ -213,13 +189,33 @@ pub enum AuraZone {
     Three,
     Four,
 }
+impl Default for AuraZone {
+    fn default() -> Self {
+        AuraZone::None
+    }
+}
+impl FromStr for AuraZone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "none" | "0" => Ok(AuraZone::None),
+            "one" | "1" => Ok(AuraZone::One),
+            "two" | "2" => Ok(AuraZone::Two),
+            "three" | "3" => Ok(AuraZone::Three),
+            "four" | "4" => Ok(AuraZone::Four),
+            _ => Err(Error::ParseZone),
+        }
+    }
+}
 
 /// Default factory modes structure. This easily converts to an USB HID packet with:
 /// ```rust
 /// let bytes: [u8; LED_MSG_LEN] = mode.into();
 /// ```
 #[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AuraEffect {
     /// The effect type
     pub mode: AuraModeNum,
@@ -0,0 +1,462 @@
+//! Colour parsing, colour spaces, and interpolation shared by the builtin
+//! modes, the software effects and the CLI.
+
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+#[cfg(feature = "dbus")]
+use zvariant_derive::Type;
+
+use crate::error::Error;
+
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, PartialEq, Copy, Deserialize, Serialize)]
+pub struct Colour(pub u8, pub u8, pub u8);
+
+impl Default for Colour {
+    fn default() -> Self {
+        Colour(166, 0, 0)
+    }
+}
+
+/// Names accepted by `Colour::from_str()`, the CSS basic colours plus a few
+/// commonly used extended ones
+pub const NAMED_COLOURS: [(&str, Colour); 26] = [
+    ("black", Colour(0, 0, 0)),
+    ("silver", Colour(192, 192, 192)),
+    ("gray", Colour(128, 128, 128)),
+    ("grey", Colour(128, 128, 128)),
+    ("white", Colour(255, 255, 255)),
+    ("maroon", Colour(128, 0, 0)),
+    ("red", Colour(255, 0, 0)),
+    ("purple", Colour(128, 0, 128)),
+    ("fuchsia", Colour(255, 0, 255)),
+    ("magenta", Colour(255, 0, 255)),
+    ("green", Colour(0, 128, 0)),
+    ("lime", Colour(0, 255, 0)),
+    ("olive", Colour(128, 128, 0)),
+    ("yellow", Colour(255, 255, 0)),
+    ("navy", Colour(0, 0, 128)),
+    ("blue", Colour(0, 0, 255)),
+    ("teal", Colour(0, 128, 128)),
+    ("aqua", Colour(0, 255, 255)),
+    ("cyan", Colour(0, 255, 255)),
+    ("orange", Colour(255, 165, 0)),
+    ("gold", Colour(255, 215, 0)),
+    ("pink", Colour(255, 192, 203)),
+    ("hotpink", Colour(255, 105, 180)),
+    ("violet", Colour(238, 130, 238)),
+    ("indigo", Colour(75, 0, 130)),
+    ("crimson", Colour(220, 20, 60)),
+];
+
+/// Kelvin values accepted by `Colour::from_kelvin()`
+pub const KELVIN_RANGE: (u32, u32) = (1000, 40000);
+
+/// A colour as hue in degrees (0.0-360.0), and saturation and value (0.0-1.0)
+#[derive(Debug, Clone, PartialEq, Copy, Default, Deserialize, Serialize)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+}
+
+/// The space in which colours are interpolated
+#[derive(Debug, Clone, PartialEq, Copy, Deserialize, Serialize)]
+pub enum ColourSpace {
+    /// Linear light RGB, avoids the dark middle of a plain sRGB blend
+    LinearRgb,
+    /// Around the shortest way of the hue circle, keeps blends saturated
+    Hsv,
+}
+
+impl Default for ColourSpace {
+    fn default() -> Self {
+        ColourSpace::LinearRgb
+    }
+}
+
+impl FromStr for ColourSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rgb" | "linear" | "linear-rgb" => Ok(ColourSpace::LinearRgb),
+            "hsv" => Ok(ColourSpace::Hsv),
+            _ => Err(Error::ParseColour),
+        }
+    }
+}
+
+#[inline]
+fn to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[inline]
+fn from_linear(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+#[inline]
+fn unit_to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl Colour {
+    pub fn from_hsv(hsv: Hsv) -> Self {
+        let h = hsv.h.rem_euclid(360.0) / 60.0;
+        let s = hsv.s.clamp(0.0, 1.0);
+        let v = hsv.v.clamp(0.0, 1.0);
+        let c = v * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = v - c;
+        Colour(unit_to_u8(r + m), unit_to_u8(g + m), unit_to_u8(b + m))
+    }
+
+    pub fn to_hsv(&self) -> Hsv {
+        let r = self.0 as f32 / 255.0;
+        let g = self.1 as f32 / 255.0;
+        let b = self.2 as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+        Hsv { h, s, v: max }
+    }
+
+    /// The colour of a black body at this temperature, clamped to `KELVIN_RANGE`.
+    /// 6500K is close to white, lower is warmer and higher is bluer.
+    pub fn from_kelvin(kelvin: u32) -> Self {
+        // Approximation of the CIE 1964 black body colours, from Tanner Helland
+        let t = kelvin.clamp(KELVIN_RANGE.0, KELVIN_RANGE.1) as f32 / 100.0;
+        let r = if t <= 66.0 {
+            255.0
+        } else {
+            329.698_73 * (t - 60.0).powf(-0.133_204_76)
+        };
+        let g = if t <= 66.0 {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_16 * (t - 60.0).powf(-0.075_514_85)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.517_73 * (t - 10.0).ln() - 305.044_8
+        };
+        Colour(
+            r.clamp(0.0, 255.0).round() as u8,
+            g.clamp(0.0, 255.0).round() as u8,
+            b.clamp(0.0, 255.0).round() as u8,
+        )
+    }
+
+    /// Scale each channel by `factor` (0.0-1.0)
+    pub fn scale(&self, factor: f32) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+        Colour(
+            (self.0 as f32 * factor) as u8,
+            (self.1 as f32 * factor) as u8,
+            (self.2 as f32 * factor) as u8,
+        )
+    }
+
+    /// Interpolate towards `other` in linear light, `t` is 0.0-1.0
+    pub fn lerp(&self, other: Colour, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| from_linear(to_linear(a) + (to_linear(b) - to_linear(a)) * t);
+        Colour(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        )
+    }
+
+    /// Interpolate towards `other` the short way around the hue circle, `t` is
+    /// 0.0-1.0. Greys take the hue of the other colour so they don't swing
+    /// through red.
+    pub fn lerp_hsv(&self, other: Colour, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mut a = self.to_hsv();
+        let mut b = other.to_hsv();
+        if a.s == 0.0 {
+            a.h = b.h;
+        }
+        if b.s == 0.0 {
+            b.h = a.h;
+        }
+        let mut dh = b.h - a.h;
+        if dh > 180.0 {
+            dh -= 360.0;
+        } else if dh < -180.0 {
+            dh += 360.0;
+        }
+        Colour::from_hsv(Hsv {
+            h: a.h + dh * t,
+            s: a.s + (b.s - a.s) * t,
+            v: a.v + (b.v - a.v) * t,
+        })
+    }
+
+    pub fn lerp_in(&self, other: Colour, t: f32, space: ColourSpace) -> Self {
+        match space {
+            ColourSpace::LinearRgb => self.lerp(other, t),
+            ColourSpace::Hsv => self.lerp_hsv(other, t),
+        }
+    }
+
+    /// The colour at `t` (0.0-1.0) along evenly spaced `stops`
+    pub fn gradient_at(stops: &[Colour], t: f32, space: ColourSpace) -> Self {
+        match stops.len() {
+            0 => Colour(0, 0, 0),
+            1 => stops[0],
+            len => {
+                let pos = t.clamp(0.0, 1.0) * (len - 1) as f32;
+                let i = (pos as usize).min(len - 2);
+                stops[i].lerp_in(stops[i + 1], pos - i as f32, space)
+            }
+        }
+    }
+
+    /// `steps` colours evenly spaced along `stops`, starting and ending with
+    /// the first and last stop
+    pub fn gradient(stops: &[Colour], steps: usize, space: ColourSpace) -> Vec<Colour> {
+        (0..steps)
+            .map(|i| {
+                let t = if steps > 1 {
+                    i as f32 / (steps - 1) as f32
+                } else {
+                    0.0
+                };
+                Colour::gradient_at(stops, t, space)
+            })
+            .collect()
+    }
+}
+
+/// Parse the arguments of a function like `rgb(1, 2, 3)`. Percentages are
+/// returned as 0.0-1.0 and flagged.
+fn parse_args(args: &str) -> Result<Vec<(f32, bool)>, Error> {
+    let args: Vec<(f32, bool)> = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .map(|a| match a.strip_suffix('%') {
+            Some(p) => p.parse::<f32>().map(|p| (p / 100.0, true)),
+            None => a.parse::<f32>().map(|a| (a, false)),
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::ParseColour)?;
+    if args.len() != 3 || args.iter().any(|(a, _)| !a.is_finite() || *a < 0.0) {
+        return Err(Error::ParseColour);
+    }
+    Ok(args)
+}
+
+fn parse_hex(hex: &str) -> Result<Colour, Error> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::ParseColour);
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).or(Err(Error::ParseColour));
+    match hex.len() {
+        3 => {
+            let c: Vec<u8> = hex
+                .chars()
+                .map(|c| channel(&c.to_string()).map(|c| c * 17))
+                .collect::<Result<_, _>>()?;
+            Ok(Colour(c[0], c[1], c[2]))
+        }
+        6 => Ok(Colour(
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        _ => Err(Error::ParseColour),
+    }
+}
+
+impl FromStr for Colour {
+    type Err = Error;
+
+    /// Accepts `rrggbb`, `#rrggbb`, `#rgb`, `rgb(r, g, b)` with 0-255 or
+    /// percentages, `hsv(h, s%, v%)` with the hue in degrees, a kelvin
+    /// temperature such as `6500k`, or a name from `NAMED_COLOURS`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some(args) = s.strip_prefix("rgb(").and_then(|a| a.strip_suffix(')')) {
+            let args = parse_args(args)?;
+            let c: Vec<u8> = args
+                .iter()
+                .map(|(c, pct)| {
+                    if *pct {
+                        Ok(unit_to_u8(*c))
+                    } else if *c <= 255.0 {
+                        Ok(c.round() as u8)
+                    } else {
+                        Err(Error::ParseColour)
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok(Colour(c[0], c[1], c[2]));
+        }
+        if let Some(args) = s.strip_prefix("hsv(").and_then(|a| a.strip_suffix(')')) {
+            let args = parse_args(args)?;
+            // Saturation and value may be given with or without the `%`
+            let unit = |(c, pct): (f32, bool)| if pct { c } else { c / 100.0 };
+            if args[0].1 || unit(args[1]) > 1.0 || unit(args[2]) > 1.0 {
+                return Err(Error::ParseColour);
+            }
+            return Ok(Colour::from_hsv(Hsv {
+                h: args[0].0,
+                s: unit(args[1]),
+                v: unit(args[2]),
+            }));
+        }
+        if let Some(kelvin) = s.strip_suffix('k') {
+            if let Ok(kelvin) = kelvin.parse::<u32>() {
+                if kelvin < KELVIN_RANGE.0 || kelvin > KELVIN_RANGE.1 {
+                    return Err(Error::ParseColour);
+                }
+                return Ok(Colour::from_kelvin(kelvin));
+            }
+        }
+        if let Some((_, colour)) = NAMED_COLOURS.iter().find(|(name, _)| *name == s) {
+            return Ok(*colour);
+        }
+        // Plain hex digits, as accepted before the other forms were added
+        if s.len() != 6 {
+            return Err(Error::ParseColour);
+        }
+        parse_hex(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Colour, ColourSpace, Hsv};
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_forms() {
+        let red = Colour(255, 0, 0);
+        for s in [
+            "ff0000",
+            "#FF0000",
+            "#f00",
+            "rgb(255, 0, 0)",
+            "rgb(100%,0%,0%)",
+            "hsv(0, 100%, 100%)",
+            "hsv(360 100 100)",
+            "Red",
+        ]
+        .iter()
+        {
+            assert_eq!(Colour::from_str(s).unwrap(), red, "{}", s);
+        }
+        assert_eq!(Colour::from_str("6500k").unwrap(), Colour(255, 254, 250));
+        assert_eq!(
+            Colour::from_str("hsv(120,50,50)").unwrap(),
+            Colour(64, 128, 64)
+        );
+
+        for s in [
+            "",
+            "ff00",
+            "fff",
+            "#ff00zz",
+            "rgb(256,0,0)",
+            "rgb(1,2)",
+            "hsv(0,101,0)",
+            "500k",
+            "nope",
+        ]
+        .iter()
+        {
+            assert!(Colour::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for c in [
+            Colour(166, 0, 0),
+            Colour(0, 128, 255),
+            Colour(12, 200, 99),
+            Colour(77, 77, 77),
+        ]
+        .iter()
+        {
+            assert_eq!(Colour::from_hsv(c.to_hsv()), *c);
+        }
+        let hsv = Colour(0, 0, 255).to_hsv();
+        assert_eq!(
+            hsv,
+            Hsv {
+                h: 240.0,
+                s: 1.0,
+                v: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        let red = Colour(255, 0, 0);
+        let blue = Colour(0, 0, 255);
+        // Linear light keeps the middle bright, sRGB would give (128, 0, 128)
+        assert_eq!(red.lerp(blue, 0.5), Colour(188, 0, 188));
+        assert_eq!(red.lerp(blue, 0.0), red);
+        assert_eq!(red.lerp(blue, 1.0), blue);
+        // The short way from red to blue is through magenta
+        assert_eq!(red.lerp_hsv(blue, 0.5), Colour(255, 0, 255));
+
+        let grad = Colour::gradient(&[red, Colour(0, 255, 0), blue], 5, ColourSpace::Hsv);
+        assert_eq!(
+            grad,
+            vec![
+                red,
+                Colour(255, 255, 0),
+                Colour(0, 255, 0),
+                Colour(0, 255, 255),
+                blue
+            ]
+        );
+        assert_eq!(
+            Colour::gradient(&[red], 3, ColourSpace::LinearRgb),
+            vec![red; 3]
+        );
+        assert!(Colour::gradient(&[], 0, ColourSpace::LinearRgb).is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::VecDeque, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{blend_max, EffectState};
+use crate::{layouts::KeyboardLayout, Colour, KeyColourArray};
+
+/// Distance the ball moves before a new trail point is recorded
+const TRAIL_SPACING: f32 = 0.5;
+/// Keys within this distance of the ball are lit
+const BALL_RADIUS: f32 = 0.7;
+
+/// A ball which bounces around the edges of the keyboard with a fading trail
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ball {
+    pub colour: Colour,
+    /// Keys per second
+    pub speed: f32,
+    /// Number of trail points to keep
+    pub trail: usize,
+    /// Starting position in key units
+    pub start: (f32, f32),
+    /// Starting direction, this is normalised before use
+    pub heading: (f32, f32),
+    #[serde(skip)]
+    position: Option<(f32, f32)>,
+    #[serde(skip)]
+    velocity: (f32, f32),
+    #[serde(skip)]
+    points: VecDeque<(f32, f32)>,
+}
+
+impl Default for Ball {
+    fn default() -> Self {
+        Self {
+            colour: Colour(255, 255, 255),
+            speed: 15.0,
+            trail: 12,
+            start: (2.0, 1.0),
+            heading: (1.0, 1.0),
+            position: None,
+            velocity: (0.0, 0.0),
+            points: VecDeque::new(),
+        }
+    }
+}
+
+impl EffectState for Ball {
+    fn advance(&mut self, dt: Duration, layout: &KeyboardLayout) {
+        let (w, h) = layout.extent();
+        let mut pos = match self.position {
+            Some(pos) => pos,
+            None => {
+                let len = (self.heading.0.powi(2) + self.heading.1.powi(2))
+                    .sqrt()
+                    .max(f32::EPSILON);
+                self.velocity = (self.heading.0 / len, self.heading.1 / len);
+                self.points.push_back(self.start);
+                self.start
+            }
+        };
+
+        let step = self.speed * dt.as_secs_f32();
+        pos.0 += self.velocity.0 * step;
+        pos.1 += self.velocity.1 * step;
+        // Reflect off the edges of the board
+        if pos.0 < 0.0 || pos.0 > w {
+            self.velocity.0 = -self.velocity.0;
+            pos.0 = pos.0.clamp(0.0, w);
+        }
+        if pos.1 < 0.0 || pos.1 > h {
+            self.velocity.1 = -self.velocity.1;
+            pos.1 = pos.1.clamp(0.0, h);
+        }
+        self.position = Some(pos);
+
+        if let Some(last) = self.points.back() {
+            if ((pos.0 - last.0).powi(2) + (pos.1 - last.1).powi(2)).sqrt() >= TRAIL_SPACING {
+                self.points.push_back(pos);
+            }
+        }
+        while self.points.len() > self.trail.max(1) {
+            self.points.pop_front();
+        }
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        let len = self.points.len() as f32;
+        // Oldest points first so the head is brightest
+        for (i, point) in self.points.iter().chain(self.position.iter()).enumerate() {
+            let intensity = ((i + 1) as f32 / (len + 1.0)).min(1.0);
+            for pos in &layout.keys {
+                let (x, y) = pos.centre();
+                let dist = ((x - point.0).powi(2) + (y - point.1).powi(2)).sqrt();
+                if dist < BALL_RADIUS {
+                    blend_max(layout, colours, pos.key, self.colour, intensity);
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.position = None;
+        self.points.clear();
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{blend_max, EffectState};
+use crate::{layouts::KeyboardLayout, Colour, Direction, KeyColourArray};
+
+/// A bar of light which sweeps across the keyboard leaving a fading trail
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Comet {
+    pub colour: Colour,
+    /// Keys per second
+    pub speed: f32,
+    pub direction: Direction,
+    /// Length of the trail in keys
+    pub trail: f32,
+    /// Distance the head has travelled
+    #[serde(skip)]
+    head: f32,
+}
+
+impl Default for Comet {
+    fn default() -> Self {
+        Self {
+            colour: Colour(255, 0, 0),
+            speed: 12.0,
+            direction: Direction::Right,
+            trail: 4.0,
+            head: 0.0,
+        }
+    }
+}
+
+impl Comet {
+    #[inline]
+    fn travel(&self, layout: &KeyboardLayout) -> f32 {
+        let (w, h) = layout.extent();
+        match self.direction {
+            Direction::Right | Direction::Left => w,
+            Direction::Up | Direction::Down => h,
+        }
+    }
+}
+
+impl EffectState for Comet {
+    fn advance(&mut self, dt: Duration, layout: &KeyboardLayout) {
+        // Allow the trail to fully leave the board before wrapping
+        let span = self.travel(layout) + self.trail;
+        self.head = (self.head + self.speed * dt.as_secs_f32()) % span.max(f32::EPSILON);
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        let travel = self.travel(layout);
+        for pos in &layout.keys {
+            let (x, y) = pos.centre();
+            let along = match self.direction {
+                Direction::Right => x,
+                Direction::Left => travel - x,
+                Direction::Down => y,
+                Direction::Up => travel - y,
+            };
+            let behind = self.head - along;
+            if behind >= 0.0 && behind < self.trail {
+                blend_max(
+                    layout,
+                    colours,
+                    pos.key,
+                    self.colour,
+                    1.0 - behind / self.trail,
+                );
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.head = 0.0;
+    }
+}
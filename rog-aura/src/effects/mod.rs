@@ -0,0 +1,193 @@
+//! Software per-key effects. Each effect is advanced by a time step and then
+//! painted in to a `KeyColourArray`, so that several effects can be layered
+//! on one frame.
+
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::Error, layouts::KeyboardLayout, Colour, Key, KeyColourArray};
+
+mod ball;
+pub use ball::*;
+
+mod comet;
+pub use comet::*;
+
+mod pulser;
+pub use pulser::*;
+
+mod reactive;
+pub use reactive::*;
+
+mod ripple;
+pub use ripple::*;
+
+/// Implemented by all software effects
+pub trait EffectState {
+    /// Advance the effect state by `dt`
+    fn advance(&mut self, dt: Duration, layout: &KeyboardLayout);
+
+    /// Paint the current state in to `colours`. Effects should blend with
+    /// `blend_max()` so that they can be layered.
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray);
+
+    /// Reset the effect to its starting state
+    fn reset(&mut self);
+
+    /// Called for each key press, effects which don't react to input can ignore this
+    fn key_pressed(&mut self, _key: Key) {}
+}
+
+/// All of the software effects, selectable by name in configs
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Effect {
+    Ball(Ball),
+    Comet(Comet),
+    Pulser(Pulser),
+    Reactive(Reactive),
+    Ripple(Ripple),
+}
+
+impl Effect {
+    /// The names accepted by `from_name()`
+    pub const NAMES: [&'static str; 5] = ["Ball", "Comet", "Pulser", "Reactive", "Ripple"];
+
+    /// Create an effect with default parameters from its name
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name.to_lowercase().as_str() {
+            "ball" => Ok(Effect::Ball(Ball::default())),
+            "comet" => Ok(Effect::Comet(Comet::default())),
+            "pulser" => Ok(Effect::Pulser(Pulser::default())),
+            "reactive" => Ok(Effect::Reactive(Reactive::default())),
+            "ripple" => Ok(Effect::Ripple(Ripple::default())),
+            _ => Err(Error::ParseEffect(name.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Ball(_) => "Ball",
+            Effect::Comet(_) => "Comet",
+            Effect::Pulser(_) => "Pulser",
+            Effect::Reactive(_) => "Reactive",
+            Effect::Ripple(_) => "Ripple",
+        }
+    }
+
+    #[inline]
+    fn inner(&self) -> &dyn EffectState {
+        match self {
+            Effect::Ball(e) => e,
+            Effect::Comet(e) => e,
+            Effect::Pulser(e) => e,
+            Effect::Reactive(e) => e,
+            Effect::Ripple(e) => e,
+        }
+    }
+
+    #[inline]
+    fn inner_mut(&mut self) -> &mut dyn EffectState {
+        match self {
+            Effect::Ball(e) => e,
+            Effect::Comet(e) => e,
+            Effect::Pulser(e) => e,
+            Effect::Reactive(e) => e,
+            Effect::Ripple(e) => e,
+        }
+    }
+}
+
+impl EffectState for Effect {
+    fn advance(&mut self, dt: Duration, layout: &KeyboardLayout) {
+        self.inner_mut().advance(dt, layout)
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        self.inner().paint(layout, colours)
+    }
+
+    fn reset(&mut self) {
+        self.inner_mut().reset()
+    }
+
+    fn key_pressed(&mut self, key: Key) {
+        self.inner_mut().key_pressed(key)
+    }
+}
+
+/// Blend `colour` scaled by `intensity` (0.0-1.0) in to the key, keeping the
+/// brightest value of each channel
+#[inline]
+pub fn blend_max(
+    layout: &KeyboardLayout,
+    colours: &mut KeyColourArray,
+    key: Key,
+    colour: Colour,
+    intensity: f32,
+) {
+    let colour = colour.scale(intensity);
+    if let Some((r, g, b)) = colours.key_in(layout, key) {
+        *r = (*r).max(colour.0);
+        *g = (*g).max(colour.1);
+        *b = (*b).max(colour.2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Effect, EffectState};
+    use crate::{layouts::KeyboardLayout, Key, KeyColourArray};
+
+    #[test]
+    fn all_effects_paint() {
+        let layout = KeyboardLayout::default();
+        for name in Effect::NAMES.iter() {
+            let mut effect = Effect::from_name(name).unwrap();
+            assert_eq!(effect.name(), *name);
+            let mut lit = false;
+            for _ in 0..50 {
+                effect.key_pressed(Key::Esc);
+                effect.advance(Duration::from_millis(40), &layout);
+                let mut colours = KeyColourArray::new();
+                effect.paint(&layout, &mut colours);
+                lit |= layout.keys.iter().any(|k| {
+                    let (r, g, b) = colours.key_in(&layout, k.key).unwrap();
+                    *r > 0 || *g > 0 || *b > 0
+                });
+            }
+            assert!(lit, "{} never lit a key", name);
+        }
+    }
+
+    #[test]
+    fn reactive_fades_out() {
+        let layout = KeyboardLayout::default();
+        let mut effect = Effect::from_name("Reactive").unwrap();
+        effect.key_pressed(Key::W);
+        effect.advance(Duration::from_millis(400), &layout);
+        let mut colours = KeyColourArray::new();
+        effect.paint(&layout, &mut colours);
+        let half = *colours.key_in(&layout, Key::W).unwrap().0;
+        assert!(half > 100 && half < 155);
+
+        effect.advance(Duration::from_millis(400), &layout);
+        let mut colours = KeyColourArray::new();
+        effect.paint(&layout, &mut colours);
+        assert_eq!(*colours.key_in(&layout, Key::W).unwrap().0, 0);
+    }
+
+    #[test]
+    fn evdev_space_lights_all_leds() {
+        assert_eq!(Key::from_evdev(57).len(), 4);
+        assert_eq!(Key::from_evdev(30), &[Key::A]);
+        assert!(Key::from_evdev(0).is_empty());
+    }
+
+    #[test]
+    fn unknown_name_fails() {
+        assert!(Effect::from_name("Sparkle").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use std::{f32::consts::PI, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{blend_max, EffectState};
+use crate::{layouts::KeyboardLayout, Colour, Key, KeyColourArray};
+
+/// Smoothly pulse a set of keys between off and `colour`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Pulser {
+    pub colour: Colour,
+    /// Pulses per second
+    pub speed: f32,
+    /// Keys to pulse, all keys if empty
+    #[serde(default)]
+    pub keys: Vec<Key>,
+    #[serde(skip)]
+    phase: f32,
+}
+
+impl Default for Pulser {
+    fn default() -> Self {
+        Self {
+            colour: Colour(255, 0, 0),
+            speed: 0.5,
+            keys: Vec::new(),
+            phase: 0.0,
+        }
+    }
+}
+
+impl EffectState for Pulser {
+    fn advance(&mut self, dt: Duration, _layout: &KeyboardLayout) {
+        self.phase = (self.phase + self.speed * dt.as_secs_f32()).fract();
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        let intensity = 0.5 - 0.5 * (self.phase * 2.0 * PI).cos();
+        if self.keys.is_empty() {
+            for pos in &layout.keys {
+                blend_max(layout, colours, pos.key, self.colour, intensity);
+            }
+        } else {
+            for key in &self.keys {
+                blend_max(layout, colours, *key, self.colour, intensity);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+}
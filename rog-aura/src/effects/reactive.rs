@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{blend_max, EffectState};
+use crate::{layouts::KeyboardLayout, Colour, Key, KeyClass, KeyColourArray};
+
+/// How a key press is drawn
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum ReactStyle {
+    /// Only the pressed key lights, then fades out
+    Fade,
+    /// A ring spreads out from the pressed key
+    Ripple { speed: f32, width: f32 },
+}
+
+/// Override the colour used for a class of keys
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ClassColour {
+    pub class: KeyClass,
+    pub colour: Colour,
+}
+
+/// Lights keys in response to key presses. Presses must be fed in with
+/// `EffectState::key_pressed()`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Reactive {
+    pub style: ReactStyle,
+    /// Colour for any key class not in `class_colours`
+    pub colour: Colour,
+    #[serde(default)]
+    pub class_colours: Vec<ClassColour>,
+    /// Time for a press to fade out completely
+    pub fade: Duration,
+    /// Live presses and their age in seconds
+    #[serde(skip)]
+    presses: Vec<(Key, f32)>,
+}
+
+impl Default for Reactive {
+    fn default() -> Self {
+        Self {
+            style: ReactStyle::Fade,
+            colour: Colour(255, 255, 255),
+            class_colours: Vec::new(),
+            fade: Duration::from_millis(800),
+            presses: Vec::new(),
+        }
+    }
+}
+
+impl Reactive {
+    /// The colour a key press is drawn with
+    pub fn colour_for(&self, key: Key) -> Colour {
+        let class = key.class();
+        self.class_colours
+            .iter()
+            .find(|c| c.class == class)
+            .map_or(self.colour, |c| c.colour)
+    }
+}
+
+impl EffectState for Reactive {
+    fn advance(&mut self, dt: Duration, _layout: &KeyboardLayout) {
+        let dt = dt.as_secs_f32();
+        let fade = self.fade.as_secs_f32();
+        for (_, age) in self.presses.iter_mut() {
+            *age += dt;
+        }
+        self.presses.retain(|(_, age)| *age < fade);
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        let fade = self.fade.as_secs_f32().max(f32::EPSILON);
+        for (key, age) in &self.presses {
+            let colour = self.colour_for(*key);
+            let intensity = 1.0 - age / fade;
+            match self.style {
+                ReactStyle::Fade => blend_max(layout, colours, *key, colour, intensity),
+                ReactStyle::Ripple { speed, width } => {
+                    let origin = match layout.key_position(*key) {
+                        Some(pos) => pos.centre(),
+                        None => continue,
+                    };
+                    let radius = age * speed;
+                    let width = width.max(f32::EPSILON);
+                    for pos in &layout.keys {
+                        let (x, y) = pos.centre();
+                        let dist = ((x - origin.0).powi(2) + (y - origin.1).powi(2)).sqrt();
+                        let offset = (dist - radius).abs();
+                        if offset < width {
+                            blend_max(
+                                layout,
+                                colours,
+                                pos.key,
+                                colour,
+                                (1.0 - offset / width) * intensity,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.presses.clear();
+    }
+
+    fn key_pressed(&mut self, key: Key) {
+        // Restart the fade if the key is already lit
+        self.presses.retain(|(k, _)| *k != key);
+        self.presses.push((key, 0.0));
+    }
+}
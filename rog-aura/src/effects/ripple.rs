@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{blend_max, EffectState};
+use crate::{layouts::KeyboardLayout, Colour, Key, KeyColourArray};
+
+/// The slowest ring speed in keys a second, so every ring passes the edge
+const MIN_SPEED: f32 = 1.0;
+/// The shortest time between rings
+const MIN_INTERVAL: f32 = 0.05;
+/// The most rings spreading at once
+const MAX_RINGS: usize = 32;
+
+/// Rings of light which spread out from a key at a fixed interval
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ripple {
+    pub colour: Colour,
+    /// Keys per second
+    pub speed: f32,
+    /// Width of the ring in keys
+    pub width: f32,
+    /// The key each ripple starts from
+    pub origin: Key,
+    /// Time between each new ripple
+    pub interval: Duration,
+    /// Age of each live ripple in seconds
+    #[serde(skip)]
+    ages: Vec<f32>,
+    #[serde(skip)]
+    since_last: f32,
+}
+
+impl Default for Ripple {
+    fn default() -> Self {
+        Self {
+            colour: Colour(0, 128, 255),
+            speed: 10.0,
+            width: 1.5,
+            origin: Key::G,
+            interval: Duration::from_millis(1500),
+            ages: Vec::new(),
+            since_last: 0.0,
+        }
+    }
+}
+
+impl Ripple {
+    /// The speed limited to `MIN_SPEED`, as it can be set to anything in the
+    /// config
+    fn speed(&self) -> f32 {
+        self.speed.max(MIN_SPEED)
+    }
+}
+
+impl EffectState for Ripple {
+    fn advance(&mut self, dt: Duration, layout: &KeyboardLayout) {
+        let dt = dt.as_secs_f32();
+        for age in self.ages.iter_mut() {
+            *age += dt;
+        }
+        // Drop rings once they have passed the furthest edge
+        let (w, h) = layout.extent();
+        let max_radius = (w * w + h * h).sqrt() + self.width;
+        let speed = self.speed();
+        self.ages.retain(|age| age * speed < max_radius);
+
+        self.since_last += dt;
+        let interval = self.interval.as_secs_f32().max(MIN_INTERVAL);
+        if self.ages.is_empty() || (self.since_last >= interval && self.ages.len() < MAX_RINGS) {
+            self.ages.push(0.0);
+            self.since_last = 0.0;
+        }
+    }
+
+    fn paint(&self, layout: &KeyboardLayout, colours: &mut KeyColourArray) {
+        let origin = match layout.key_position(self.origin) {
+            Some(pos) => pos.centre(),
+            None => return,
+        };
+        let (w, h) = layout.extent();
+        let max_radius = (w * w + h * h).sqrt();
+        for age in &self.ages {
+            let radius = age * self.speed();
+            // Rings dim as they spread out
+            let fade = 1.0 - (radius / max_radius).min(1.0);
+            for pos in &layout.keys {
+                let (x, y) = pos.centre();
+                let dist = ((x - origin.0).powi(2) + (y - origin.1).powi(2)).sqrt();
+                let offset = (dist - radius).abs();
+                if offset < self.width {
+                    blend_max(
+                        layout,
+                        colours,
+                        pos.key,
+                        self.colour,
+                        (1.0 - offset / self.width) * fade,
+                    );
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.ages.clear();
+        self.since_last = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Ripple, MAX_RINGS};
+    use crate::{effects::EffectState, layouts::KeyboardLayout};
+
+    #[test]
+    fn rings_are_limited() {
+        let layout = KeyboardLayout::default();
+        for speed in [0.0, -5.0, f32::NAN, 10.0].iter() {
+            let mut ripple = Ripple {
+                speed: *speed,
+                interval: Duration::from_secs(0),
+                ..Default::default()
+            };
+            for _ in 0..1000 {
+                ripple.advance(Duration::from_millis(16), &layout);
+                assert!(ripple.ages.len() <= MAX_RINGS);
+            }
+        }
+
+        // Slow rings still pass the edge and are dropped
+        let mut ripple = Ripple {
+            speed: 0.0,
+            ..Default::default()
+        };
+        for _ in 0..1000 {
+            ripple.advance(Duration::from_millis(100), &layout);
+        }
+        assert!(ripple.ages.len() < MAX_RINGS);
+    }
+}
@@ -6,8 +6,16 @@ pub enum Error {
     ParseColour,
     ParseSpeed,
     ParseDirection,
+    ParseZone,
+    ParsePower(String),
     ParseBrightness,
     ParseAnime,
+    ParseLayout(String),
+    ParseSequence(String),
+    InsertIndex(usize, usize),
+    ParseEffect(String),
+    Encode(String),
+    Io(String, std::io::Error),
 }
 
 impl fmt::Display for Error {
@@ -17,8 +25,18 @@ impl fmt::Display for Error {
             Error::ParseColour => write!(f, "Could not parse colour"),
             Error::ParseSpeed => write!(f, "Could not parse speed"),
             Error::ParseDirection => write!(f, "Could not parse direction"),
+            Error::ParseZone => write!(f, "Could not parse zone"),
+            Error::ParsePower(deets) => write!(f, "Could not parse power zone or state: {}", deets),
             Error::ParseBrightness => write!(f, "Could not parse brightness"),
             Error::ParseAnime => write!(f, "Could not parse anime"),
+            Error::ParseLayout(deets) => write!(f, "Could not parse layout: {}", deets),
+            Error::ParseSequence(deets) => write!(f, "Could not parse sequence: {}", deets),
+            Error::InsertIndex(index, len) => {
+                write!(f, "Can not insert at {}, there are {} actions", index, len)
+            }
+            Error::ParseEffect(name) => write!(f, "Unknown effect: {}", name),
+            Error::Encode(deets) => write!(f, "Could not encode image: {}", deets),
+            Error::Io(path, error) => write!(f, "{}: {}", path, error),
         }
     }
 }
@@ -0,0 +1,226 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::Key;
+
+/// Broad groups of keys, used to colour keys by type
+#[derive(Debug, PartialEq, Copy, Clone, Deserialize, Serialize)]
+pub enum KeyClass {
+    Letter,
+    Number,
+    Function,
+    Modifier,
+    Navigation,
+    Numpad,
+    Media,
+    Other,
+}
+
+impl Key {
+    /// Map a Linux evdev keycode (`KEY_*` in `linux/input-event-codes.h`) to the
+    /// LEDs it lights. Some keys such as space and backspace have several LEDs.
+    pub fn from_evdev(code: u16) -> &'static [Key] {
+        match code {
+            1 => &[Key::Esc],
+            2 => &[Key::N1],
+            3 => &[Key::N2],
+            4 => &[Key::N3],
+            5 => &[Key::N4],
+            6 => &[Key::N5],
+            7 => &[Key::N6],
+            8 => &[Key::N7],
+            9 => &[Key::N8],
+            10 => &[Key::N9],
+            11 => &[Key::N0],
+            12 => &[Key::Hyphen],
+            13 => &[Key::Equals],
+            14 => &[Key::BkSpc1, Key::BkSpc2, Key::BkSpc3],
+            15 => &[Key::Tab],
+            16 => &[Key::Q],
+            17 => &[Key::W],
+            18 => &[Key::E],
+            19 => &[Key::R],
+            20 => &[Key::T],
+            21 => &[Key::Y],
+            22 => &[Key::U],
+            23 => &[Key::I],
+            24 => &[Key::O],
+            25 => &[Key::P],
+            26 => &[Key::LBracket],
+            27 => &[Key::RBracket],
+            28 => &[Key::Ret1, Key::Ret2, Key::Ret3],
+            29 => &[Key::LCtrl],
+            30 => &[Key::A],
+            31 => &[Key::S],
+            32 => &[Key::D],
+            33 => &[Key::F],
+            34 => &[Key::G],
+            35 => &[Key::H],
+            36 => &[Key::J],
+            37 => &[Key::K],
+            38 => &[Key::L],
+            39 => &[Key::SemiColon],
+            40 => &[Key::Quote],
+            41 => &[Key::Tilde],
+            42 => &[Key::LShift],
+            43 => &[Key::BackSlash],
+            44 => &[Key::Z],
+            45 => &[Key::X],
+            46 => &[Key::C],
+            47 => &[Key::V],
+            48 => &[Key::B],
+            49 => &[Key::N],
+            50 => &[Key::M],
+            51 => &[Key::Comma],
+            52 => &[Key::Period],
+            53 => &[Key::FwdSlash],
+            54 => &[Key::Rshift1, Key::Rshift2, Key::Rshift3],
+            55 => &[Key::NumStar],
+            56 => &[Key::LAlt],
+            57 => &[Key::Space1, Key::Space2, Key::Space3, Key::Space4],
+            58 => &[Key::Caps],
+            59 => &[Key::F1],
+            60 => &[Key::F2],
+            61 => &[Key::F3],
+            62 => &[Key::F4],
+            63 => &[Key::F5],
+            64 => &[Key::F6],
+            65 => &[Key::F7],
+            66 => &[Key::F8],
+            67 => &[Key::F9],
+            68 => &[Key::F10],
+            69 => &[Key::NumLock],
+            71 => &[Key::Num7],
+            72 => &[Key::Num8],
+            73 => &[Key::Num9],
+            74 => &[Key::NumMinus],
+            75 => &[Key::Num4],
+            76 => &[Key::Num5],
+            77 => &[Key::Num6],
+            78 => &[Key::NumPlus],
+            79 => &[Key::Num1],
+            80 => &[Key::Num2],
+            81 => &[Key::Num3],
+            82 => &[Key::Num0],
+            83 => &[Key::NumPeriod],
+            86 => &[Key::IsoBackSlash],
+            87 => &[Key::F11],
+            88 => &[Key::F12],
+            96 => &[Key::NumEnter],
+            97 => &[Key::RCtrl],
+            98 => &[Key::NumSlash],
+            99 => &[Key::PrtSc],
+            100 => &[Key::RAlt],
+            102 => &[Key::Home],
+            103 => &[Key::Up],
+            104 => &[Key::PgUp],
+            105 => &[Key::Left],
+            106 => &[Key::Right],
+            107 => &[Key::End],
+            108 => &[Key::Down],
+            109 => &[Key::PgDn],
+            110 => &[Key::Ins],
+            111 => &[Key::Del],
+            114 => &[Key::VolDown],
+            115 => &[Key::VolUp],
+            119 => &[Key::Pause],
+            125 => &[Key::Meta],
+            148 => &[Key::Rog],
+            248 => &[Key::MicMute],
+            _ => &[],
+        }
+    }
+
+    pub fn class(&self) -> KeyClass {
+        match self {
+            Key::Q
+            | Key::W
+            | Key::E
+            | Key::R
+            | Key::T
+            | Key::Y
+            | Key::U
+            | Key::I
+            | Key::O
+            | Key::P
+            | Key::A
+            | Key::S
+            | Key::D
+            | Key::F
+            | Key::G
+            | Key::H
+            | Key::J
+            | Key::K
+            | Key::L
+            | Key::Z
+            | Key::X
+            | Key::C
+            | Key::V
+            | Key::B
+            | Key::N
+            | Key::M => KeyClass::Letter,
+            Key::N1
+            | Key::N2
+            | Key::N3
+            | Key::N4
+            | Key::N5
+            | Key::N6
+            | Key::N7
+            | Key::N8
+            | Key::N9
+            | Key::N0 => KeyClass::Number,
+            Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+            | Key::F12 => KeyClass::Function,
+            Key::LShift
+            | Key::Rshift1
+            | Key::Rshift2
+            | Key::Rshift3
+            | Key::LCtrl
+            | Key::RCtrl
+            | Key::LAlt
+            | Key::RAlt
+            | Key::Meta
+            | Key::LFn
+            | Key::RFn
+            | Key::Caps => KeyClass::Modifier,
+            Key::Up
+            | Key::Down
+            | Key::Left
+            | Key::Right
+            | Key::Home
+            | Key::End
+            | Key::PgUp
+            | Key::PgDn
+            | Key::Ins
+            | Key::Del => KeyClass::Navigation,
+            Key::NumLock
+            | Key::NumSlash
+            | Key::NumStar
+            | Key::NumMinus
+            | Key::NumPlus
+            | Key::NumEnter
+            | Key::NumPeriod
+            | Key::Num0
+            | Key::Num1
+            | Key::Num2
+            | Key::Num3
+            | Key::Num4
+            | Key::Num5
+            | Key::Num6
+            | Key::Num7
+            | Key::Num8
+            | Key::Num9 => KeyClass::Numpad,
+            Key::VolUp | Key::VolDown | Key::MicMute | Key::Rog => KeyClass::Media,
+            _ => KeyClass::Other,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::{fs::OpenOptions, io::Read, path::Path};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::Error, Key, PER_KEY_PACKET_COUNT, PER_KEY_PACKET_LEN};
+
+/// Where shipped and user added keyboard layouts are located
+pub const LAYOUT_DIR: &str = "/usr/share/asusd/layouts";
+/// The layout used if a laptop has not named one
+pub const DEFAULT_LAYOUT: &str = "gx502";
+
+/// The physical arrangement standard of the keyboard
+#[derive(Debug, PartialEq, Copy, Clone, Deserialize, Serialize)]
+pub enum LayoutType {
+    Ansi,
+    Iso,
+    Jis,
+}
+
+/// The location of a single key LED, both in the per-key packets and on the
+/// physical keyboard
+#[derive(Debug, PartialEq, Copy, Clone, Deserialize, Serialize)]
+pub struct KeyPosition {
+    pub key: Key,
+    /// Row and column of the red byte in the packets of a `KeyColourArray`
+    pub packet: (usize, usize),
+    /// Physical x, y of the top left of the key, in key units
+    pub position: (f32, f32),
+    /// Physical width of the key, in key units
+    #[serde(default = "default_width")]
+    pub width: f32,
+}
+
+impl KeyPosition {
+    /// Physical centre of the key, in key units
+    #[inline]
+    pub fn centre(&self) -> (f32, f32) {
+        (self.position.0 + self.width / 2.0, self.position.1 + 0.5)
+    }
+}
+
+fn default_width() -> f32 {
+    1.0
+}
+
+fn default_layout_type() -> LayoutType {
+    LayoutType::Ansi
+}
+
+/// A description of a keyboard which maps each key to its packet location
+/// and physical position. These are loaded from TOML files in `LAYOUT_DIR`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeyboardLayout {
+    pub name: String,
+    #[serde(default = "default_layout_type")]
+    pub layout_type: LayoutType,
+    pub keys: Vec<KeyPosition>,
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_LAYOUT.to_string(),
+            layout_type: LayoutType::Ansi,
+            keys: GX502_KEYS
+                .iter()
+                .map(|(key, packet, position, width)| KeyPosition {
+                    key: *key,
+                    packet: *packet,
+                    position: *position,
+                    width: *width,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl KeyboardLayout {
+    /// Load a layout by name from `LAYOUT_DIR`, e.g, `gx502` will load
+    /// `/usr/share/asusd/layouts/gx502.toml`
+    pub fn load(name: &str) -> Result<Self, Error> {
+        Self::from_file(Path::new(LAYOUT_DIR).join(format!("{}.toml", name)))
+    }
+
+    /// Load and validate a layout file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| Error::Io(path.to_string_lossy().to_string(), e))?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)
+            .map_err(|e| Error::Io(path.to_string_lossy().to_string(), e))?;
+        Self::from_toml(&buf)
+    }
+
+    /// Parse and validate a layout from a TOML string
+    pub fn from_toml(input: &str) -> Result<Self, Error> {
+        let layout: Self = toml::from_str(input).map_err(|e| Error::ParseLayout(e.to_string()))?;
+        for pos in &layout.keys {
+            let (row, col) = pos.packet;
+            if row >= PER_KEY_PACKET_COUNT || col + 3 > PER_KEY_PACKET_LEN {
+                return Err(Error::ParseLayout(format!(
+                    "{:?} packet position {:?} is out of range",
+                    pos.key, pos.packet
+                )));
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Get the position data for a key, if the layout contains it
+    #[inline]
+    pub fn key_position(&self, key: Key) -> Option<&KeyPosition> {
+        self.keys.iter().find(|k| k.key == key)
+    }
+
+    /// The physical width and height of the keyboard, in key units
+    pub fn extent(&self) -> (f32, f32) {
+        self.keys.iter().fold((0.0, 0.0), |(w, h), k| {
+            (w.max(k.position.0 + k.width), h.max(k.position.1 + 1.0))
+        })
+    }
+
+    #[inline]
+    pub fn has_numpad(&self) -> bool {
+        self.key_position(Key::NumLock).is_some()
+    }
+
+    /// The keys grouped by physical row, each row ordered left to right
+    pub fn rows(&self) -> Vec<Vec<Key>> {
+        let mut rows: Vec<(f32, Vec<&KeyPosition>)> = Vec::new();
+        for pos in &self.keys {
+            if let Some(row) = rows.iter_mut().find(|(y, _)| *y == pos.position.1) {
+                row.1.push(pos);
+            } else {
+                rows.push((pos.position.1, vec![pos]));
+            }
+        }
+        rows.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        rows.into_iter()
+            .map(|(_, mut row)| {
+                row.sort_by(|a, b| {
+                    a.position
+                        .0
+                        .partial_cmp(&b.position.0)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                row.iter().map(|p| p.key).collect()
+            })
+            .collect()
+    }
+}
+
+/// Key, packet row/col, physical x/y, width
+pub(crate) type KeyEntry = (Key, (usize, usize), (f32, f32), f32);
+
+/// Builtin copy of `data/layouts/gx502.toml` so that a layout is always available
+#[rustfmt::skip]
+pub(crate) static GX502_KEYS: [KeyEntry; 97] = [
+    (Key::VolDown, (0, 15), (2.0, 0.0), 1.0),
+    (Key::VolUp, (0, 18), (3.0, 0.0), 1.0),
+    (Key::MicMute, (0, 21), (4.0, 0.0), 1.0),
+    (Key::Rog, (0, 24), (5.0, 0.0), 1.0),
+    (Key::Esc, (1, 24), (0.0, 1.0), 1.0),
+    (Key::F1, (1, 30), (2.0, 1.0), 1.0),
+    (Key::F2, (1, 33), (3.0, 1.0), 1.0),
+    (Key::F3, (1, 36), (4.0, 1.0), 1.0),
+    (Key::F4, (1, 39), (5.0, 1.0), 1.0),
+    (Key::F5, (1, 45), (7.0, 1.0), 1.0),
+    (Key::F6, (1, 48), (8.0, 1.0), 1.0),
+    (Key::F7, (1, 51), (9.0, 1.0), 1.0),
+    (Key::F8, (1, 54), (10.0, 1.0), 1.0),
+    (Key::F9, (2, 12), (11.0, 1.0), 2.0),
+    (Key::F10, (2, 15), (13.0, 1.0), 1.0),
+    (Key::F11, (2, 18), (14.0, 1.0), 1.0),
+    (Key::F12, (2, 21), (15.0, 1.0), 1.0),
+    (Key::Del, (2, 24), (16.0, 1.0), 1.0),
+    (Key::Tilde, (2, 39), (0.0, 2.0), 1.0),
+    (Key::N1, (2, 42), (1.0, 2.0), 1.0),
+    (Key::N2, (2, 45), (2.0, 2.0), 1.0),
+    (Key::N3, (2, 48), (3.0, 2.0), 1.0),
+    (Key::N4, (2, 51), (4.0, 2.0), 1.0),
+    (Key::N5, (2, 54), (5.0, 2.0), 1.0),
+    (Key::N6, (3, 9), (6.0, 2.0), 1.0),
+    (Key::N7, (3, 12), (7.0, 2.0), 1.0),
+    (Key::N8, (3, 15), (8.0, 2.0), 1.0),
+    (Key::N9, (3, 18), (9.0, 2.0), 1.0),
+    (Key::N0, (3, 21), (10.0, 2.0), 1.0),
+    (Key::Hyphen, (3, 24), (11.0, 2.0), 1.0),
+    (Key::Equals, (3, 27), (12.0, 2.0), 1.0),
+    (Key::BkSpc1, (3, 30), (13.0, 2.0), 1.0),
+    (Key::BkSpc2, (3, 33), (14.0, 2.0), 1.0),
+    (Key::BkSpc3, (3, 36), (15.0, 2.0), 1.0),
+    (Key::Home, (3, 39), (16.0, 2.0), 1.0),
+    (Key::Tab, (3, 54), (0.0, 3.0), 1.0),
+    (Key::Q, (4, 9), (1.0, 3.0), 1.0),
+    (Key::W, (4, 12), (2.0, 3.0), 1.0),
+    (Key::E, (4, 15), (3.0, 3.0), 1.0),
+    (Key::R, (4, 18), (4.0, 3.0), 1.0),
+    (Key::T, (4, 21), (5.0, 3.0), 1.0),
+    (Key::Y, (4, 24), (6.0, 3.0), 1.0),
+    (Key::U, (4, 27), (7.0, 3.0), 1.0),
+    (Key::I, (4, 30), (8.0, 3.0), 1.0),
+    (Key::O, (4, 33), (9.0, 3.0), 1.0),
+    (Key::P, (4, 36), (10.0, 3.0), 1.0),
+    (Key::LBracket, (4, 39), (11.0, 3.0), 1.0),
+    (Key::RBracket, (4, 42), (12.0, 3.0), 1.0),
+    (Key::BackSlash, (4, 45), (13.0, 3.0), 3.0),
+    (Key::PgUp, (4, 54), (16.0, 3.0), 1.0),
+    (Key::Caps, (5, 21), (0.0, 4.0), 1.0),
+    (Key::A, (5, 24), (1.0, 4.0), 1.0),
+    (Key::S, (5, 27), (2.0, 4.0), 1.0),
+    (Key::D, (5, 30), (3.0, 4.0), 1.0),
+    (Key::F, (5, 33), (4.0, 4.0), 1.0),
+    (Key::G, (5, 36), (5.0, 4.0), 1.0),
+    (Key::H, (5, 39), (6.0, 4.0), 1.0),
+    (Key::J, (5, 42), (7.0, 4.0), 1.0),
+    (Key::K, (5, 45), (8.0, 4.0), 1.0),
+    (Key::L, (5, 48), (9.0, 4.0), 1.0),
+    (Key::SemiColon, (5, 51), (10.0, 4.0), 1.0),
+    (Key::Quote, (5, 54), (11.0, 4.0), 2.0),
+    (Key::Ret1, (6, 12), (13.0, 4.0), 1.0),
+    (Key::Ret2, (6, 15), (14.0, 4.0), 1.0),
+    (Key::Ret3, (6, 18), (15.0, 4.0), 1.0),
+    (Key::PgDn, (6, 21), (16.0, 4.0), 1.0),
+    (Key::LShift, (6, 36), (0.0, 5.0), 2.0),
+    (Key::Z, (6, 42), (2.0, 5.0), 1.0),
+    (Key::X, (6, 45), (3.0, 5.0), 1.0),
+    (Key::C, (6, 48), (4.0, 5.0), 1.0),
+    (Key::V, (6, 51), (5.0, 5.0), 1.0),
+    (Key::B, (6, 54), (6.0, 5.0), 1.0),
+    (Key::N, (7, 9), (7.0, 5.0), 1.0),
+    (Key::M, (7, 12), (8.0, 5.0), 1.0),
+    (Key::Comma, (7, 15), (9.0, 5.0), 1.0),
+    (Key::Period, (7, 18), (10.0, 5.0), 1.0),
+    (Key::FwdSlash, (7, 21), (11.0, 5.0), 2.0),
+    (Key::Rshift1, (7, 27), (13.0, 5.0), 1.0),
+    (Key::Rshift2, (7, 30), (14.0, 5.0), 1.0),
+    (Key::Rshift3, (7, 33), (15.0, 5.0), 1.0),
+    (Key::End, (7, 36), (16.0, 5.0), 1.0),
+    (Key::LCtrl, (7, 51), (0.0, 6.0), 1.0),
+    (Key::LFn, (7, 54), (1.0, 6.0), 1.0),
+    (Key::Meta, (8, 9), (2.0, 6.0), 1.0),
+    (Key::LAlt, (8, 12), (3.0, 6.0), 1.0),
+    (Key::Space1, (8, 15), (4.0, 6.0), 1.0),
+    (Key::Space2, (8, 18), (5.0, 6.0), 1.0),
+    (Key::Space3, (8, 21), (6.0, 6.0), 1.0),
+    (Key::Space4, (8, 24), (7.0, 6.0), 2.0),
+    (Key::RAlt, (8, 30), (9.0, 6.0), 1.0),
+    (Key::PrtSc, (8, 33), (10.0, 6.0), 1.0),
+    (Key::RCtrl, (8, 36), (11.0, 6.0), 2.0),
+    (Key::Up, (8, 42), (14.0, 6.0), 1.0),
+    (Key::RFn, (8, 51), (16.0, 6.0), 1.0),
+    (Key::Left, (9, 54), (13.0, 6.0), 1.0),
+    (Key::Down, (10, 9), (14.0, 7.0), 1.0),
+    (Key::Right, (10, 12), (15.0, 6.0), 1.0),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyboardLayout, LayoutType};
+    use crate::Key;
+
+    #[test]
+    fn shipped_layout_matches_builtin() {
+        let file = KeyboardLayout::from_toml(include_str!("../data/layouts/gx502.toml")).unwrap();
+        let builtin = KeyboardLayout::default();
+        assert_eq!(file.name, builtin.name);
+        assert_eq!(file.keys, builtin.keys);
+    }
+
+    #[test]
+    fn out_of_range_packet_is_rejected() {
+        let input = r#"
+            name = "bad"
+            [[keys]]
+            key = "Esc"
+            packet = [1, 62]
+            position = [0.0, 0.0]
+        "#;
+        assert!(KeyboardLayout::from_toml(input).is_err());
+    }
+
+    #[test]
+    fn numpad_and_iso_keys() {
+        let input = r#"
+            name = "numpad"
+            layout_type = "Iso"
+            [[keys]]
+            key = "IsoBackSlash"
+            packet = [6, 39]
+            position = [1.0, 5.0]
+            [[keys]]
+            key = "NumLock"
+            packet = [10, 15]
+            position = [18.0, 2.0]
+            [[keys]]
+            key = "NumEnter"
+            packet = [10, 18]
+            position = [21.0, 5.0]
+        "#;
+        let layout = KeyboardLayout::from_toml(input).unwrap();
+        assert_eq!(layout.layout_type, LayoutType::Iso);
+        assert!(layout.has_numpad());
+        assert!(!KeyboardLayout::default().has_numpad());
+        assert_eq!(layout.extent(), (22.0, 6.0));
+    }
+
+    #[test]
+    fn rows_are_ordered() {
+        let layout = KeyboardLayout::default();
+        let rows = layout.rows();
+        assert_eq!(rows[0][0], Key::VolDown);
+        assert_eq!(rows[1][0], Key::Esc);
+        assert_eq!(rows[3][0], Key::Tab);
+    }
+}
@@ -1,14 +1,30 @@
-/// A container of images/grids/gifs/pauses which can be iterated over to generate
+/// A timeline of per-key keyframes and pauses which can be played back to generate
 /// cool effects
 mod sequencer;
 pub use sequencer::*;
 
+mod colour;
+pub use colour::*;
+
 mod builtin_modes;
 pub use builtin_modes::*;
 
+mod power;
+pub use power::*;
+
 mod per_key_rgb;
 pub use per_key_rgb::*;
 
+mod keycodes;
+pub use keycodes::*;
+
+pub mod layouts;
+
+pub mod effects;
+
+#[cfg(feature = "preview")]
+pub mod preview;
+
 pub mod usb;
 
 pub mod error;
@@ -1,3 +1,15 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    layouts::{KeyboardLayout, GX502_KEYS},
+    Colour,
+};
+
+/// The number of HID packets required to set every key colour
+pub const PER_KEY_PACKET_COUNT: usize = 11;
+/// The length of each per-key HID packet
+pub const PER_KEY_PACKET_LEN: usize = 64;
+
 /// A `KeyColourArray` contains all data to change the full set of keyboard
 /// key colours individually.
 ///
@@ -6,7 +18,7 @@
 /// necessarily all on the same row of the keyboard, with some splitting between
 /// two rows.
 #[derive(Clone)]
-pub struct KeyColourArray([[u8; 64]; 11]);
+pub struct KeyColourArray([[u8; PER_KEY_PACKET_LEN]; PER_KEY_PACKET_COUNT]);
 impl Default for KeyColourArray {
     fn default() -> Self {
         Self::new()
@@ -14,7 +26,7 @@ impl Default for KeyColourArray {
 }
 impl KeyColourArray {
     pub fn new() -> Self {
-        let mut set = [[0u8; 64]; 11];
+        let mut set = [[0u8; PER_KEY_PACKET_LEN]; PER_KEY_PACKET_COUNT];
         for (count, row) in set.iter_mut().enumerate() {
             row[0] = 0x5d; // Report ID
             row[1] = 0xbc; // Mode = custom??, 0xb3 is builtin
@@ -36,8 +48,8 @@ impl KeyColourArray {
     /// Initialise and clear the keyboard for custom effects, this must be done for
     /// every time mode switches from builtin to custom
     #[inline]
-    pub const fn get_init_msg() -> [u8; 64] {
-        let mut init = [0u8; 64];
+    pub const fn get_init_msg() -> [u8; PER_KEY_PACKET_LEN] {
+        let mut init = [0u8; PER_KEY_PACKET_LEN];
         init[0] = 0x5d; // Report ID
         init[1] = 0xbc; // Mode = custom??, 0xb3 is builtin
         init
@@ -52,137 +64,75 @@ impl KeyColourArray {
         }
     }
 
+    /// Set a key colour using the packet position given by a `KeyboardLayout`
+    #[inline]
+    pub fn set_in(&mut self, layout: &KeyboardLayout, key: Key, r: u8, g: u8, b: u8) {
+        if let Some((rr, gg, bb)) = self.key_in(layout, key) {
+            *rr = r;
+            *gg = g;
+            *bb = b;
+        }
+    }
+
     /// Indexes in to `KeyColourArray` at the correct row and column
-    /// to set a series of three bytes to the chosen R,G,B values
+    /// to set a series of three bytes to the chosen R,G,B values.
+    ///
+    /// This uses the builtin GX502 layout, use `key_in()` for other keyboards.
     pub fn key(&mut self, key: Key) -> Option<(&mut u8, &mut u8, &mut u8)> {
-        // Tuples are indexes in to array
-        let (row, col) = match key {
-            Key::VolDown => (0, 15),
-            Key::VolUp => (0, 18),
-            Key::MicMute => (0, 21),
-            Key::Rog => (0, 24),
-            //
-            Key::Esc => (1, 24),
-            Key::F1 => (1, 30),
-            Key::F2 => (1, 33),
-            Key::F3 => (1, 36),
-            Key::F4 => (1, 39),
-            Key::F5 => (1, 45),
-            Key::F6 => (1, 48),
-            Key::F7 => (1, 51),
-            Key::F8 => (1, 54),
-            //
-            Key::F9 => (2, 12),
-            Key::F10 => (2, 15),
-            Key::F11 => (2, 18),
-            Key::F12 => (2, 21),
-            Key::Del => (2, 24),
-            Key::Tilde => (2, 39),
-            Key::N1 => (2, 42),
-            Key::N2 => (2, 45),
-            Key::N3 => (2, 48),
-            Key::N4 => (2, 51),
-            Key::N5 => (2, 54),
-            //
-            Key::N6 => (3, 9),
-            Key::N7 => (3, 12),
-            Key::N8 => (3, 15),
-            Key::N9 => (3, 18),
-            Key::N0 => (3, 21),
-            Key::Hyphen => (3, 24),
-            Key::Equals => (3, 27),
-            Key::BkSpc1 => (3, 30),
-            Key::BkSpc2 => (3, 33),
-            Key::BkSpc3 => (3, 36),
-            Key::Home => (3, 39),
-            Key::Tab => (3, 54),
-            //
-            Key::Q => (4, 9),
-            Key::W => (4, 12),
-            Key::E => (4, 15),
-            Key::R => (4, 18),
-            Key::T => (4, 21),
-            Key::Y => (4, 24),
-            Key::U => (4, 27),
-            Key::I => (4, 30),
-            Key::O => (4, 33),
-            Key::P => (4, 36),
-            Key::LBracket => (4, 39),
-            Key::RBracket => (4, 42),
-            Key::BackSlash => (4, 45),
-            Key::PgUp => (4, 54),
-            //
-            Key::Caps => (5, 21),
-            Key::A => (5, 24),
-            Key::S => (5, 27),
-            Key::D => (5, 30),
-            Key::F => (5, 33),
-            Key::G => (5, 36),
-            Key::H => (5, 39),
-            Key::J => (5, 42),
-            Key::K => (5, 45),
-            Key::L => (5, 48),
-            Key::SemiColon => (5, 51),
-            Key::Quote => (5, 54),
-            //
-            Key::Ret1 => (6, 12),
-            Key::Ret2 => (6, 15),
-            Key::Ret3 => (6, 18),
-            Key::PgDn => (6, 21),
-            Key::LShift => (6, 36),
-            Key::Z => (6, 42),
-            Key::X => (6, 45),
-            Key::C => (6, 48),
-            Key::V => (6, 51),
-            Key::B => (6, 54),
-            //
-            Key::N => (7, 9),
-            Key::M => (7, 12),
-            Key::Comma => (7, 15),
-            Key::Period => (7, 18),
-            Key::FwdSlash => (7, 21),
-            Key::Rshift1 => (7, 27),
-            Key::Rshift2 => (7, 30),
-            Key::Rshift3 => (7, 33),
-            Key::End => (7, 36),
-            Key::LCtrl => (7, 51),
-            Key::LFn => (7, 54),
-            //
-            Key::Meta => (8, 9),
-            Key::LAlt => (8, 12),
-            Key::Space1 => (8, 15),
-            Key::Space2 => (8, 18),
-            Key::Space3 => (8, 21),
-            Key::Space4 => (8, 24),
-            Key::RAlt => (8, 30),
-            Key::PrtSc => (8, 33),
-            Key::RCtrl => (8, 36),
-            Key::Up => (8, 42),
-            Key::RFn => (8, 51),
-            //
-            Key::Left => (9, 54),
-            //
-            Key::Down => (10, 9),
-            Key::Right => (10, 12),
-            Key::None => return None,
-        };
-        // LOLOLOLOLOLOLOL! Look it's safe okay
-        unsafe {
-            Some((
-                &mut *(&mut self.0[row][col] as *mut u8),
-                &mut *(&mut self.0[row][col + 1] as *mut u8),
-                &mut *(&mut self.0[row][col + 2] as *mut u8),
-            ))
+        let (_, packet, _, _) = GX502_KEYS.iter().find(|(k, _, _, _)| *k == key)?;
+        self.rgb_at(*packet)
+    }
+
+    /// Indexes in to `KeyColourArray` at the row and column that `layout`
+    /// gives for the key
+    pub fn key_in(
+        &mut self,
+        layout: &KeyboardLayout,
+        key: Key,
+    ) -> Option<(&mut u8, &mut u8, &mut u8)> {
+        let packet = layout.key_position(key)?.packet;
+        self.rgb_at(packet)
+    }
+
+    /// The colour of a key at the position `layout` gives for it
+    pub fn colour_in(&self, layout: &KeyboardLayout, key: Key) -> Option<Colour> {
+        let (row, col) = layout.key_position(key)?.packet;
+        match self.0.get(row)?.get(col..col + 3)? {
+            [r, g, b] => Some(Colour(*r, *g, *b)),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn rgb_at(&mut self, (row, col): (usize, usize)) -> Option<(&mut u8, &mut u8, &mut u8)> {
+        match self.0.get_mut(row)?.get_mut(col..col + 3)? {
+            [r, g, b] => Some((r, g, b)),
+            _ => None,
         }
     }
 
     #[inline]
-    pub fn get(&self) -> &[[u8; 64]; 11] {
+    pub fn get(&self) -> &[[u8; PER_KEY_PACKET_LEN]; PER_KEY_PACKET_COUNT] {
         &self.0
     }
+
+    /// Copy the packets out to a form which can be sent over dbus
+    #[inline]
+    pub fn to_vecs(&self) -> Vec<Vec<u8>> {
+        self.0.iter().map(|row| row.to_vec()).collect()
+    }
+
+    /// Check that a block of packets is the correct shape to be written
+    /// to the keyboard as a per-key effect
+    pub fn is_valid_block(block: &[Vec<u8>]) -> bool {
+        block.len() == PER_KEY_PACKET_COUNT
+            && block
+                .iter()
+                .all(|row| row.len() == PER_KEY_PACKET_LEN && row[0] == 0x5d && row[1] == 0xbc)
+    }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Deserialize, Serialize)]
 pub enum Key {
     VolUp,
     VolDown,
@@ -281,6 +231,29 @@ pub enum Key {
     Left,
     Right,
     RFn,
+    Ins,
+    Pause,
+    /// The extra key between left-shift and Z on ISO keyboards
+    IsoBackSlash,
+    /// The extra key left of return on ISO keyboards
+    IsoHash,
+    NumLock,
+    NumSlash,
+    NumStar,
+    NumMinus,
+    NumPlus,
+    NumEnter,
+    NumPeriod,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
     None,
 }
 
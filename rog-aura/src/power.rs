@@ -0,0 +1,195 @@
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+#[cfg(feature = "dbus")]
+use zvariant_derive::Type;
+
+use crate::{
+    error::Error,
+    usb::{
+        aura_power_zone_bytes, LED_AWAKE_OFF_SLEEP_OFF, LED_AWAKE_OFF_SLEEP_ON,
+        LED_AWAKE_ON_SLEEP_OFF, LED_AWAKE_ON_SLEEP_ON,
+    },
+    LED_MSG_LEN,
+};
+
+/// A part of the laptop with LEDs that can be powered separately
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PowerZone {
+    Keyboard,
+    Logo,
+    Lightbar,
+    Lid,
+    RearGlow,
+}
+
+impl PowerZone {
+    pub const ALL: [PowerZone; 5] = [
+        PowerZone::Keyboard,
+        PowerZone::Logo,
+        PowerZone::Lightbar,
+        PowerZone::Lid,
+        PowerZone::RearGlow,
+    ];
+}
+
+impl FromStr for PowerZone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "keyboard" => Ok(PowerZone::Keyboard),
+            "logo" => Ok(PowerZone::Logo),
+            "lightbar" => Ok(PowerZone::Lightbar),
+            "lid" => Ok(PowerZone::Lid),
+            "rear-glow" | "rearglow" => Ok(PowerZone::RearGlow),
+            _ => Err(Error::ParsePower(s)),
+        }
+    }
+}
+
+/// The power states of the laptop in which a zone can be lit
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PowerState {
+    Boot,
+    Awake,
+    Sleep,
+    Shutdown,
+}
+
+impl PowerState {
+    pub const ALL: [PowerState; 4] = [
+        PowerState::Boot,
+        PowerState::Awake,
+        PowerState::Sleep,
+        PowerState::Shutdown,
+    ];
+}
+
+impl FromStr for PowerState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "boot" => Ok(PowerState::Boot),
+            "awake" => Ok(PowerState::Awake),
+            "sleep" => Ok(PowerState::Sleep),
+            "shutdown" => Ok(PowerState::Shutdown),
+            _ => Err(Error::ParsePower(s)),
+        }
+    }
+}
+
+/// Which zones are lit in which power states. One bit per zone and state,
+/// with four bits per zone in the order of `PowerZone` and `PowerState`.
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuraPowerStates(u32);
+
+impl Default for AuraPowerStates {
+    /// Everything lit in every state
+    fn default() -> Self {
+        Self((1 << (PowerZone::ALL.len() * PowerState::ALL.len())) - 1)
+    }
+}
+
+impl AuraPowerStates {
+    #[inline]
+    fn bit(zone: PowerZone, state: PowerState) -> u32 {
+        1 << (zone as u32 * PowerState::ALL.len() as u32 + state as u32)
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn get(&self, zone: PowerZone, state: PowerState) -> bool {
+        self.0 & Self::bit(zone, state) != 0
+    }
+
+    pub fn set(&mut self, zone: PowerZone, state: PowerState, enabled: bool) {
+        if enabled {
+            self.0 |= Self::bit(zone, state);
+        } else {
+            self.0 &= !Self::bit(zone, state);
+        }
+    }
+
+    /// The packet for keyboards which power each zone separately (0x19b6)
+    pub fn to_zone_packet(&self) -> [u8; LED_MSG_LEN] {
+        // Bit positions used by the device for each zone, in `PowerState` order
+        const BITS: [(PowerZone, [u32; 4]); 5] = [
+            (PowerZone::Logo, [0, 2, 4, 6]),
+            (PowerZone::Keyboard, [1, 3, 5, 7]),
+            (PowerZone::Lightbar, [9, 10, 11, 12]),
+            (PowerZone::Lid, [17, 18, 19, 20]),
+            (PowerZone::RearGlow, [25, 26, 27, 28]),
+        ];
+        let mut flags = 0u32;
+        for (zone, bits) in BITS.iter() {
+            for (state, bit) in PowerState::ALL.iter().zip(bits.iter()) {
+                if self.get(*zone, *state) {
+                    flags |= 1 << bit;
+                }
+            }
+        }
+        aura_power_zone_bytes(flags)
+    }
+
+    /// The packet for older keyboards, these can only switch the keyboard while
+    /// awake and the sleep animation
+    pub fn to_keyboard_packet(&self) -> [u8; LED_MSG_LEN] {
+        let awake = self.get(PowerZone::Keyboard, PowerState::Awake);
+        let sleep = self.get(PowerZone::Keyboard, PowerState::Sleep);
+        match (awake, sleep) {
+            (true, true) => LED_AWAKE_ON_SLEEP_ON,
+            (true, false) => LED_AWAKE_ON_SLEEP_OFF,
+            (false, true) => LED_AWAKE_OFF_SLEEP_ON,
+            (false, false) => LED_AWAKE_OFF_SLEEP_OFF,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuraPowerStates, PowerState, PowerZone};
+    use crate::usb::{LED_AWAKE_ON_SLEEP_OFF, LED_AWAKE_ON_SLEEP_ON};
+
+    #[test]
+    fn zone_packet() {
+        let mut states = AuraPowerStates::default();
+        assert_eq!(
+            &states.to_zone_packet()[..8],
+            &[0x5d, 0xbd, 0x01, 0xff, 0x1e, 0x1e, 0x1e, 0xff]
+        );
+
+        for state in PowerState::ALL.iter() {
+            states.set(PowerZone::Lightbar, *state, false);
+        }
+        states.set(PowerZone::Keyboard, PowerState::Sleep, false);
+        assert_eq!(
+            &states.to_zone_packet()[..8],
+            &[0x5d, 0xbd, 0x01, 0xdf, 0x00, 0x1e, 0x1e, 0xff]
+        );
+        assert!(states.get(PowerZone::Keyboard, PowerState::Awake));
+        assert!(!states.get(PowerZone::Lightbar, PowerState::Awake));
+    }
+
+    #[test]
+    fn keyboard_packet() {
+        let mut states = AuraPowerStates::default();
+        assert_eq!(states.to_keyboard_packet(), LED_AWAKE_ON_SLEEP_ON);
+        // Other zones don't exist on these keyboards
+        states.set(PowerZone::Lightbar, PowerState::Sleep, false);
+        assert_eq!(states.to_keyboard_packet(), LED_AWAKE_ON_SLEEP_ON);
+        states.set(PowerZone::Keyboard, PowerState::Sleep, false);
+        assert_eq!(states.to_keyboard_packet(), LED_AWAKE_ON_SLEEP_OFF);
+    }
+}
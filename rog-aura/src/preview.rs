@@ -0,0 +1,414 @@
+//! Rendering of key colours to images and terminal art, so that effects can
+//! be seen without sitting at the keyboard. The builtin modes run on the
+//! keyboard itself, so `approximate()` only paints a likeness of them.
+
+use std::convert::TryFrom;
+use std::f32::consts::PI;
+use std::io::Write;
+use std::time::Duration;
+
+use pix::{rgb::SRgb8, Raster};
+
+use crate::{
+    error::Error, layouts::KeyboardLayout, AuraEffect, AuraModeNum, AuraZone, Colour, Direction,
+    Hsv, KeyColourArray, Speed,
+};
+
+/// The colour around and between keys
+pub const BACKGROUND: Colour = Colour(24, 24, 24);
+
+/// An RGB image of the keys of a keyboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardImage {
+    pub width: u32,
+    pub height: u32,
+    /// Three bytes per pixel, row by row from the top left
+    pub pixels: Vec<u8>,
+}
+
+impl KeyboardImage {
+    /// Draw each key of `layout` as a block of its colour in `colours`, at
+    /// `scale` pixels per key unit. Keys are drawn at their physical position
+    /// with a gap on the right and bottom edges.
+    pub fn render(layout: &KeyboardLayout, colours: &KeyColourArray, scale: u32) -> Self {
+        let scale = scale.max(1);
+        let (w, h) = layout.extent();
+        let width = (w * scale as f32).ceil() as u32;
+        let height = (h * scale as f32).ceil() as u32;
+        let mut image = Self {
+            width,
+            height,
+            pixels: [BACKGROUND.0, BACKGROUND.1, BACKGROUND.2].repeat((width * height) as usize),
+        };
+
+        let gap = if scale > 1 { (scale / 8).max(1) } else { 0 };
+        let to_px = |units: f32| (units * scale as f32).round() as u32;
+        for pos in &layout.keys {
+            let colour = colours
+                .colour_in(layout, pos.key)
+                .unwrap_or(Colour(0, 0, 0));
+            let (x, y) = pos.position;
+            let (x0, y0) = (to_px(x), to_px(y));
+            let x1 = to_px(x + pos.width).saturating_sub(gap).min(width);
+            let y1 = to_px(y + 1.0).saturating_sub(gap).min(height);
+            for py in y0..y1 {
+                for px in x0..x1 {
+                    image.put(px, py, colour);
+                }
+            }
+        }
+        image
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, colour: Colour) {
+        let idx = ((y * self.width + x) * 3) as usize;
+        self.pixels[idx..idx + 3].copy_from_slice(&[colour.0, colour.1, colour.2]);
+    }
+
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Colour> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = ((y * self.width + x) * 3) as usize;
+        Some(Colour(
+            self.pixels[idx],
+            self.pixels[idx + 1],
+            self.pixels[idx + 2],
+        ))
+    }
+
+    /// Truecolour ANSI art of the image, one character for each pair of pixel
+    /// rows drawn with a half block. Each line ends by resetting the colours.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for y in (0..self.height).step_by(2) {
+            let mut last = None;
+            for x in 0..self.width {
+                let top = self.pixel(x, y).unwrap_or(BACKGROUND);
+                let bottom = self.pixel(x, y + 1).unwrap_or(BACKGROUND);
+                if last != Some((top, bottom)) {
+                    out.push_str(&format!(
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        top.0, top.1, top.2, bottom.0, bottom.1, bottom.2
+                    ));
+                    last = Some((top, bottom));
+                }
+                out.push('▀');
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// The number of lines `to_ansi()` prints
+    #[inline]
+    pub fn ansi_lines(&self) -> u32 {
+        self.height.div_ceil(2)
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let raster = Raster::<SRgb8>::with_u8_buffer(self.width, self.height, self.pixels.clone());
+        png_pong::Encoder::new(writer)
+            .into_step_enc()
+            .still(&raster)
+            .map_err(|err| Error::Encode(err.to_string()))
+    }
+}
+
+/// Write `frames` as a looping GIF, showing each frame for `delay`. All frames
+/// should be the same size.
+pub fn write_gif<W: Write>(
+    frames: &[KeyboardImage],
+    delay: Duration,
+    writer: W,
+) -> Result<(), Error> {
+    let first = frames
+        .first()
+        .ok_or_else(|| Error::Encode("no frames to write".into()))?;
+    let size = |image: &KeyboardImage| -> Result<(u16, u16), Error> {
+        match (
+            u16::try_from(image.width).ok(),
+            u16::try_from(image.height).ok(),
+        ) {
+            (Some(w), Some(h)) => Ok((w, h)),
+            _ => Err(Error::Encode("image is too large for a GIF".into())),
+        }
+    };
+    let (width, height) = size(first)?;
+    let encode = |err: gif::EncodingError| Error::Encode(err.to_string());
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(encode)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(encode)?;
+    // GIF delays are in hundredths of a second
+    let delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for image in frames {
+        let (width, height) = size(image)?;
+        let mut frame = gif::Frame::from_rgb_speed(width, height, &image.pixels, 10);
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(encode)?;
+    }
+    Ok(())
+}
+
+/// Seconds for one cycle of an animated builtin mode
+#[inline]
+fn period(speed: Speed) -> f32 {
+    match speed {
+        Speed::Low => 4.0,
+        Speed::Med => 2.0,
+        Speed::High => 1.0,
+    }
+}
+
+/// Is a key at `x` in the zone, the four zones split the keyboard from left
+/// to right
+#[inline]
+fn in_zone(zone: AuraZone, x: f32, width: f32) -> bool {
+    let quarter = (x / width * 4.0).floor() as i32;
+    match zone {
+        AuraZone::None => true,
+        AuraZone::One => quarter <= 0,
+        AuraZone::Two => quarter == 1,
+        AuraZone::Three => quarter == 2,
+        AuraZone::Four => quarter >= 3,
+    }
+}
+
+/// A cheap repeatable hash, for the random looking modes
+#[inline]
+fn hash(a: u32, b: u32) -> u32 {
+    let mut x = a.wrapping_mul(0x9e37_79b9) ^ b.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2c1b_3c6d);
+    x ^ (x >> 12)
+}
+
+/// Paint a likeness of `effect` as shown at `time` after it was set. Only the
+/// keys in the zone of the effect are painted, so each effect of a multizone
+/// mode can be painted in turn. The keypress modes show a press of the middle
+/// key once each cycle.
+pub fn approximate(
+    effect: &AuraEffect,
+    layout: &KeyboardLayout,
+    time: Duration,
+    colours: &mut KeyColourArray,
+) {
+    let (w, h) = layout.extent();
+    let cycles = time.as_secs_f32() / period(effect.speed);
+    let phase = cycles.fract();
+    let cycle = cycles as u32;
+    // The key closest to the middle, where the keypress modes start from
+    let middle = layout
+        .keys
+        .iter()
+        .map(|pos| pos.centre())
+        .min_by(|a, b| {
+            let dist = |(x, y): (f32, f32)| (x - w / 2.0).powi(2) + (y - h / 2.0).powi(2);
+            dist(*a)
+                .partial_cmp(&dist(*b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or((w / 2.0, h / 2.0));
+    let c1 = effect.colour1;
+
+    for (idx, pos) in layout.keys.iter().enumerate() {
+        let (x, y) = pos.centre();
+        if !in_zone(effect.zone, x, w) {
+            continue;
+        }
+        let colour = match effect.mode {
+            AuraModeNum::Static => c1,
+            AuraModeNum::Breathe => {
+                let level = (phase * 2.0 * PI).sin().abs();
+                if phase < 0.5 { c1 } else { effect.colour2 }.scale(level)
+            }
+            AuraModeNum::Strobe => Colour::from_hsv(Hsv {
+                h: phase * 360.0,
+                s: 1.0,
+                v: 1.0,
+            }),
+            AuraModeNum::Rainbow => {
+                let along = match effect.direction {
+                    Direction::Right => x / w,
+                    Direction::Left => 1.0 - x / w,
+                    Direction::Down => y / h,
+                    Direction::Up => 1.0 - y / h,
+                };
+                Colour::from_hsv(Hsv {
+                    h: (along - phase) * 360.0,
+                    s: 1.0,
+                    v: 1.0,
+                })
+            }
+            AuraModeNum::Star => {
+                let roll = hash(idx as u32, cycle);
+                // A fifth of the keys are lit each cycle
+                if roll % 100 < 20 {
+                    let star = if roll & 0x100 == 0 {
+                        c1
+                    } else {
+                        effect.colour2
+                    };
+                    star.scale((phase * PI).sin())
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Rain => {
+                let column = x.floor() as u32;
+                let offset = (hash(column, 0) % 1000) as f32 / 1000.0;
+                let drop = (phase + offset).fract() * (h + 2.0);
+                let behind = drop - y;
+                if (0.0..2.0).contains(&behind) {
+                    Colour::from_hsv(Hsv {
+                        h: (hash(column, 1) % 3) as f32 * 120.0 + 180.0,
+                        s: 1.0,
+                        v: 1.0 - behind / 2.0,
+                    })
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Highlight => {
+                if (x - middle.0).abs() < 0.5 && (y - middle.1).abs() < 0.5 {
+                    c1.scale(1.0 - phase)
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Laser => {
+                if (y - middle.1).abs() < 0.5 && (x - middle.0).abs() < phase * w {
+                    c1.scale(1.0 - phase)
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Ripple => {
+                let dist = ((x - middle.0).powi(2) + (y - middle.1).powi(2)).sqrt();
+                let offset = (dist - phase * w.max(h) / 2.0).abs();
+                if offset < 1.0 {
+                    c1.scale((1.0 - offset) * (1.0 - phase))
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Pulse => c1.scale(1.0 - phase),
+            AuraModeNum::Comet => {
+                let behind = phase * (w + 4.0) - x;
+                if (0.0..4.0).contains(&behind) {
+                    c1.scale(1.0 - behind / 4.0)
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+            AuraModeNum::Flash => {
+                if (phase * (w + 4.0) - 2.0 - x).abs() < 1.5 {
+                    c1
+                } else {
+                    Colour(0, 0, 0)
+                }
+            }
+        };
+        colours.set_in(layout, pos.key, colour.0, colour.1, colour.2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{approximate, write_gif, KeyboardImage, BACKGROUND};
+    use crate::{
+        layouts::KeyboardLayout, AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Key,
+        KeyColourArray,
+    };
+
+    #[test]
+    fn keys_are_drawn_in_place() {
+        let layout = KeyboardLayout::default();
+        let mut colours = KeyColourArray::new();
+        colours.set_in(&layout, Key::Esc, 255, 0, 0);
+        let image = KeyboardImage::render(&layout, &colours, 8);
+        let (w, h) = layout.extent();
+        assert_eq!(image.width, (w * 8.0) as u32);
+        assert_eq!(image.height, (h * 8.0) as u32);
+        assert_eq!(
+            image.pixels.len(),
+            (image.width * image.height * 3) as usize
+        );
+
+        // Esc is at 0, 1 and the gap is on the right and bottom
+        assert_eq!(image.pixel(0, 8), Some(Colour(255, 0, 0)));
+        assert_eq!(image.pixel(6, 14), Some(Colour(255, 0, 0)));
+        assert_eq!(image.pixel(7, 8), Some(BACKGROUND));
+        // Unlit keys are black, the corner above Esc has no key
+        assert_eq!(image.pixel(8, 8), Some(BACKGROUND));
+        assert_eq!(image.pixel(16, 8), Some(Colour(0, 0, 0)));
+        assert_eq!(image.pixel(0, 0), Some(BACKGROUND));
+        assert_eq!(image.pixel(image.width, 0), None);
+    }
+
+    #[test]
+    fn ansi_has_a_line_per_two_rows() {
+        let layout = KeyboardLayout::default();
+        let image = KeyboardImage::render(&layout, &KeyColourArray::new(), 4);
+        let ansi = image.to_ansi();
+        assert_eq!(ansi.lines().count() as u32, image.ansi_lines());
+        assert!(ansi.starts_with("\x1b[38;2;24;24;24m\x1b[48;2;24;24;24m"));
+        assert!(ansi.lines().all(|line| line.ends_with("\x1b[0m")));
+    }
+
+    #[test]
+    fn encodes_png_and_gif() {
+        let layout = KeyboardLayout::default();
+        let image = KeyboardImage::render(&layout, &KeyColourArray::new(), 4);
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        let mut gif = Vec::new();
+        write_gif(&[image.clone(), image], Duration::from_millis(40), &mut gif).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert!(write_gif(&[], Duration::from_millis(40), &mut gif).is_err());
+    }
+
+    #[test]
+    fn approximations() {
+        let layout = KeyboardLayout::default();
+        let mut effect = AuraEffect::default_with_mode(AuraModeNum::Static);
+        effect.colour1 = Colour(0, 255, 0);
+        let mut colours = KeyColourArray::new();
+        approximate(&effect, &layout, Duration::from_secs(3), &mut colours);
+        assert!(layout
+            .keys
+            .iter()
+            .all(|pos| colours.colour_in(&layout, pos.key) == Some(Colour(0, 255, 0))));
+
+        // Zones split the keyboard left to right
+        let mut colours = KeyColourArray::new();
+        effect.zone = AuraZone::Four;
+        approximate(&effect, &layout, Duration::from_secs(0), &mut colours);
+        assert_eq!(colours.colour_in(&layout, Key::Esc), Some(Colour(0, 0, 0)));
+        assert_eq!(
+            colours.colour_in(&layout, Key::Del),
+            Some(Colour(0, 255, 0))
+        );
+
+        // Rainbow hues move along the direction
+        let mut effect = AuraEffect::default_with_mode(AuraModeNum::Rainbow);
+        effect.direction = Direction::Right;
+        let mut colours = KeyColourArray::new();
+        approximate(&effect, &layout, Duration::from_secs(0), &mut colours);
+        assert_ne!(
+            colours.colour_in(&layout, Key::Tab),
+            colours.colour_in(&layout, Key::PgUp)
+        );
+        // Q and A share a column
+        assert_eq!(
+            colours.colour_in(&layout, Key::Q),
+            colours.colour_in(&layout, Key::A)
+        );
+    }
+}
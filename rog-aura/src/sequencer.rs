@@ -1,48 +1,160 @@
+use std::{fs::OpenOptions, io::Read, path::Path, time::Duration};
+
 use serde_derive::{Deserialize, Serialize};
 
-use crate::error::Error;
+use crate::{error::Error, layouts::KeyboardLayout, Colour, Key, KeyColourArray};
+
+/// The curve used to tween between two keyframes
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
 
-/// All the possible AniMe actions that can be used. The enum is intended to be
+impl Easing {
+    /// Map a linear progress `t` in the range 0.0-1.0 on to the curve
+    #[inline]
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+fn default_easing() -> Easing {
+    Easing::Linear
+}
+
+/// The colour a single key should reach in a `Keyframe`
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct KeyColour {
+    pub key: Key,
+    pub colour: Colour,
+}
+
+/// A full keyboard state to tween towards from the previous state
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Keyframe {
+    /// Time taken to tween from the previous state to this one
+    pub transition: Duration,
+    /// Time to hold this state once it is reached
+    pub hold: Duration,
+    #[serde(default = "default_easing")]
+    pub easing: Easing,
+    /// Colour for all keys not listed in `keys`. If `None` those keys keep
+    /// their previous colour
+    #[serde(default)]
+    pub base: Option<Colour>,
+    #[serde(default)]
+    pub keys: Vec<KeyColour>,
+}
+
+/// All the possible per-key actions that can be used. The enum is intended to be
 /// used in a array allowing the user to cycle through a series of actions.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum ActionData {
-    Static,
+    /// Tween all keys to a new state
+    Keyframe(Keyframe),
+    /// Hold the current state
+    Pause(Duration),
 }
 
-/// An optimised precomputed set of actions that the user can cycle through
-#[derive(Debug, Deserialize, Serialize, Default)]
-pub struct Sequences(Vec<ActionData>);
+/// A timeline of per-key actions which can be played back as frames
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct Sequences {
+    /// Restart from the first action after the last finishes
+    #[serde(default)]
+    pub looping: bool,
+    actions: Vec<ActionData>,
+}
 
 impl Sequences {
     #[inline]
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            looping: false,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Load a sequence from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| Error::Io(path.to_string_lossy().to_string(), e))?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)
+            .map_err(|e| Error::Io(path.to_string_lossy().to_string(), e))?;
+        toml::from_str(&buf).map_err(|e| Error::ParseSequence(e.to_string()))
     }
 
-    /// Use a base `AnimeAction` to generate the precomputed data and insert in to
-    /// the run buffer
+    /// Insert an action in to the run buffer at this position. The `index`
+    /// may be at most the number of actions, to append.
     #[inline]
-    pub fn insert(&mut self, _index: usize) -> Result<(), Error> {
+    pub fn insert(&mut self, index: usize, action: ActionData) -> Result<(), Error> {
+        if index > self.actions.len() {
+            return Err(Error::InsertIndex(index, self.actions.len()));
+        }
+        self.actions.insert(index, action);
         Ok(())
     }
 
+    /// Append an action to the end of the run buffer
+    #[inline]
+    pub fn push(&mut self, action: ActionData) {
+        self.actions.push(action);
+    }
+
     /// Remove an item at this position from the run buffer. If the `index` supplied
     /// is not in range then `None` is returned, otherwise the `ActionData` at that location
     /// is yeeted and returned.
     #[inline]
     pub fn remove_item(&mut self, index: usize) -> Option<ActionData> {
-        if index < self.0.len() {
-            return Some(self.0.remove(index));
+        if index < self.actions.len() {
+            return Some(self.actions.remove(index));
         }
         None
     }
 
-    pub fn iter(&self) -> ActionIterator {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn iter(&self) -> ActionIterator<'_> {
         ActionIterator {
             actions: self,
             next_idx: 0,
         }
     }
+
+    /// Play the sequence back as a series of `KeyColourArray`, one per frame at
+    /// `fps`. Keys are mapped to packets with `layout`. All keys start unlit.
+    pub fn frames<'a>(&'a self, layout: &'a KeyboardLayout, fps: u32) -> FrameIterator<'a> {
+        FrameIterator {
+            sequence: self,
+            layout,
+            fps: fps.max(1),
+            state: vec![[0.0; 3]; layout.keys.len()],
+            from: vec![[0.0; 3]; layout.keys.len()],
+            action_idx: 0,
+            frame_idx: 0,
+            yielded_this_pass: false,
+        }
+    }
 }
 
 /// Iteractor helper for iterating over all the actions in `Sequences`
@@ -56,7 +168,7 @@ impl<'a> Iterator for ActionIterator<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<&'a ActionData> {
-        if self.next_idx == self.actions.0.len() {
+        if self.next_idx == self.actions.actions.len() {
             self.next_idx = 0;
             return None;
         }
@@ -64,6 +176,195 @@ impl<'a> Iterator for ActionIterator<'a> {
         let current = self.next_idx;
         self.next_idx += 1;
 
-        Some(&self.actions.0[current])
+        Some(&self.actions.actions[current])
+    }
+}
+
+/// Generates the frames of a `Sequences` at a fixed framerate
+pub struct FrameIterator<'a> {
+    sequence: &'a Sequences,
+    layout: &'a KeyboardLayout,
+    fps: u32,
+    /// Current colour of each key in `layout.keys`
+    state: Vec<[f32; 3]>,
+    /// Colour of each key when the current action started
+    from: Vec<[f32; 3]>,
+    action_idx: usize,
+    frame_idx: u32,
+    /// Guards against looping forever on a sequence with no frames
+    yielded_this_pass: bool,
+}
+
+impl<'a> FrameIterator<'a> {
+    /// The time each frame should be displayed for
+    #[inline]
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps as f64)
+    }
+
+    #[inline]
+    fn frames_for(&self, duration: Duration) -> u32 {
+        (duration.as_secs_f64() * self.fps as f64).round() as u32
+    }
+
+    fn target_for(&self, keyframe: &Keyframe) -> Vec<[f32; 3]> {
+        self.layout
+            .keys
+            .iter()
+            .zip(self.from.iter())
+            .map(|(pos, from)| {
+                let colour = keyframe
+                    .keys
+                    .iter()
+                    .find(|k| k.key == pos.key)
+                    .map(|k| k.colour)
+                    .or(keyframe.base);
+                match colour {
+                    Some(c) => [c.0 as f32, c.1 as f32, c.2 as f32],
+                    None => *from,
+                }
+            })
+            .collect()
+    }
+
+    fn build_frame(&self) -> KeyColourArray {
+        let mut frame = KeyColourArray::new();
+        for (pos, c) in self.layout.keys.iter().zip(self.state.iter()) {
+            frame.set_in(
+                self.layout,
+                pos.key,
+                c[0].round() as u8,
+                c[1].round() as u8,
+                c[2].round() as u8,
+            );
+        }
+        frame
+    }
+}
+
+impl<'a> Iterator for FrameIterator<'a> {
+    type Item = KeyColourArray;
+
+    fn next(&mut self) -> Option<KeyColourArray> {
+        loop {
+            if self.action_idx >= self.sequence.actions.len() {
+                if !self.sequence.looping || !self.yielded_this_pass {
+                    return None;
+                }
+                self.action_idx = 0;
+                self.yielded_this_pass = false;
+            }
+
+            match &self.sequence.actions[self.action_idx] {
+                ActionData::Keyframe(keyframe) => {
+                    // A keyframe always shows at least the final state once
+                    let tween = self.frames_for(keyframe.transition).max(1);
+                    let total = tween + self.frames_for(keyframe.hold);
+                    if self.frame_idx < total {
+                        let progress = if self.frame_idx < tween {
+                            keyframe
+                                .easing
+                                .apply((self.frame_idx + 1) as f32 / tween as f32)
+                        } else {
+                            1.0
+                        };
+                        let target = self.target_for(keyframe);
+                        for ((state, from), to) in
+                            self.state.iter_mut().zip(self.from.iter()).zip(target)
+                        {
+                            for i in 0..3 {
+                                state[i] = from[i] + (to[i] - from[i]) * progress;
+                            }
+                        }
+                        self.frame_idx += 1;
+                        self.yielded_this_pass = true;
+                        return Some(self.build_frame());
+                    }
+                }
+                ActionData::Pause(duration) => {
+                    if self.frame_idx < self.frames_for(*duration) {
+                        self.frame_idx += 1;
+                        self.yielded_this_pass = true;
+                        return Some(self.build_frame());
+                    }
+                }
+            }
+
+            self.action_idx += 1;
+            self.frame_idx = 0;
+            self.from = self.state.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ActionData, Easing, KeyColour, Keyframe, Sequences};
+    use crate::{layouts::KeyboardLayout, Colour, Key};
+
+    fn keyframe(transition: u64, colour: Colour) -> ActionData {
+        ActionData::Keyframe(Keyframe {
+            transition: Duration::from_millis(transition),
+            hold: Duration::from_millis(0),
+            easing: Easing::Linear,
+            base: None,
+            keys: vec![KeyColour {
+                key: Key::Esc,
+                colour,
+            }],
+        })
+    }
+
+    #[test]
+    fn tween_reaches_target() {
+        let layout = KeyboardLayout::default();
+        let mut seq = Sequences::new();
+        seq.push(keyframe(1000, Colour(200, 100, 0)));
+
+        let mut frames: Vec<_> = seq.frames(&layout, 10).collect();
+        assert_eq!(frames.len(), 10);
+        let (r, g, _) = frames[4].key(Key::Esc).unwrap();
+        assert_eq!((*r, *g), (100, 50));
+        let (r, g, b) = frames[9].key(Key::Esc).unwrap();
+        assert_eq!((*r, *g, *b), (200, 100, 0));
+        // Keys not in the keyframe are left alone
+        assert_eq!(*frames[9].key(Key::A).unwrap().0, 0);
+    }
+
+    #[test]
+    fn pause_and_loop() {
+        let layout = KeyboardLayout::default();
+        let mut seq = Sequences::new();
+        seq.push(keyframe(0, Colour(255, 0, 0)));
+        seq.push(ActionData::Pause(Duration::from_millis(200)));
+        assert_eq!(seq.frames(&layout, 10).count(), 3);
+
+        seq.looping = true;
+        assert_eq!(seq.frames(&layout, 10).take(7).count(), 7);
+    }
+
+    #[test]
+    fn empty_loop_ends() {
+        let layout = KeyboardLayout::default();
+        let mut seq = Sequences::new();
+        seq.looping = true;
+        seq.push(ActionData::Pause(Duration::from_millis(0)));
+        assert_eq!(seq.frames(&layout, 10).count(), 0);
+    }
+
+    #[test]
+    fn insert_in_range() {
+        let mut seq = Sequences::new();
+        assert!(seq.insert(1, keyframe(0, Colour(255, 0, 0))).is_err());
+        seq.insert(0, keyframe(0, Colour(255, 0, 0))).unwrap();
+        seq.insert(1, ActionData::Pause(Duration::from_millis(100)))
+            .unwrap();
+        seq.insert(0, ActionData::Pause(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(seq.len(), 3);
+        assert!(matches!(seq.iter().next(), Some(ActionData::Pause(_))));
+        assert!(seq.insert(4, keyframe(0, Colour(0, 0, 0))).is_err());
     }
 }
@@ -4,6 +4,15 @@ pub const LED_INIT3: [u8; 6] = [0x5d, 0x05, 0x20, 0x31, 0, 0x08];
 pub const LED_INIT4: &str = "^ASUS Tech.Inc."; // ^ == 0x5e
 pub const LED_INIT5: [u8; 6] = [0x5e, 0x05, 0x20, 0x31, 0, 0x08];
 
+/// The packets to wake the keyboard up for LED control, in order
+pub const LED_INIT: [&[u8]; 5] = [
+    &LED_INIT1,
+    LED_INIT2.as_bytes(),
+    &LED_INIT3,
+    LED_INIT4.as_bytes(),
+    &LED_INIT5,
+];
+
 // Only these two packets must be 17 bytes
 pub const LED_APPLY: [u8; 17] = [0x5d, 0xb4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 pub const LED_SET: [u8; 17] = [0x5d, 0xb5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
@@ -15,6 +24,30 @@ pub const fn aura_brightness_bytes(brightness: u8) -> [u8; 17] {
     ]
 }
 
+/// Writes out the power state packet for keyboards with separately powered
+/// zones, `flags` is the device bitfield of zones and states
+pub const fn aura_power_zone_bytes(flags: u32) -> [u8; 17] {
+    [
+        0x5d,
+        0xbd,
+        0x01,
+        (flags & 0xff) as u8,
+        ((flags >> 8) & 0xff) as u8,
+        ((flags >> 16) & 0xff) as u8,
+        ((flags >> 24) & 0xff) as u8,
+        0xff,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]
+}
+
 pub const LED_AWAKE_ON_SLEEP_OFF: [u8; 17] = [
     0x5d, 0xbd, 0x01, 0xcf, 0x17, 0x0b, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
 ];
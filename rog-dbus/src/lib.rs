@@ -10,7 +10,7 @@ pub mod zbus_rogbios;
 pub mod zbus_supported;
 
 use rog_anime::AnimePowerStates;
-use rog_aura::{AuraEffect, LedPowerStates};
+use rog_aura::{AuraEffect, LedBrightness, LedPowerStates};
 use rog_profiles::Profile;
 use std::sync::mpsc::{channel, Receiver};
 use zbus::{Connection, Result, SignalReceiver};
@@ -84,6 +84,7 @@ pub struct Signals {
     pub profile: Receiver<Profile>,
     pub led_mode: Receiver<AuraEffect>,
     pub led_power_state: Receiver<LedPowerStates>,
+    pub led_brightness: Receiver<LedBrightness>,
     pub anime_power_state: Receiver<AnimePowerStates>,
     pub charge: Receiver<u8>,
     pub bios_gsync: Receiver<bool>,
@@ -114,6 +115,11 @@ impl Signals {
                 proxies.led.connect_notify_power_states(tx)?;
                 rx
             },
+            led_brightness: {
+                let (tx, rx) = channel();
+                proxies.led.connect_notify_brightness(tx)?;
+                rx
+            },
             anime_power_state: {
                 let (tx, rx) = channel();
                 proxies.anime.connect_notify_power_states(tx)?;
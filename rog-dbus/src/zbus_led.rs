@@ -23,10 +23,27 @@ use std::sync::mpsc::Sender;
 
 use zbus::{dbus_proxy, Connection, Result};
 
-use rog_aura::{AuraEffect, KeyColourArray, LedBrightness, LedPowerStates};
+use rog_aura::{
+    AuraEffect, AuraPowerStates, KeyColourArray, LedBrightness, LedPowerStates, PowerState,
+    PowerZone,
+};
+use rog_profiles::Profile;
 
 const BLOCKING_TIME: u64 = 40; // 100ms = 10 FPS, max 50ms = 20 FPS, 40ms = 25 FPS
 
+/// The laptop keyboard, each LED device is also at `led_device_path`
+pub const LED_PATH: &str = "/org/asuslinux/Led";
+
+/// The object path of the LED device with this ID, as listed in
+/// `LedSupportedFunctions::devices`
+pub fn led_device_path(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}/{}", LED_PATH, name)
+}
+
 #[dbus_proxy(
     interface = "org.asuslinux.Daemon",
     default_path = "/org/asuslinux/Led"
@@ -50,16 +67,84 @@ trait Daemon {
     /// SetLedMode method
     fn set_led_mode(&self, effect: &AuraEffect) -> zbus::Result<()>;
 
+    /// InitPerKey method
+    fn init_per_key(&self) -> zbus::Result<()>;
+
+    /// SetPerKey method
+    fn set_per_key(&self, effect: &[Vec<u8>]) -> zbus::Result<()>;
+
     /// SetAwakeEnabled method
     fn set_awake_enabled(&self, enabled: bool) -> zbus::Result<()>;
 
+    /// SetPowerState method
+    fn set_power_state(
+        &self,
+        zone: PowerZone,
+        state: PowerState,
+        enabled: bool,
+    ) -> zbus::Result<()>;
+
     /// SetSleepEnabled method
     fn set_sleep_enabled(&self, enabled: bool) -> zbus::Result<()>;
 
+    /// AddScheduleEntry method
+    fn add_schedule_entry(
+        &self,
+        start: &str,
+        end: &str,
+        effect: &AuraEffect,
+        brightness: LedBrightness,
+    ) -> zbus::Result<()>;
+
+    /// RemoveScheduleEntry method
+    fn remove_schedule_entry(&self, index: u32) -> zbus::Result<()>;
+
+    /// SetScheduleOverride method
+    fn set_schedule_override(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// SetIdleTimeout method
+    fn set_idle_timeout(
+        &self,
+        timeout_ac: u32,
+        timeout_battery: u32,
+        fade: bool,
+    ) -> zbus::Result<()>;
+
+    /// SetBatteryEffects method
+    fn set_battery_effects(&self, rules: &str) -> zbus::Result<()>;
+
+    /// SetProfileMode method
+    fn set_profile_mode(&self, profile: Profile, effect: &AuraEffect) -> zbus::Result<()>;
+
+    /// RemoveProfileMode method
+    fn remove_profile_mode(&self, profile: Profile) -> zbus::Result<()>;
+
+    /// SetProfileModesEnabled method
+    fn set_profile_modes_enabled(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// Multizone method, the settings as JSON
+    fn multizone(&self) -> zbus::Result<String>;
+
+    /// Schedule method, the entries as JSON
+    fn schedule(&self) -> zbus::Result<String>;
+
+    /// IdleTimeout method, the settings as JSON
+    fn idle_timeout(&self) -> zbus::Result<String>;
+
+    /// BatteryEffects method, the rules as JSON
+    fn battery_effects(&self) -> zbus::Result<String>;
+
+    /// ProfileModes method, the bound modes as JSON
+    fn profile_modes(&self) -> zbus::Result<String>;
+
     /// NotifyLed signal
     #[dbus_proxy(signal)]
     fn notify_led(&self, data: AuraEffect) -> zbus::Result<()>;
 
+    /// NotifyBrightness signal
+    #[dbus_proxy(signal)]
+    fn notify_brightness(&self, data: LedBrightness) -> zbus::Result<()>;
+
     #[dbus_proxy(signal)]
     fn notify_power_states(&self, data: LedPowerStates) -> zbus::Result<()>;
 
@@ -80,6 +165,15 @@ trait Daemon {
 
     #[dbus_proxy(property)]
     fn sleep_enabled(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn per_key_active(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn power_states(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn schedule_override(&self) -> zbus::Result<bool>;
 }
 
 pub struct LedProxy<'a>(DaemonProxy<'a>);
@@ -90,6 +184,16 @@ impl<'a> LedProxy<'a> {
         Ok(LedProxy(DaemonProxy::new(conn)?))
     }
 
+    /// Control the LED device with this ID rather than the laptop keyboard
+    #[inline]
+    pub fn new_for_device(conn: &Connection, id: &str) -> Result<LedProxy<'static>> {
+        Ok(LedProxy(DaemonProxy::new_for_owned(
+            conn.clone(),
+            crate::DBUS_NAME.to_string(),
+            led_device_path(id),
+        )?))
+    }
+
     #[inline]
     pub fn proxy(&self) -> &DaemonProxy<'a> {
         &self.0
@@ -120,6 +224,17 @@ impl<'a> LedProxy<'a> {
         Ok(())
     }
 
+    /// Set if a zone is lit in a power state
+    #[inline]
+    pub fn set_power_state(&self, zone: PowerZone, state: PowerState, enabled: bool) -> Result<()> {
+        self.0.set_power_state(zone, state, enabled)
+    }
+
+    #[inline]
+    pub fn power_states(&self) -> Result<AuraPowerStates> {
+        Ok(AuraPowerStates::from_bits(self.0.power_states()?))
+    }
+
     #[inline]
     pub fn next_led_mode(&self) -> Result<()> {
         self.0.next_led_mode()
@@ -155,26 +270,102 @@ impl<'a> LedProxy<'a> {
         self.0.sleep_enabled()
     }
 
+    /// Switch to `effect` and `brightness` from `start` until `end`, as 24
+    /// hour `HH:MM` local times
+    #[inline]
+    pub fn add_schedule_entry(
+        &self,
+        start: &str,
+        end: &str,
+        effect: &AuraEffect,
+        brightness: LedBrightness,
+    ) -> Result<()> {
+        self.0.add_schedule_entry(start, end, effect, brightness)
+    }
+
+    #[inline]
+    pub fn remove_schedule_entry(&self, index: u32) -> Result<()> {
+        self.0.remove_schedule_entry(index)
+    }
+
+    /// Keep the current LED settings until the next schedule boundary
+    #[inline]
+    pub fn set_schedule_override(&self, enabled: bool) -> Result<()> {
+        self.0.set_schedule_override(enabled)
+    }
+
+    /// The schedule entries as JSON
+    #[inline]
+    pub fn schedule(&self) -> Result<String> {
+        self.0.schedule()
+    }
+
+    #[inline]
+    pub fn schedule_override(&self) -> Result<bool> {
+        self.0.schedule_override()
+    }
+
+    /// Turn the keyboard backlight off after this many seconds without input,
+    /// 0 to never turn it off
+    #[inline]
+    pub fn set_idle_timeout(
+        &self,
+        timeout_ac: u32,
+        timeout_battery: u32,
+        fade: bool,
+    ) -> Result<()> {
+        self.0.set_idle_timeout(timeout_ac, timeout_battery, fade)
+    }
+
+    /// The idle timeout settings as JSON
+    #[inline]
+    pub fn idle_timeout(&self) -> Result<String> {
+        self.0.idle_timeout()
+    }
+
+    /// Set the modes shown while the battery is in a state, as a JSON array of
+    /// `{"condition": <CONDITION>, "effect": <AuraEffect>}`
+    #[inline]
+    pub fn set_battery_effects(&self, rules: &str) -> Result<()> {
+        self.0.set_battery_effects(rules)
+    }
+
+    /// The battery rules as JSON
+    #[inline]
+    pub fn battery_effects(&self) -> Result<String> {
+        self.0.battery_effects()
+    }
+
+    /// Switch to `effect` whenever the platform profile changes to `profile`
+    #[inline]
+    pub fn set_profile_mode(&self, profile: Profile, effect: &AuraEffect) -> Result<()> {
+        self.0.set_profile_mode(profile, effect)
+    }
+
+    #[inline]
+    pub fn remove_profile_mode(&self, profile: Profile) -> Result<()> {
+        self.0.remove_profile_mode(profile)
+    }
+
+    #[inline]
+    pub fn set_profile_modes_enabled(&self, enabled: bool) -> Result<()> {
+        self.0.set_profile_modes_enabled(enabled)
+    }
+
+    /// The modes bound to each profile as JSON
+    #[inline]
+    pub fn profile_modes(&self) -> Result<String> {
+        self.0.profile_modes()
+    }
+
     /// Write a single colour block.
     ///
     /// Intentionally blocks for 10ms after sending to allow the block to
     /// be written to the keyboard EC. This should not be async.
     #[inline]
     pub fn set_per_key(&self, key_colour_array: &KeyColourArray) -> Result<()> {
-        let group = key_colour_array.get();
-        let mut vecs = Vec::with_capacity(group.len());
-        for v in group {
-            vecs.push(v.to_vec());
-        }
-        // TODO: let mode = AuraModes::PerKey(vecs);
-        // self.set_led_mode(&mode)?;
-
+        self.0.set_per_key(&key_colour_array.to_vecs())?;
         std::thread::sleep(std::time::Duration::from_millis(BLOCKING_TIME));
-
-        // if self.stop.load(Ordering::Relaxed) {
-        //     println!("Keyboard backlight was changed, exiting");
-        //     std::process::exit(1)
-        // }
         Ok(())
     }
 
@@ -182,9 +373,12 @@ impl<'a> LedProxy<'a> {
     /// the keyboard LED EC in the correct mode
     #[inline]
     pub fn init_effect(&self) -> Result<()> {
-        // TODO: let mode = AuraModes::PerKey(vec![vec![]]);
-        // self.0.set_led_mode(&serde_json::to_string(&mode).unwrap())
-        Ok(())
+        self.0.init_per_key()
+    }
+
+    #[inline]
+    pub fn per_key_active(&self) -> Result<bool> {
+        self.0.per_key_active()
     }
 
     #[inline]
@@ -196,6 +390,15 @@ impl<'a> LedProxy<'a> {
         })
     }
 
+    #[inline]
+    pub fn connect_notify_brightness(&self, send: Sender<LedBrightness>) -> zbus::fdo::Result<()> {
+        self.0.connect_notify_brightness(move |data| {
+            send.send(data)
+                .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?;
+            Ok(())
+        })
+    }
+
     #[inline]
     pub fn connect_notify_power_states(
         &self,
@@ -1,6 +1,6 @@
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 
-use rog_aura::AuraModeNum;
+use rog_aura::{AuraModeNum, PowerZone};
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 use zvariant_derive::Type;
@@ -34,6 +34,10 @@ pub struct LedSupportedFunctions {
     pub stock_led_modes: Vec<AuraModeNum>,
     pub multizone_led_mode: bool,
     pub per_key_led_mode: bool,
+    pub per_key_layout: String,
+    pub power_zones: Vec<PowerZone>,
+    /// IDs of the LED devices, the first is the laptop keyboard
+    pub devices: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Type, Debug)]
@@ -81,7 +85,10 @@ impl fmt::Display for LedSupportedFunctions {
         writeln!(f, "\tBrightness control: {}", self.brightness_set)?;
         writeln!(f, "\tStock LED modes: {:?}", self.stock_led_modes)?;
         writeln!(f, "\tMultizone LED mode: {}", self.multizone_led_mode)?;
-        writeln!(f, "\tPer key LED mode: {}", self.per_key_led_mode)
+        writeln!(f, "\tPer key LED mode: {}", self.per_key_led_mode)?;
+        writeln!(f, "\tPer key layout: {}", self.per_key_layout)?;
+        writeln!(f, "\tPower zones: {:?}", self.power_zones)?;
+        writeln!(f, "\tDevices: {:?}", self.devices)
     }
 }
 impl fmt::Display for RogBiosSupportedFunctions {